@@ -0,0 +1,20 @@
+use miette::{Context, IntoDiagnostic, Result};
+use std::env::args;
+use std::fs::read_to_string;
+use vdf_reader::vmt::Material;
+
+fn main() -> Result<()> {
+    let path = args().nth(1).expect("no path provided");
+    let raw = read_to_string(path)
+        .into_diagnostic()
+        .wrap_err("failed to read input")?;
+    let material = Material::load_from_str(&raw).wrap_err("failed to parse material")?;
+    println!("shader: {}", material.shader);
+    for parameter in material.parameters.iter() {
+        println!("{}: {:?}", parameter.name, parameter.value);
+    }
+    for proxy in &material.proxies {
+        println!("proxy: {}", proxy.name);
+    }
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! Steam's `appcache/appinfo.vdf`, containing the product info for every known app.
+//!
+//! The file starts with a header followed by a record for every app, each containing some
+//! metadata and the app info as binary KeyValues. Records are read lazily, the KeyValues of
+//! an app are only parsed when calling [`App::table`].
+
+use super::cursor::Cursor;
+use super::{with_offset, BinaryReader};
+use crate::entry::Table;
+use crate::Result;
+use std::borrow::Cow;
+
+/// The version of an appinfo file, determined by the magic number at the start of the file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AppInfoVersion {
+    /// Magic `0x07564427`
+    V27,
+    /// Magic `0x07564428`, adds the hash of the binary KeyValues
+    V28,
+    /// Magic `0x07564429`, keys are stored in a string table at the end of the file
+    V29,
+}
+
+impl AppInfoVersion {
+    pub fn magic(&self) -> u32 {
+        match self {
+            AppInfoVersion::V27 => 0x07564427,
+            AppInfoVersion::V28 => 0x07564428,
+            AppInfoVersion::V29 => 0x07564429,
+        }
+    }
+}
+
+impl TryFrom<u32> for AppInfoVersion {
+    type Error = u32;
+
+    fn try_from(magic: u32) -> Result<Self, Self::Error> {
+        Ok(match magic {
+            0x07564427 => AppInfoVersion::V27,
+            0x07564428 => AppInfoVersion::V28,
+            0x07564429 => AppInfoVersion::V29,
+            _ => return Err(magic),
+        })
+    }
+}
+
+/// The header of an appinfo file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AppInfoHeader {
+    pub version: AppInfoVersion,
+    pub universe: u32,
+}
+
+/// A parsed appinfo file.
+///
+/// Only the header and string table are read up front.
+pub struct AppInfo<'a> {
+    header: AppInfoHeader,
+    data: &'a [u8],
+    records_start: usize,
+    string_table: Option<Vec<Cow<'a, str>>>,
+}
+
+impl<'a> AppInfo<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(data, 0);
+        let magic = cursor.u32()?;
+        let Ok(version) = AppInfoVersion::try_from(magic) else {
+            return Cursor::new(data, 0).error(format!("unknown appinfo magic 0x{magic:08x}"));
+        };
+        let universe = cursor.u32()?;
+
+        let (data, string_table) = if version == AppInfoVersion::V29 {
+            let offset = cursor.u64()?;
+            let Some(offset) = usize::try_from(offset).ok().filter(|o| *o <= data.len()) else {
+                return cursor.error(format!("string table offset {offset} out of range"));
+            };
+            let mut strings = Cursor::new(data, offset);
+            let count = strings.u32()?;
+            let table = (0..count)
+                .map(|_| strings.string())
+                .collect::<Result<Vec<_>>>()?;
+            (&data[..offset], Some(table))
+        } else {
+            (data, None)
+        };
+
+        Ok(AppInfo {
+            header: AppInfoHeader { version, universe },
+            data,
+            records_start: cursor.position(),
+            string_table,
+        })
+    }
+
+    pub fn header(&self) -> AppInfoHeader {
+        self.header
+    }
+
+    /// The keys shared by all apps, only present for [`AppInfoVersion::V29`].
+    pub fn string_table(&self) -> Option<&[Cow<'a, str>]> {
+        self.string_table.as_deref()
+    }
+
+    /// Iterate over all apps in the file, without parsing their KeyValues.
+    pub fn apps(&self) -> AppIter<'_> {
+        AppIter {
+            cursor: Cursor::new(self.data, self.records_start),
+            version: self.header.version,
+            string_table: self.string_table.as_deref(),
+            done: false,
+        }
+    }
+
+    /// Find the app with the given id.
+    pub fn get(&self, app_id: u32) -> Result<Option<App<'_>>> {
+        for app in self.apps() {
+            let app = app?;
+            if app.app_id == app_id {
+                return Ok(Some(app));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A single app record, the KeyValues are parsed on demand by [`App::table`].
+#[derive(Clone, Debug)]
+pub struct App<'a> {
+    pub app_id: u32,
+    pub info_state: u32,
+    /// Unix timestamp of the last update
+    pub last_updated: u32,
+    pub pics_token: u64,
+    /// SHA-1 of the app info in text form
+    pub sha1: [u8; 20],
+    pub change_number: u32,
+    /// SHA-1 of the binary KeyValues, not present before [`AppInfoVersion::V28`]
+    pub binary_sha1: Option<[u8; 20]>,
+    data: &'a [u8],
+    offset: usize,
+    string_table: Option<&'a [Cow<'a, str>]>,
+}
+
+impl<'a> App<'a> {
+    /// The raw binary KeyValues of the app.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// A reader for the KeyValues of the app.
+    pub fn reader(&self) -> BinaryReader<'a> {
+        let reader = BinaryReader::new(self.data);
+        match self.string_table {
+            Some(string_table) => reader.with_string_table(string_table),
+            None => reader,
+        }
+    }
+
+    /// Parse the KeyValues of the app.
+    pub fn table(&self) -> Result<Table> {
+        Table::load(&mut self.reader()).map_err(|e| with_offset(e, self.offset))
+    }
+}
+
+/// Iterator over the apps in an appinfo file, created by [`AppInfo::apps`].
+pub struct AppIter<'a> {
+    cursor: Cursor<'a>,
+    version: AppInfoVersion,
+    string_table: Option<&'a [Cow<'a, str>]>,
+    done: bool,
+}
+
+impl<'a> AppIter<'a> {
+    fn read_app(&mut self) -> Result<Option<App<'a>>> {
+        if self.cursor.is_empty() {
+            return self.cursor.error("missing end of apps marker");
+        }
+        let app_id = self.cursor.u32()?;
+        if app_id == 0 {
+            return Ok(None);
+        }
+        let size = self.cursor.u32()? as usize;
+        let mut record = Cursor::new(self.cursor.slice(size)?, 0);
+        let start = self.cursor.position() - size;
+
+        let info_state = record.u32()?;
+        let last_updated = record.u32()?;
+        let pics_token = record.u64()?;
+        let sha1 = record.bytes()?;
+        let change_number = record.u32()?;
+        let binary_sha1 = match self.version {
+            AppInfoVersion::V27 => None,
+            AppInfoVersion::V28 | AppInfoVersion::V29 => Some(record.bytes()?),
+        };
+        let offset = start + record.position();
+        let data = record.slice(size - record.position())?;
+
+        Ok(Some(App {
+            app_id,
+            info_state,
+            last_updated,
+            pics_token,
+            sha1,
+            change_number,
+            binary_sha1,
+            data,
+            offset,
+            string_table: self.string_table,
+        }))
+    }
+}
+
+impl<'a> Iterator for AppIter<'a> {
+    type Item = Result<App<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_app();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Entry;
+
+    fn record(version: AppInfoVersion, app_id: u32, body: &[u8]) -> Vec<u8> {
+        let mut fields = Vec::new();
+        fields.extend(2u32.to_le_bytes());
+        fields.extend(1700000000u32.to_le_bytes());
+        fields.extend(42u64.to_le_bytes());
+        fields.extend([1; 20]);
+        fields.extend(app_id.to_le_bytes());
+        if version != AppInfoVersion::V27 {
+            fields.extend([2; 20]);
+        }
+        fields.extend(body);
+
+        let mut out = app_id.to_le_bytes().to_vec();
+        out.extend((fields.len() as u32).to_le_bytes());
+        out.extend(fields);
+        out
+    }
+
+    fn file(version: AppInfoVersion, records: &[Vec<u8>], strings: &[&str]) -> Vec<u8> {
+        let mut out = version.magic().to_le_bytes().to_vec();
+        out.extend(1u32.to_le_bytes());
+        let offset_position = out.len();
+        if version == AppInfoVersion::V29 {
+            out.extend(0u64.to_le_bytes());
+        }
+        for record in records {
+            out.extend(record);
+        }
+        out.extend(0u32.to_le_bytes());
+        if version == AppInfoVersion::V29 {
+            let offset = out.len() as u64;
+            out[offset_position..offset_position + 8].copy_from_slice(&offset.to_le_bytes());
+            out.extend((strings.len() as u32).to_le_bytes());
+            for string in strings {
+                out.extend(string.as_bytes());
+                out.push(0);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_v28() {
+        let body = b"\x00appinfo\0\x02appid\0\xb8\x01\0\0\x01name\0Team Fortress 2\0\x08\x08";
+        let data = file(
+            AppInfoVersion::V28,
+            &[
+                record(AppInfoVersion::V28, 440, body),
+                record(AppInfoVersion::V28, 570, b"\x08"),
+            ],
+            &[],
+        );
+        let appinfo = AppInfo::new(&data).unwrap();
+        assert_eq!(
+            AppInfoHeader {
+                version: AppInfoVersion::V28,
+                universe: 1
+            },
+            appinfo.header()
+        );
+
+        let ids: Vec<_> = appinfo.apps().map(|app| app.unwrap().app_id).collect();
+        assert_eq!(vec![440, 570], ids);
+
+        let app = appinfo.get(440).unwrap().unwrap();
+        assert_eq!(42, app.pics_token);
+        assert_eq!(Some([2; 20]), app.binary_sha1);
+        let table = app.table().unwrap();
+        let info = table.get("appinfo").and_then(Entry::as_table).unwrap();
+        assert_eq!(Some("440"), info.get("appid").and_then(Entry::as_str));
+        assert_eq!(
+            Some("Team Fortress 2"),
+            info.get("name").and_then(Entry::as_str)
+        );
+        assert!(appinfo.get(10).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_v29_string_table() {
+        let body = b"\x00\x00\0\0\0\x02\x01\0\0\0\xb8\x01\0\0\x08\x08";
+        let data = file(
+            AppInfoVersion::V29,
+            &[record(AppInfoVersion::V29, 440, body)],
+            &["appinfo", "appid"],
+        );
+        let appinfo = AppInfo::new(&data).unwrap();
+        assert_eq!(AppInfoVersion::V29, appinfo.header().version);
+        assert_eq!(2, appinfo.string_table().unwrap().len());
+
+        let table = appinfo.get(440).unwrap().unwrap().table().unwrap();
+        assert_eq!(
+            Some("440"),
+            table
+                .get("appinfo")
+                .and_then(|info| info.get("appid"))
+                .and_then(Entry::as_str)
+        );
+    }
+
+    #[test]
+    fn test_lazy() {
+        // a broken body doesn't prevent iterating over the records
+        let data = file(
+            AppInfoVersion::V27,
+            &[
+                record(AppInfoVersion::V27, 10, b"\x09broken"),
+                record(AppInfoVersion::V27, 20, b"\x08"),
+            ],
+            &[],
+        );
+        let appinfo = AppInfo::new(&data).unwrap();
+        let app = appinfo.get(20).unwrap().unwrap();
+        assert_eq!(None, app.binary_sha1);
+        assert!(app.table().unwrap().is_empty());
+
+        let error = appinfo.get(10).unwrap().unwrap().table().unwrap_err();
+        assert_eq!("unknown type 0x09 at offset 56", error.to_string());
+
+        let mut truncated = data.clone();
+        truncated.truncate(data.len() - 6);
+        let appinfo = AppInfo::new(&truncated).unwrap();
+        assert!(appinfo.apps().any(|app| app.is_err()));
+
+        assert!(AppInfo::new(b"\x27\x44\x56\x08\x01\0\0\0").is_err());
+    }
+}
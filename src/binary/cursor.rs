@@ -0,0 +1,69 @@
+use crate::error::BinaryError;
+use crate::Result;
+use std::borrow::Cow;
+
+/// Reads the fixed size fields of the binary container formats.
+pub(crate) struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8], position: usize) -> Self {
+        Cursor { data, position }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    /// The data after the current position.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position.min(self.data.len())..]
+    }
+
+    pub fn error<T>(&self, error: impl Into<String>) -> Result<T> {
+        Err(BinaryError::new(error, self.position).into())
+    }
+
+    pub fn slice(&mut self, length: usize) -> Result<&'a [u8]> {
+        match self
+            .data
+            .get(self.position..self.position.saturating_add(length))
+        {
+            Some(bytes) => {
+                self.position += length;
+                Ok(bytes)
+            }
+            None => self.error("unexpected end of data"),
+        }
+    }
+
+    pub fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self
+            .slice(N)?
+            .try_into()
+            .expect("slice has the right length"))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    pub fn string(&mut self) -> Result<Cow<'a, str>> {
+        let rest = self.remaining();
+        let Some(length) = rest.iter().position(|byte| *byte == 0) else {
+            return self.error("unterminated string");
+        };
+        self.position += length + 1;
+        Ok(String::from_utf8_lossy(&rest[..length]))
+    }
+}
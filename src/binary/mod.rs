@@ -0,0 +1,268 @@
+//! Binary KeyValues, as used by Steam for `shortcuts.vdf`, `appinfo.vdf` and various caches.
+//!
+//! Every entry starts with a type byte followed by the nul terminated key and the value,
+//! a group is ended by an [`BinaryType::End`] byte.
+//!
+//! The [`BinaryReader`] emits the same [`Event`]s as the text [`Reader`](crate::Reader),
+//! so binary data can be loaded with [`Table::load`] or deserialized with [`from_bytes`].
+//!
+//! Steam's `appinfo.vdf` and `packageinfo.vdf` containers are read with the [`appinfo`] and
+//! [`packageinfo`] modules.
+
+pub mod appinfo;
+pub(crate) mod cursor;
+pub mod packageinfo;
+mod reader;
+mod table;
+mod writer;
+
+use crate::entry::{Entry, ParseItem, Table};
+use crate::error::ParseStringError;
+use crate::event::{EntryEvent, GroupEndEvent, GroupStartEvent};
+use crate::{Event, Item, Result, VdfError};
+use logos::Span;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+
+pub use reader::BinaryReader;
+pub use table::{BinaryEntry, BinaryTable, BinaryTableIter};
+
+/// The type byte in front of every binary entry.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum BinaryType {
+    /// A nested group
+    Table = 0x00,
+    /// A nul terminated string
+    String = 0x01,
+    Int32 = 0x02,
+    Float32 = 0x03,
+    Pointer = 0x04,
+    /// A length prefixed UTF-16 string
+    WideString = 0x05,
+    /// Four bytes with the red, green, blue and alpha components
+    Color = 0x06,
+    Uint64 = 0x07,
+    /// The end of a group
+    End = 0x08,
+    Int64 = 0x0A,
+    /// An alternative end of group marker, used by some writers
+    AlternateEnd = 0x0B,
+}
+
+impl TryFrom<u8> for BinaryType {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x00 => BinaryType::Table,
+            0x01 => BinaryType::String,
+            0x02 => BinaryType::Int32,
+            0x03 => BinaryType::Float32,
+            0x04 => BinaryType::Pointer,
+            0x05 => BinaryType::WideString,
+            0x06 => BinaryType::Color,
+            0x07 => BinaryType::Uint64,
+            0x08 => BinaryType::End,
+            0x0A => BinaryType::Int64,
+            0x0B => BinaryType::AlternateEnd,
+            _ => return Err(value),
+        })
+    }
+}
+
+/// A value read from binary data, with its wire type.
+#[derive(Clone, PartialEq, Debug)]
+pub enum BinaryValue<'a> {
+    String(Cow<'a, str>),
+    /// A nul terminated string that isn't valid UTF-8, kept as the original bytes
+    Bytes(Cow<'a, [u8]>),
+    Int32(i32),
+    Float32(f32),
+    Pointer(u32),
+    WideString(String),
+    Color([u8; 4]),
+    Uint64(u64),
+    Int64(i64),
+}
+
+impl BinaryValue<'_> {
+    pub fn ty(&self) -> BinaryType {
+        match self {
+            BinaryValue::String(_) | BinaryValue::Bytes(_) => BinaryType::String,
+            BinaryValue::Int32(_) => BinaryType::Int32,
+            BinaryValue::Float32(_) => BinaryType::Float32,
+            BinaryValue::Pointer(_) => BinaryType::Pointer,
+            BinaryValue::WideString(_) => BinaryType::WideString,
+            BinaryValue::Color(_) => BinaryType::Color,
+            BinaryValue::Uint64(_) => BinaryType::Uint64,
+            BinaryValue::Int64(_) => BinaryType::Int64,
+        }
+    }
+
+    pub fn into_owned(self) -> BinaryValue<'static> {
+        match self {
+            BinaryValue::String(value) => BinaryValue::String(value.into_owned().into()),
+            BinaryValue::Bytes(value) => BinaryValue::Bytes(value.into_owned().into()),
+            BinaryValue::Int32(value) => BinaryValue::Int32(value),
+            BinaryValue::Float32(value) => BinaryValue::Float32(value),
+            BinaryValue::Pointer(value) => BinaryValue::Pointer(value),
+            BinaryValue::WideString(value) => BinaryValue::WideString(value),
+            BinaryValue::Color(value) => BinaryValue::Color(value),
+            BinaryValue::Uint64(value) => BinaryValue::Uint64(value),
+            BinaryValue::Int64(value) => BinaryValue::Int64(value),
+        }
+    }
+
+    /// Parse a value from its text form as the given wire type.
+    ///
+    /// Colors are parsed from `"r g b a"`, parsing as [`BinaryType::Table`] or an end marker always fails.
+    pub fn parse(value: &str, ty: BinaryType) -> Result<BinaryValue<'static>, ParseStringError> {
+        Ok(match ty {
+            BinaryType::String => BinaryValue::String(value.to_string().into()),
+            BinaryType::Int32 => BinaryValue::Int32(ParseItem::from_str(value)?),
+            BinaryType::Float32 => BinaryValue::Float32(ParseItem::from_str(value)?),
+            BinaryType::Pointer => BinaryValue::Pointer(ParseItem::from_str(value)?),
+            BinaryType::WideString => BinaryValue::WideString(value.into()),
+            BinaryType::Color => {
+                let components = value
+                    .split_whitespace()
+                    .map(<u8 as ParseItem>::from_str)
+                    .collect::<Result<Vec<_>, _>>()?;
+                BinaryValue::Color(
+                    components
+                        .try_into()
+                        .map_err(|_| ParseStringError::new("color", value))?,
+                )
+            }
+            BinaryType::Uint64 => BinaryValue::Uint64(ParseItem::from_str(value)?),
+            BinaryType::Int64 => BinaryValue::Int64(ParseItem::from_str(value)?),
+            BinaryType::Table | BinaryType::End | BinaryType::AlternateEnd => {
+                return Err(ParseStringError::new("binary value", value))
+            }
+        })
+    }
+
+    /// The value as it would be written in vdf text, invalid UTF-8 is replaced.
+    pub fn into_string(self) -> Cow<'static, str> {
+        match self {
+            BinaryValue::String(value) => value.into_owned().into(),
+            BinaryValue::Bytes(value) => String::from_utf8_lossy(&value).into_owned().into(),
+            BinaryValue::WideString(value) => value.into(),
+            value => value.to_string().into(),
+        }
+    }
+}
+
+/// Formats the value as it would be written in vdf text, colors are written as `"r g b a"`.
+impl Display for BinaryValue<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryValue::String(value) => f.write_str(value),
+            BinaryValue::Bytes(value) => f.write_str(&String::from_utf8_lossy(value)),
+            BinaryValue::Int32(value) => write!(f, "{value}"),
+            BinaryValue::Float32(value) => write!(f, "{value}"),
+            BinaryValue::Pointer(value) => write!(f, "{value}"),
+            BinaryValue::WideString(value) => f.write_str(value),
+            BinaryValue::Color([r, g, b, a]) => write!(f, "{r} {g} {b} {a}"),
+            BinaryValue::Uint64(value) => write!(f, "{value}"),
+            BinaryValue::Int64(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Binary reader event, like [`Event`] but keeping the wire type of values.
+#[derive(Clone, PartialEq, Debug)]
+pub enum BinaryEvent<'a> {
+    GroupStart {
+        name: Cow<'a, str>,
+        span: Span,
+    },
+    GroupEnd {
+        span: Span,
+    },
+    Entry {
+        key: Cow<'a, str>,
+        value: BinaryValue<'a>,
+        span: Span,
+    },
+}
+
+impl<'a> From<BinaryEvent<'a>> for Event<'a> {
+    fn from(event: BinaryEvent<'a>) -> Self {
+        match event {
+            BinaryEvent::GroupStart { name, span } => Event::GroupStart(GroupStartEvent {
+                name,
+                condition: None,
+                span,
+            }),
+            BinaryEvent::GroupEnd { span } => Event::GroupEnd(GroupEndEvent { span }),
+            BinaryEvent::Entry { key, value, span } => {
+                let content = match value {
+                    BinaryValue::String(value) => value,
+                    value => value.into_string(),
+                };
+                Event::Entry(EntryEvent {
+                    key: Item::Item {
+                        content: key,
+                        span: span.clone(),
+                    },
+                    value: Item::Item {
+                        content,
+                        span: span.clone(),
+                    },
+                    condition: None,
+                    span,
+                })
+            }
+        }
+    }
+}
+
+/// Pick a wire type for a value from its text form.
+///
+/// Integers become [`BinaryType::Int32`] if they fit, otherwise [`BinaryType::Uint64`] or [`BinaryType::Int64`],
+/// finite floats become [`BinaryType::Float32`] and anything else is a [`BinaryType::String`].
+pub fn infer_type(value: &str) -> BinaryType {
+    if <i32 as ParseItem>::from_str(value).is_ok() {
+        BinaryType::Int32
+    } else if <u64 as ParseItem>::from_str(value).is_ok() {
+        BinaryType::Uint64
+    } else if <i64 as ParseItem>::from_str(value).is_ok() {
+        BinaryType::Int64
+    } else if <f32 as ParseItem>::from_str(value).is_ok_and(f32::is_finite) {
+        BinaryType::Float32
+    } else {
+        BinaryType::String
+    }
+}
+
+/// Make the offset of binary errors relative to the start of the containing file.
+fn with_offset(error: VdfError, offset: usize) -> VdfError {
+    match error {
+        VdfError::Binary(mut error) => {
+            error.offset += offset;
+            error.into()
+        }
+        error => error,
+    }
+}
+
+/// Deserialize a type from binary KeyValues data.
+pub fn from_bytes<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    let table = Table::load(&mut BinaryReader::new(data))?;
+    crate::from_entry(Entry::Table(table))
+}
+
+/// Serialize a type as binary KeyValues data.
+///
+/// Types are mapped the same way as for [`to_string`](crate::to_string) and booleans are written as
+/// [`BinaryType::Int32`]. `i64` and `u64` are written as [`BinaryType::Int64`] and [`BinaryType::Uint64`],
+/// smaller integers as [`BinaryType::Int32`] or as [`BinaryType::Uint64`] for a `u32` that doesn't fit.
+/// Since there is no 64-bit float type, `f64` values that can't be stored as [`BinaryType::Float32`]
+/// without losing precision are written as a string.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    Ok(crate::ser::to_binary_table(value)?.to_bytes())
+}
@@ -0,0 +1,334 @@
+//! Steam's `appcache/packageinfo.vdf`, containing the info for every known package (license).
+//!
+//! The file starts with a header followed by a record for every package, each containing some
+//! metadata and the package info as binary KeyValues. Records don't store their size, so the
+//! KeyValues are scanned to find the next record, but they are only parsed into a [`Table`]
+//! when calling [`Package::table`].
+
+use super::cursor::Cursor;
+use super::{with_offset, BinaryReader};
+use crate::entry::{Entry, ParseItem, Table};
+use crate::Result;
+
+/// The version of a packageinfo file, determined by the magic number at the start of the file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PackageInfoVersion {
+    /// Magic `0x06565527`
+    V27,
+    /// Magic `0x06565528`, adds the PICS token
+    V28,
+}
+
+impl PackageInfoVersion {
+    pub fn magic(&self) -> u32 {
+        match self {
+            PackageInfoVersion::V27 => 0x06565527,
+            PackageInfoVersion::V28 => 0x06565528,
+        }
+    }
+}
+
+impl TryFrom<u32> for PackageInfoVersion {
+    type Error = u32;
+
+    fn try_from(magic: u32) -> Result<Self, Self::Error> {
+        Ok(match magic {
+            0x06565527 => PackageInfoVersion::V27,
+            0x06565528 => PackageInfoVersion::V28,
+            _ => return Err(magic),
+        })
+    }
+}
+
+/// The header of a packageinfo file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PackageInfoHeader {
+    pub version: PackageInfoVersion,
+    pub universe: u32,
+}
+
+/// The package id marking the end of the packages.
+const END_MARKER: u32 = 0xFFFFFFFF;
+
+/// A parsed packageinfo file.
+pub struct PackageInfo<'a> {
+    header: PackageInfoHeader,
+    data: &'a [u8],
+    records_start: usize,
+}
+
+impl<'a> PackageInfo<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(data, 0);
+        let magic = cursor.u32()?;
+        let Ok(version) = PackageInfoVersion::try_from(magic) else {
+            return Cursor::new(data, 0).error(format!("unknown packageinfo magic 0x{magic:08x}"));
+        };
+        let universe = cursor.u32()?;
+
+        Ok(PackageInfo {
+            header: PackageInfoHeader { version, universe },
+            data,
+            records_start: cursor.position(),
+        })
+    }
+
+    pub fn header(&self) -> PackageInfoHeader {
+        self.header
+    }
+
+    /// Iterate over all packages in the file, without parsing their KeyValues.
+    pub fn packages(&self) -> PackageIter<'a> {
+        PackageIter {
+            cursor: Cursor::new(self.data, self.records_start),
+            version: self.header.version,
+            done: false,
+        }
+    }
+
+    /// Find the package with the given id.
+    pub fn get(&self, package_id: u32) -> Result<Option<Package<'a>>> {
+        for package in self.packages() {
+            let package = package?;
+            if package.package_id == package_id {
+                return Ok(Some(package));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find all packages that contain the given app.
+    pub fn packages_with_app(&self, app_id: u32) -> Result<Vec<Package<'a>>> {
+        let mut packages = Vec::new();
+        for package in self.packages() {
+            let package = package?;
+            if package.app_ids()?.contains(&app_id) {
+                packages.push(package);
+            }
+        }
+        Ok(packages)
+    }
+}
+
+/// A single package record, the KeyValues are parsed on demand by [`Package::table`].
+#[derive(Clone, Debug)]
+pub struct Package<'a> {
+    pub package_id: u32,
+    /// SHA-1 of the package info in text form
+    pub sha1: [u8; 20],
+    pub change_number: u32,
+    /// Not present before [`PackageInfoVersion::V28`]
+    pub pics_token: Option<u64>,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Package<'a> {
+    /// The raw binary KeyValues of the package.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// A reader for the KeyValues of the package.
+    pub fn reader(&self) -> BinaryReader<'a> {
+        BinaryReader::new(self.data)
+    }
+
+    /// Parse the KeyValues of the package.
+    pub fn table(&self) -> Result<Table> {
+        Table::load(&mut self.reader()).map_err(|e| with_offset(e, self.offset))
+    }
+
+    /// The apps included in the package.
+    pub fn app_ids(&self) -> Result<Vec<u32>> {
+        self.ids("appids")
+    }
+
+    /// The depots included in the package.
+    pub fn depot_ids(&self) -> Result<Vec<u32>> {
+        self.ids("depotids")
+    }
+
+    fn ids(&self, key: &str) -> Result<Vec<u32>> {
+        let table = self.table()?;
+        // the package info is stored in a group named after the package id
+        let Some(ids) = table
+            .values()
+            .next()
+            .and_then(|info| info.get(key))
+            .and_then(Entry::as_table)
+        else {
+            return Ok(Vec::new());
+        };
+        ids.values()
+            .filter_map(Entry::as_str)
+            .map(|id| Ok(u32::from_str(id)?))
+            .collect()
+    }
+}
+
+/// Iterator over the packages in a packageinfo file, created by [`PackageInfo::packages`].
+pub struct PackageIter<'a> {
+    cursor: Cursor<'a>,
+    version: PackageInfoVersion,
+    done: bool,
+}
+
+impl<'a> PackageIter<'a> {
+    fn read_package(&mut self) -> Result<Option<Package<'a>>> {
+        let package_id = self.cursor.u32()?;
+        if package_id == END_MARKER {
+            return Ok(None);
+        }
+        let sha1 = self.cursor.bytes()?;
+        let change_number = self.cursor.u32()?;
+        let pics_token = match self.version {
+            PackageInfoVersion::V27 => None,
+            PackageInfoVersion::V28 => Some(self.cursor.u64()?),
+        };
+
+        // scan the KeyValues to find the start of the next record
+        let offset = self.cursor.position();
+        let mut reader = BinaryReader::new(self.cursor.remaining());
+        while let Some(event) = reader.typed_event() {
+            event.map_err(|e| with_offset(e, offset))?;
+        }
+        let data = self.cursor.slice(reader.position())?;
+
+        Ok(Some(Package {
+            package_id,
+            sha1,
+            change_number,
+            pics_token,
+            data,
+            offset,
+        }))
+    }
+}
+
+impl<'a> Iterator for PackageIter<'a> {
+    type Item = Result<Package<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_package();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(key: &str, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x00];
+        out.extend(key.as_bytes());
+        out.push(0);
+        out.extend(body);
+        out.push(0x08);
+        out
+    }
+
+    fn int(key: &str, value: i32) -> Vec<u8> {
+        let mut out = vec![0x02];
+        out.extend(key.as_bytes());
+        out.push(0);
+        out.extend(value.to_le_bytes());
+        out
+    }
+
+    fn record(version: PackageInfoVersion, package_id: u32, app_ids: &[i32]) -> Vec<u8> {
+        let mut out = package_id.to_le_bytes().to_vec();
+        out.extend([1; 20]);
+        out.extend(7u32.to_le_bytes());
+        if version == PackageInfoVersion::V28 {
+            out.extend(99u64.to_le_bytes());
+        }
+
+        let mut apps = Vec::new();
+        for (i, app_id) in app_ids.iter().enumerate() {
+            apps.extend(int(&i.to_string(), *app_id));
+        }
+        let mut info = int("packageid", package_id as i32);
+        info.extend(group("appids", &apps));
+        info.extend(group("depotids", &int("0", 441)));
+        out.extend(group(&package_id.to_string(), &info));
+        out.push(0x08);
+        out
+    }
+
+    fn file(version: PackageInfoVersion, records: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = version.magic().to_le_bytes().to_vec();
+        out.extend(1u32.to_le_bytes());
+        for record in records {
+            out.extend(record);
+        }
+        out.extend(END_MARKER.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn test_packages() {
+        let version = PackageInfoVersion::V28;
+        let data = file(
+            version,
+            &[
+                record(version, 0, &[7, 440]),
+                record(version, 12, &[570]),
+                record(version, 13, &[440, 570]),
+            ],
+        );
+        let packageinfo = PackageInfo::new(&data).unwrap();
+        assert_eq!(
+            PackageInfoHeader {
+                version,
+                universe: 1
+            },
+            packageinfo.header()
+        );
+
+        let ids: Vec<_> = packageinfo
+            .packages()
+            .map(|package| package.unwrap().package_id)
+            .collect();
+        assert_eq!(vec![0, 12, 13], ids);
+
+        let package = packageinfo.get(13).unwrap().unwrap();
+        assert_eq!(Some(99), package.pics_token);
+        assert_eq!(vec![440, 570], package.app_ids().unwrap());
+        assert_eq!(vec![441], package.depot_ids().unwrap());
+
+        let ids: Vec<_> = packageinfo
+            .packages_with_app(440)
+            .unwrap()
+            .into_iter()
+            .map(|package| package.package_id)
+            .collect();
+        assert_eq!(vec![0, 13], ids);
+    }
+
+    #[test]
+    fn test_v27() {
+        let version = PackageInfoVersion::V27;
+        let data = file(version, &[record(version, 5, &[10])]);
+        let packageinfo = PackageInfo::new(&data).unwrap();
+        let package = packageinfo.get(5).unwrap().unwrap();
+        assert_eq!(None, package.pics_token);
+        assert_eq!(vec![10], package.app_ids().unwrap());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let version = PackageInfoVersion::V28;
+        let mut data = file(version, &[record(version, 5, &[10])]);
+        data.truncate(data.len() - 8);
+        let packageinfo = PackageInfo::new(&data).unwrap();
+        assert!(packageinfo.packages().any(|package| package.is_err()));
+        assert!(PackageInfo::new(b"\x29\x44\x56\x07\x01\0\0\0").is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use super::table::StoredKey;
+use super::{BinaryEvent, BinaryType, BinaryValue};
+use crate::error::BinaryError;
+use crate::{Event, Result};
+use std::borrow::Cow;
+
+/// A reader for binary KeyValues.
+pub struct BinaryReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    depth: usize,
+    string_table: Option<&'a [Cow<'a, str>]>,
+    done: bool,
+    /// Where the last key started
+    key_start: usize,
+    /// The end marker read by the last event, if it was the end of a group or the document
+    end: Option<BinaryType>,
+}
+
+impl<'a> BinaryReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        BinaryReader {
+            data,
+            position: 0,
+            depth: 0,
+            string_table: None,
+            done: false,
+            key_start: 0,
+            end: None,
+        }
+    }
+
+    /// Read keys as `u32` indices into the string table instead of inline strings.
+    pub fn with_string_table(self, string_table: &'a [Cow<'a, str>]) -> Self {
+        BinaryReader {
+            string_table: Some(string_table),
+            ..self
+        }
+    }
+
+    /// The offset of the next byte to be read.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn error<T>(&self, error: impl Into<String>) -> Result<T> {
+        Err(BinaryError::new(error, self.position).into())
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        match self.data.get(self.position..self.position + N) {
+            Some(bytes) => {
+                self.position += N;
+                Ok(bytes.try_into().expect("slice has the right length"))
+            }
+            None => self.error("unexpected end of data"),
+        }
+    }
+
+    fn raw_string(&mut self) -> Result<&'a [u8]> {
+        let rest = &self.data[self.position.min(self.data.len())..];
+        let Some(length) = rest.iter().position(|byte| *byte == 0) else {
+            return self.error("unterminated string");
+        };
+        self.position += length + 1;
+        Ok(&rest[..length])
+    }
+
+    fn string(&mut self) -> Result<Cow<'a, str>> {
+        self.raw_string().map(String::from_utf8_lossy)
+    }
+
+    /// Strings that aren't valid UTF-8 are kept as bytes, so they can be written back unchanged.
+    fn string_value(&mut self) -> Result<BinaryValue<'a>> {
+        let bytes = self.raw_string()?;
+        Ok(match std::str::from_utf8(bytes) {
+            Ok(string) => BinaryValue::String(string.into()),
+            Err(_) => BinaryValue::Bytes(bytes.into()),
+        })
+    }
+
+    fn wide_string(&mut self) -> Result<String> {
+        let length = u16::from_le_bytes(self.bytes()?);
+        let units = (0..length)
+            .map(|_| self.bytes().map(u16::from_le_bytes))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    fn key(&mut self) -> Result<Cow<'a, str>> {
+        self.key_start = self.position;
+        match self.string_table {
+            Some(string_table) => {
+                let index = u32::from_le_bytes(self.bytes()?);
+                match string_table.get(index as usize) {
+                    Some(key) => Ok(key.clone()),
+                    None => self.error(format!("string table index {index} out of range")),
+                }
+            }
+            None => self.string(),
+        }
+    }
+
+    /// How the key of the last entry or group was stored.
+    pub(crate) fn stored_key(&self) -> StoredKey {
+        let data = &self.data[self.key_start..];
+        if self.string_table.is_some() {
+            let index = data[..4].try_into().expect("index was read");
+            return StoredKey::Index(u32::from_le_bytes(index));
+        }
+        let length = data.iter().position(|byte| *byte == 0).unwrap_or_default();
+        match std::str::from_utf8(&data[..length]) {
+            Ok(_) => StoredKey::Inline,
+            Err(_) => StoredKey::Bytes(data[..length].into()),
+        }
+    }
+
+    /// The end marker of the group that just ended, or of the document once all events are read.
+    ///
+    /// This is `None` for a document that ended without an end marker.
+    pub(crate) fn end_marker(&self) -> Option<BinaryType> {
+        self.end
+    }
+
+    /// Get the next event, keeping the wire type of values.
+    pub fn typed_event(&mut self) -> Option<Result<BinaryEvent<'a>>> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_event();
+        match &result {
+            Ok(None) | Err(_) => self.done = true,
+            Ok(Some(_)) => {}
+        }
+        result.transpose()
+    }
+
+    fn read_event(&mut self) -> Result<Option<BinaryEvent<'a>>> {
+        let start = self.position;
+        self.end = None;
+        let Some(&byte) = self.data.get(self.position) else {
+            return if self.depth == 0 {
+                Ok(None)
+            } else {
+                self.error("unexpected end of data")
+            };
+        };
+        let Ok(ty) = BinaryType::try_from(byte) else {
+            return self.error(format!("unknown type 0x{byte:02x}"));
+        };
+        self.position += 1;
+
+        if matches!(ty, BinaryType::End | BinaryType::AlternateEnd) {
+            self.end = Some(ty);
+            if self.depth == 0 {
+                // end of the document
+                return Ok(None);
+            }
+            self.depth -= 1;
+            return Ok(Some(BinaryEvent::GroupEnd {
+                span: start..self.position,
+            }));
+        }
+
+        let key = self.key()?;
+        let value = match ty {
+            BinaryType::Table => {
+                self.depth += 1;
+                return Ok(Some(BinaryEvent::GroupStart {
+                    name: key,
+                    span: start..self.position,
+                }));
+            }
+            BinaryType::String => self.string_value()?,
+            BinaryType::Int32 => BinaryValue::Int32(i32::from_le_bytes(self.bytes()?)),
+            BinaryType::Float32 => BinaryValue::Float32(f32::from_le_bytes(self.bytes()?)),
+            BinaryType::Pointer => BinaryValue::Pointer(u32::from_le_bytes(self.bytes()?)),
+            BinaryType::WideString => BinaryValue::WideString(self.wide_string()?),
+            BinaryType::Color => BinaryValue::Color(self.bytes()?),
+            BinaryType::Uint64 => BinaryValue::Uint64(u64::from_le_bytes(self.bytes()?)),
+            BinaryType::Int64 => BinaryValue::Int64(i64::from_le_bytes(self.bytes()?)),
+            BinaryType::End | BinaryType::AlternateEnd => unreachable!("handled above"),
+        };
+        Ok(Some(BinaryEvent::Entry {
+            key,
+            value,
+            span: start..self.position,
+        }))
+    }
+}
+
+impl<'a> Iterator for BinaryReader<'a> {
+    type Item = Result<Event<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.typed_event().map(|event| event.map(Event::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::{Entry, Table};
+
+    fn entry(ty: u8, key: &str, value: &[u8]) -> Vec<u8> {
+        let mut out = vec![ty];
+        out.extend_from_slice(key.as_bytes());
+        out.push(0);
+        out.extend_from_slice(value);
+        out
+    }
+
+    #[test]
+    fn test_types() {
+        let mut data = entry(0x00, "root", &[]);
+        data.extend(entry(0x01, "string", b"foo\0"));
+        data.extend(entry(0x02, "int", &(-5i32).to_le_bytes()));
+        data.extend(entry(0x03, "float", &1.5f32.to_le_bytes()));
+        data.extend(entry(0x05, "wide", &[2, 0, b'h', 0, b'i', 0]));
+        data.extend(entry(0x06, "color", &[255, 128, 0, 255]));
+        data.extend(entry(0x07, "uint64", &u64::MAX.to_le_bytes()));
+        data.extend(entry(0x0A, "int64", &i64::MIN.to_le_bytes()));
+        data.extend([0x08, 0x08]);
+
+        let table = Table::load(&mut BinaryReader::new(&data)).unwrap();
+        let root = table.get("root").and_then(Entry::as_table).unwrap();
+        let values: Vec<_> = root
+            .iter()
+            .map(|(key, value)| (key, value.as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("string", "foo"),
+                ("int", "-5"),
+                ("float", "1.5"),
+                ("wide", "hi"),
+                ("color", "255 128 0 255"),
+                ("uint64", "18446744073709551615"),
+                ("int64", "-9223372036854775808"),
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn test_string_table() {
+        let strings: Vec<Cow<str>> = vec!["appid".into(), "common".into()];
+        let mut data = vec![0x00, 1, 0, 0, 0];
+        data.extend([0x02, 0, 0, 0, 0]);
+        data.extend(440i32.to_le_bytes());
+        data.extend([0x08, 0x08]);
+
+        let mut reader = BinaryReader::new(&data).with_string_table(&strings);
+        let table = Table::load(&mut reader).unwrap();
+        assert_eq!(
+            Some("440"),
+            table
+                .get("common")
+                .and_then(|common| common.get("appid"))
+                .and_then(Entry::as_str)
+        );
+
+        let mut reader = BinaryReader::new(&[0x01, 5, 0, 0, 0]).with_string_table(&strings);
+        assert!(Table::load(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = entry(0x02, "int", &[1, 2]);
+        let error = Table::load(&mut BinaryReader::new(&data)).unwrap_err();
+        assert_eq!("unexpected end of data at offset 5", error.to_string());
+
+        let data = entry(0x00, "group", &[]);
+        assert!(Table::load(&mut BinaryReader::new(&data)).is_err());
+
+        let data = entry(0x09, "unknown", &[]);
+        assert!(Table::load(&mut BinaryReader::new(&data)).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use super::{writer, BinaryEvent, BinaryReader, BinaryType, BinaryValue};
+use crate::entry::{Entry, Table, Value};
+use crate::error::ParseStringError;
+use crate::Result;
+
+/// An entry in a [`BinaryTable`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum BinaryEntry {
+    Table(BinaryTable),
+    Value(BinaryValue<'static>),
+}
+
+impl BinaryEntry {
+    pub fn as_table(&self) -> Option<&BinaryTable> {
+        match self {
+            BinaryEntry::Table(table) => Some(table),
+            BinaryEntry::Value(_) => None,
+        }
+    }
+
+    pub fn as_value(&self) -> Option<&BinaryValue<'static>> {
+        match self {
+            BinaryEntry::Value(value) => Some(value),
+            BinaryEntry::Table(_) => None,
+        }
+    }
+}
+
+impl From<BinaryTable> for BinaryEntry {
+    fn from(table: BinaryTable) -> Self {
+        BinaryEntry::Table(table)
+    }
+}
+
+impl From<BinaryValue<'_>> for BinaryEntry {
+    fn from(value: BinaryValue<'_>) -> Self {
+        BinaryEntry::Value(value.into_owned())
+    }
+}
+
+/// How the key of an entry is stored, so it can be written back the same way.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub(crate) enum StoredKey {
+    /// A nul terminated UTF-8 string
+    #[default]
+    Inline,
+    /// A nul terminated string that isn't valid UTF-8, with the original bytes
+    Bytes(Box<[u8]>),
+    /// An index into the string table
+    Index(u32),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(super) struct BinaryTableEntry {
+    pub(super) key: String,
+    pub(super) stored_key: StoredKey,
+    pub(super) value: BinaryEntry,
+}
+
+/// A table of binary entries, keeping the wire type of every value.
+///
+/// Unlike [`Table`], repeated keys are never combined and the way keys, strings and end markers
+/// are stored is kept, so writing a loaded table gives back the original data.
+///
+/// Keys read from a string table are written back as the same index, entries added
+/// afterwards are written with an inline key.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BinaryTable {
+    pub(super) entries: Vec<BinaryTableEntry>,
+    /// The marker ending the table, `None` if the data ended without one
+    pub(super) end: Option<BinaryType>,
+}
+
+impl Default for BinaryTable {
+    fn default() -> Self {
+        BinaryTable {
+            entries: Vec::new(),
+            end: Some(BinaryType::End),
+        }
+    }
+}
+
+impl BinaryTable {
+    pub fn load_from_bytes(data: &[u8]) -> Result<BinaryTable> {
+        Self::load(&mut BinaryReader::new(data))
+    }
+
+    /// Load a table from the given `BinaryReader`.
+    pub fn load(reader: &mut BinaryReader) -> Result<BinaryTable> {
+        let mut table = BinaryTable::default();
+        while let Some(event) = reader.typed_event() {
+            match event? {
+                BinaryEvent::GroupStart { name, .. } => {
+                    let stored_key = reader.stored_key();
+                    let child = BinaryTable::load(reader)?;
+                    table.push_stored(name.into(), stored_key, child.into());
+                }
+                BinaryEvent::Entry { key, value, .. } => {
+                    table.push_stored(key.into(), reader.stored_key(), value.into())
+                }
+                BinaryEvent::GroupEnd { .. } => {
+                    table.end = reader.end_marker();
+                    return Ok(table);
+                }
+            }
+        }
+        table.end = reader.end_marker();
+        Ok(table)
+    }
+
+    fn push_stored(&mut self, key: String, stored_key: StoredKey, value: BinaryEntry) {
+        self.entries.push(BinaryTableEntry {
+            key,
+            stored_key,
+            value,
+        });
+    }
+
+    /// Convert a [`Table`] with the wire type for every value picked by `wire_type`.
+    ///
+    /// `wire_type` is called with the key and value of every value in the table,
+    /// use [`infer_type`](super::infer_type) to pick the type based on the value.
+    /// Arrays are written as repeated keys.
+    pub fn from_table<F>(table: &Table, mut wire_type: F) -> Result<BinaryTable, ParseStringError>
+    where
+        F: FnMut(&str, &str) -> BinaryType,
+    {
+        Self::from_table_inner(table, &mut wire_type)
+    }
+
+    fn from_table_inner<F>(
+        table: &Table,
+        wire_type: &mut F,
+    ) -> Result<BinaryTable, ParseStringError>
+    where
+        F: FnMut(&str, &str) -> BinaryType,
+    {
+        let mut binary = BinaryTable::default();
+        for (key, entry) in table {
+            binary.push_entry(key, entry, wire_type)?;
+        }
+        Ok(binary)
+    }
+
+    fn push_entry<F>(
+        &mut self,
+        key: &str,
+        entry: &Entry,
+        wire_type: &mut F,
+    ) -> Result<(), ParseStringError>
+    where
+        F: FnMut(&str, &str) -> BinaryType,
+    {
+        match entry {
+            Entry::Table(table) => self.push(key, Self::from_table_inner(table, wire_type)?),
+            Entry::Array(array) => {
+                for item in array.iter() {
+                    self.push_entry(key, item, wire_type)?;
+                }
+            }
+            Entry::Value(value) => {
+                self.push(key, BinaryValue::parse(value, wire_type(key, value))?)
+            }
+            Entry::Statement(value) => {
+                self.push(key, BinaryValue::parse(value, wire_type(key, value))?)
+            }
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&BinaryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| &entry.value)
+    }
+
+    /// Get the first entry with the given key.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut BinaryEntry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.key == key)
+            .map(|entry| &mut entry.value)
+    }
+
+    /// Iterate over all entries in the order they are defined in.
+    pub fn iter(&self) -> BinaryTableIter<'_> {
+        BinaryTableIter(self.entries.iter())
+    }
+
+    /// Set the value for a key, replacing the first entry with the key or adding it to the end of the table.
+    pub fn insert<K: Into<String>, V: Into<BinaryEntry>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<BinaryEntry> {
+        let key = key.into();
+        let value = value.into();
+        match self.get_mut(&key) {
+            Some(existing) => Some(std::mem::replace(existing, value)),
+            None => {
+                self.push_stored(key, StoredKey::Inline, value);
+                None
+            }
+        }
+    }
+
+    /// Add an entry at the end of the table, without replacing existing entries with the same key.
+    pub fn push<K: Into<String>, V: Into<BinaryEntry>>(&mut self, key: K, value: V) {
+        self.push_stored(key.into(), StoredKey::Inline, value.into());
+    }
+
+    /// Remove the first entry with the given key.
+    pub fn remove(&mut self, key: &str) -> Option<BinaryEntry> {
+        let index = self.entries.iter().position(|entry| entry.key == key)?;
+        Some(self.entries.remove(index).value)
+    }
+
+    /// Write the table as binary KeyValues, including the end marker of the root if it had one when loaded.
+    pub fn write_to<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writer::write_table(out, self)?;
+        match self.end {
+            Some(end) => out.write_all(&[end as u8]),
+            None => Ok(()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)
+            .expect("writing to a vec doesn't fail");
+        out
+    }
+}
+
+impl From<BinaryTable> for Table {
+    fn from(binary: BinaryTable) -> Self {
+        let mut table = Table::default();
+        for BinaryTableEntry { key, value, .. } in binary.entries {
+            match value {
+                BinaryEntry::Table(child) => table.push(key, Table::from(child)),
+                BinaryEntry::Value(value) => table.push(key, Value::from(value.into_string())),
+            }
+        }
+        table
+    }
+}
+
+impl<'a> IntoIterator for &'a BinaryTable {
+    type Item = (&'a str, &'a BinaryEntry);
+    type IntoIter = BinaryTableIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of a [`BinaryTable`], in order.
+pub struct BinaryTableIter<'a>(std::slice::Iter<'a, BinaryTableEntry>);
+
+impl<'a> Iterator for BinaryTableIter<'a> {
+    type Item = (&'a str, &'a BinaryEntry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|entry| (entry.key.as_str(), &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
@@ -0,0 +1,60 @@
+use super::table::StoredKey;
+use super::{BinaryEntry, BinaryTable, BinaryType, BinaryValue};
+use std::io::{Result, Write};
+
+fn write_string<W: Write>(out: &mut W, value: &[u8]) -> Result<()> {
+    out.write_all(value)?;
+    out.write_all(&[0])
+}
+
+fn write_key<W: Write>(out: &mut W, key: &str, stored_key: &StoredKey) -> Result<()> {
+    match stored_key {
+        StoredKey::Inline => write_string(out, key.as_bytes()),
+        StoredKey::Bytes(bytes) => write_string(out, bytes),
+        StoredKey::Index(index) => out.write_all(&index.to_le_bytes()),
+    }
+}
+
+fn write_value<W: Write>(out: &mut W, value: &BinaryValue) -> Result<()> {
+    match value {
+        BinaryValue::String(value) => write_string(out, value.as_bytes()),
+        BinaryValue::Bytes(value) => write_string(out, value),
+        BinaryValue::Int32(value) => out.write_all(&value.to_le_bytes()),
+        BinaryValue::Float32(value) => out.write_all(&value.to_le_bytes()),
+        BinaryValue::Pointer(value) => out.write_all(&value.to_le_bytes()),
+        BinaryValue::WideString(value) => {
+            let units: Vec<u16> = value.encode_utf16().collect();
+            let length = u16::try_from(units.len()).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "wide string too long")
+            })?;
+            out.write_all(&length.to_le_bytes())?;
+            for unit in units {
+                out.write_all(&unit.to_le_bytes())?;
+            }
+            Ok(())
+        }
+        BinaryValue::Color(value) => out.write_all(value),
+        BinaryValue::Uint64(value) => out.write_all(&value.to_le_bytes()),
+        BinaryValue::Int64(value) => out.write_all(&value.to_le_bytes()),
+    }
+}
+
+/// Write the entries of a table, without the end marker of the table itself.
+pub(crate) fn write_table<W: Write>(out: &mut W, table: &BinaryTable) -> Result<()> {
+    for entry in &table.entries {
+        match &entry.value {
+            BinaryEntry::Table(child) => {
+                out.write_all(&[BinaryType::Table as u8])?;
+                write_key(out, &entry.key, &entry.stored_key)?;
+                write_table(out, child)?;
+                out.write_all(&[child.end.unwrap_or(BinaryType::End) as u8])?;
+            }
+            BinaryEntry::Value(value) => {
+                out.write_all(&[value.ty() as u8])?;
+                write_key(out, &entry.key, &entry.stored_key)?;
+                write_value(out, value)?;
+            }
+        }
+    }
+    Ok(())
+}
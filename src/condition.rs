@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// A condition attached to a key, value or group, like `[$WIN32]` or `[!$X360 && !$OSX]`.
+///
+/// Conditions are stored as written and only parsed when evaluated.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Condition(String);
+
+impl Condition {
+    /// Create a condition from the expression, with or without the surrounding brackets.
+    pub fn new<S: Into<String>>(expression: S) -> Self {
+        let expression = expression.into();
+        match expression
+            .strip_prefix('[')
+            .and_then(|expression| expression.strip_suffix(']'))
+        {
+            Some(inner) => Condition(inner.trim().into()),
+            None => Condition(expression.trim().into()),
+        }
+    }
+
+    /// The condition expression without the surrounding brackets.
+    pub fn expression(&self) -> &str {
+        &self.0
+    }
+
+    /// Evaluate the condition with the given defines.
+    ///
+    /// Supports `!`, `&&`, `||` and parentheses, with `&&` binding stronger than `||`.
+    /// Malformed conditions, including conditions with parentheses nested deeper than 32 levels, evaluate to `false`.
+    pub fn evaluate(&self, defines: &Defines) -> bool {
+        let mut parser = ConditionParser {
+            remaining: &self.0,
+            defines,
+            depth: 0,
+        };
+        parser.or().unwrap_or(false) && parser.remaining.trim().is_empty()
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.0)
+    }
+}
+
+/// The set of defines used to evaluate conditions.
+///
+/// Define names are case-insensitive and can be given with or without the leading `$`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Defines(HashSet<String>);
+
+impl Defines {
+    /// The defines set by the engine on the platform this is compiled for.
+    pub fn current_platform() -> Self {
+        if cfg!(target_os = "windows") {
+            ["WIN32", "WINDOWS"].into_iter().collect()
+        } else if cfg!(target_os = "macos") {
+            ["OSX", "POSIX"].into_iter().collect()
+        } else {
+            ["LINUX", "POSIX"].into_iter().collect()
+        }
+    }
+
+    pub fn insert(&mut self, define: &str) {
+        self.0.insert(normalize_define(define));
+    }
+
+    pub fn contains(&self, define: &str) -> bool {
+        self.0.contains(&normalize_define(define))
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for Defines {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        let mut defines = Defines::default();
+        for define in iter {
+            defines.insert(define.as_ref());
+        }
+        defines
+    }
+}
+
+fn normalize_define(define: &str) -> String {
+    define.trim().trim_start_matches('$').to_ascii_uppercase()
+}
+
+/// How deep parentheses can be nested in a condition.
+const MAX_DEPTH: usize = 32;
+
+struct ConditionParser<'a> {
+    remaining: &'a str,
+    defines: &'a Defines,
+    depth: usize,
+}
+
+impl ConditionParser<'_> {
+    fn eat(&mut self, token: &str) -> bool {
+        self.remaining = self.remaining.trim_start();
+        if let Some(rest) = self.remaining.strip_prefix(token) {
+            self.remaining = rest;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Option<bool> {
+        let mut result = self.and()?;
+        while self.eat("||") {
+            result |= self.and()?;
+        }
+        Some(result)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut result = self.unary()?;
+        while self.eat("&&") {
+            result &= self.unary()?;
+        }
+        Some(result)
+    }
+
+    fn unary(&mut self) -> Option<bool> {
+        let mut negate = false;
+        while self.eat("!") {
+            negate = !negate;
+        }
+        let value = if self.eat("(") {
+            if self.depth == MAX_DEPTH {
+                return None;
+            }
+            self.depth += 1;
+            let result = self.or()?;
+            self.depth -= 1;
+            self.eat(")").then_some(result)?
+        } else if self.eat("$") {
+            let end = self
+                .remaining
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(self.remaining.len());
+            let (name, rest) = self.remaining.split_at(end);
+            self.remaining = rest;
+            if name.is_empty() {
+                return None;
+            }
+            self.defines.contains(name)
+        } else {
+            return None;
+        };
+        Some(value != negate)
+    }
+}
+
+#[test]
+fn test_evaluate() {
+    let defines: Defines = ["$WIN32", "windows"].into_iter().collect();
+
+    assert!(Condition::new("[$WIN32]").evaluate(&defines));
+    assert!(Condition::new("$windows").evaluate(&defines));
+    assert!(!Condition::new("[$OSX]").evaluate(&defines));
+    assert!(Condition::new("[!$X360]").evaluate(&defines));
+    assert!(Condition::new("[$WIN32||$LINUX]").evaluate(&defines));
+    assert!(!Condition::new("[$WIN32&&$LINUX]").evaluate(&defines));
+    assert!(Condition::new("[ $OSX || $LINUX || $WIN32 && !$X360 ]").evaluate(&defines));
+    assert!(!Condition::new("[!($WIN32 || $OSX)]").evaluate(&defines));
+    assert!(!Condition::new("[$WIN32 garbage]").evaluate(&defines));
+    assert!(Condition::new("[!!$WIN32 && !!!$OSX]").evaluate(&defines));
+
+    let nested = |depth| format!("{}$WIN32{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(Condition::new(nested(MAX_DEPTH)).evaluate(&defines));
+    assert!(!Condition::new(nested(MAX_DEPTH + 1)).evaluate(&defines));
+    assert!(!Condition::new(nested(1_000_000)).evaluate(&defines));
+}
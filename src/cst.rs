@@ -0,0 +1,794 @@
+//! Lossless syntax tree for editing vdf files while preserving their formatting.
+//!
+//! Every byte of the source is kept, including whitespace, comments and the quoting style of
+//! keys and values, so that writing an unmodified [`Document`] gives back the original source.
+//! Edits only touch the region of the document that they modify.
+
+use crate::entry::Table;
+use crate::error::{NoValidTokenError, UnexpectedTokenError};
+use crate::lexer::bracketed;
+use crate::reader::quoted_string;
+use crate::writer::{can_be_unquoted, escape};
+use crate::{Condition, EscapeMode, Result, Token};
+use logos::{Logos, Span};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+
+/// Token including the trivia that's skipped by [`Token`].
+#[derive(PartialEq, Debug, Logos, Clone, Copy)]
+enum RawToken {
+    #[regex(r"[ \t\f\r\n]+")]
+    Whitespace,
+    #[regex(r"//[^\n]*")]
+    Comment,
+    #[token("{")]
+    GroupStart,
+    #[token("}")]
+    GroupEnd,
+    #[regex("[^# \t\n{}\"\\[][^ \t\n\r{}]*", priority = 0)]
+    Item,
+    #[regex("\"([^\"\\\\]|\\\\.)*\"")]
+    QuotedItem,
+    #[regex("#[^ \"\t\n\r{}]+")]
+    Statement,
+    #[regex("\"#([^\"\\\\]|\\\\.)*\"")]
+    QuotedStatement,
+    #[token("[", |lex| bracketed(lex, RawToken::Condition, RawToken::Item))]
+    Condition,
+}
+
+impl RawToken {
+    fn is_trivia(self) -> bool {
+        matches!(self, RawToken::Whitespace | RawToken::Comment)
+    }
+
+    fn is_string(self) -> bool {
+        matches!(
+            self,
+            RawToken::Item | RawToken::QuotedItem | RawToken::Statement | RawToken::QuotedStatement
+        )
+    }
+
+    fn token(self) -> Option<Token> {
+        match self {
+            RawToken::Whitespace | RawToken::Comment => None,
+            RawToken::GroupStart => Some(Token::GroupStart),
+            RawToken::GroupEnd => Some(Token::GroupEnd),
+            RawToken::Item => Some(Token::Item),
+            RawToken::QuotedItem => Some(Token::QuotedItem),
+            RawToken::Statement => Some(Token::Statement),
+            RawToken::QuotedStatement => Some(Token::QuotedStatement),
+            RawToken::Condition => Some(Token::Condition),
+        }
+    }
+}
+
+/// A key or value as it's written in the source.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Text {
+    raw: String,
+}
+
+impl Text {
+    /// Create a text for the value, quoting it only if required.
+    fn new(value: &str, quoted: bool) -> Self {
+        let raw = if quoted || !can_be_unquoted(value) {
+            format!("\"{}\"", escape(value, EscapeMode::default()))
+        } else {
+            value.into()
+        };
+        Text { raw }
+    }
+
+    /// The text as it's written in the source, including any quotes.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn is_quoted(&self) -> bool {
+        self.raw.starts_with('"')
+    }
+
+    /// The value of the text with quotes removed and escape sequences decoded.
+    pub fn value(&self) -> Cow<'_, str> {
+        if self.is_quoted() {
+            quoted_string(&self.raw, EscapeMode::default())
+        } else {
+            self.raw.as_str().into()
+        }
+    }
+
+    /// Replace the value while keeping the quoting style if possible.
+    fn set(&mut self, value: &str) {
+        *self = Text::new(value, self.is_quoted());
+    }
+}
+
+/// The value of a node.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NodeValue {
+    /// One or more values, additional values are the values continued on the same line.
+    ///
+    /// Every value is preceded by the trivia in front of it.
+    Values(Vec<(String, Text)>),
+    /// A nested group, preceded by the trivia between the key and the opening brace.
+    Group(String, Group),
+}
+
+/// A key with its value or group.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Node {
+    /// Whitespace and comments in front of the key
+    leading: String,
+    key: Text,
+    value: NodeValue,
+    /// A condition like `[$WIN32]` with the trivia in front of it.
+    ///
+    /// For groups the condition is written between the key and the opening brace,
+    /// otherwise it's written after the values.
+    condition: Option<(String, String)>,
+}
+
+impl Node {
+    pub fn key(&self) -> Cow<'_, str> {
+        self.key.value()
+    }
+
+    pub fn key_text(&self) -> &Text {
+        &self.key
+    }
+
+    pub fn value(&self) -> &NodeValue {
+        &self.value
+    }
+
+    /// The whitespace and comments in front of the key.
+    pub fn leading_trivia(&self) -> &str {
+        &self.leading
+    }
+
+    /// The condition of the node, if any.
+    pub fn condition(&self) -> Option<Condition> {
+        self.condition
+            .as_ref()
+            .map(|(_, condition)| Condition::new(condition.as_str()))
+    }
+
+    /// Set or remove the condition of the node.
+    pub fn set_condition(&mut self, condition: Option<&Condition>) {
+        self.condition = condition.map(|condition| {
+            let trivia = match self.condition.take() {
+                Some((trivia, _)) => trivia,
+                None => " ".into(),
+            };
+            (trivia, condition.to_string())
+        });
+    }
+
+    /// Get the first value of the node, if it's not a group.
+    pub fn as_str(&self) -> Option<Cow<'_, str>> {
+        match &self.value {
+            NodeValue::Values(values) => values.first().map(|(_, value)| value.value()),
+            NodeValue::Group(..) => None,
+        }
+    }
+
+    pub fn as_group(&self) -> Option<&Group> {
+        match &self.value {
+            NodeValue::Group(_, group) => Some(group),
+            NodeValue::Values(_) => None,
+        }
+    }
+
+    pub fn as_group_mut(&mut self) -> Option<&mut Group> {
+        match &mut self.value {
+            NodeValue::Group(_, group) => Some(group),
+            NodeValue::Values(_) => None,
+        }
+    }
+
+    /// Rename the key of the node, keeping its quoting style if possible.
+    pub fn rename(&mut self, key: &str) {
+        self.key.set(key);
+    }
+
+    /// Set the value of the node.
+    ///
+    /// The quoting style and whitespace in front of the value are kept if the node already has a value,
+    /// any additional values on the same line are removed.
+    /// If the node is a group, the group is replaced by the value.
+    pub fn set_value(&mut self, value: &str) {
+        match &mut self.value {
+            NodeValue::Values(values) => {
+                values.truncate(1);
+                match values.first_mut() {
+                    Some((_, text)) => text.set(value),
+                    None => values.push((" ".into(), Text::new(value, true))),
+                }
+            }
+            NodeValue::Group(..) => {
+                self.value =
+                    NodeValue::Values(vec![(" ".into(), Text::new(value, self.key.is_quoted()))]);
+            }
+        }
+    }
+
+    fn write_condition<W: Write>(&self, out: &mut W) -> fmt::Result {
+        if let Some((trivia, condition)) = &self.condition {
+            out.write_str(trivia)?;
+            out.write_str(condition)?;
+        }
+        Ok(())
+    }
+
+    fn write<W: Write>(&self, out: &mut W) -> fmt::Result {
+        out.write_str(&self.leading)?;
+        out.write_str(self.key.raw())?;
+        match &self.value {
+            NodeValue::Values(values) => {
+                for (trivia, value) in values {
+                    out.write_str(trivia)?;
+                    out.write_str(value.raw())?;
+                }
+                self.write_condition(out)
+            }
+            NodeValue::Group(trivia, group) => {
+                self.write_condition(out)?;
+                out.write_str(trivia)?;
+                out.write_char('{')?;
+                group.write(out)?;
+                out.write_char('}')
+            }
+        }
+    }
+}
+
+/// The contents of a group, or the top level of the document.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Group {
+    nodes: Vec<Node>,
+    /// Whitespace and comments after the last node
+    trailing: String,
+}
+
+impl Group {
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn nodes_mut(&mut self) -> &mut [Node] {
+        &mut self.nodes
+    }
+
+    /// The whitespace and comments after the last node.
+    pub fn trailing_trivia(&self) -> &str {
+        &self.trailing
+    }
+
+    /// Get the first node with the given key.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.key() == key)
+    }
+
+    /// Get the first node with the given key.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|node| node.key() == key)
+    }
+
+    /// Get the first group with the given key.
+    pub fn group(&self, key: &str) -> Option<&Group> {
+        self.nodes
+            .iter()
+            .filter(|node| node.key() == key)
+            .find_map(Node::as_group)
+    }
+
+    /// Get the first group with the given key.
+    pub fn group_mut(&mut self, key: &str) -> Option<&mut Group> {
+        self.nodes
+            .iter_mut()
+            .filter(|node| node.key() == key)
+            .find_map(Node::as_group_mut)
+    }
+
+    /// Follow a path of group names.
+    pub fn lookup_mut(&mut self, path: &[&str]) -> Option<&mut Group> {
+        path.iter()
+            .try_fold(self, |group, key| group.group_mut(key))
+    }
+
+    /// Set the value of the first node with the given key, or add a new node at the end of the group.
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.get_mut(key) {
+            Some(node) => node.set_value(value),
+            None => self.push(key, value),
+        }
+    }
+
+    /// Add a key-value at the end of the group.
+    pub fn push(&mut self, key: &str, value: &str) {
+        self.insert(self.nodes.len(), key, value)
+    }
+
+    /// Insert a key-value at the given position in the group.
+    ///
+    /// Indentation and quoting style is copied from the surrounding nodes,
+    /// comments on the same line as the previous node are kept there.
+    pub fn insert(&mut self, index: usize, key: &str, value: &str) {
+        let separator = self
+            .nodes
+            .iter()
+            .find_map(|node| match &node.value {
+                NodeValue::Values(values) => values.first().map(|(trivia, _)| trivia.clone()),
+                NodeValue::Group(..) => None,
+            })
+            .unwrap_or_else(|| " ".into());
+        let quoted = self.quoted();
+        let node = Node {
+            leading: self.node_leading(),
+            key: Text::new(key, quoted),
+            value: NodeValue::Values(vec![(separator, Text::new(value, quoted))]),
+            condition: None,
+        };
+        self.insert_node(index, node);
+    }
+
+    /// Insert an empty group at the given position and return it.
+    pub fn insert_group(&mut self, index: usize, key: &str) -> &mut Group {
+        let leading = self.node_leading();
+        let indent = leading.rsplit('\n').next().unwrap_or_default().to_string();
+        let node = Node {
+            key: Text::new(key, self.quoted()),
+            value: NodeValue::Group(
+                format!("\n{indent}"),
+                Group {
+                    nodes: Vec::new(),
+                    trailing: format!("\n{indent}"),
+                },
+            ),
+            leading,
+            condition: None,
+        };
+        let index = self.insert_node(index, node);
+        self.nodes[index]
+            .as_group_mut()
+            .expect("we just inserted a group")
+    }
+
+    fn insert_node(&mut self, index: usize, mut node: Node) -> usize {
+        let index = index.min(self.nodes.len());
+        if self.nodes.is_empty() && self.trailing.is_empty() {
+            self.trailing.push(' ');
+        }
+        // a comment on the same line as the previous node stays on that line
+        let following = match self.nodes.get_mut(index) {
+            Some(next) => &mut next.leading,
+            None => &mut self.trailing,
+        };
+        let same_line = following.find('\n').unwrap_or(following.len());
+        if following[..same_line].contains("//") {
+            node.leading.insert_str(0, &following[..same_line]);
+            following.replace_range(..same_line, "");
+        }
+        self.nodes.insert(index, node);
+        index
+    }
+
+    /// Remove all nodes with the given key, returns `true` if any node was removed.
+    ///
+    /// Comments on the same line as the previous node are kept.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let mut removed = false;
+        let mut i = 0;
+        while i < self.nodes.len() {
+            if self.nodes[i].key() == key {
+                self.remove_at(i);
+                removed = true;
+            } else {
+                i += 1;
+            }
+        }
+        removed
+    }
+
+    /// Remove the node at the given position.
+    pub fn remove_at(&mut self, index: usize) -> Node {
+        let node = self.nodes.remove(index);
+        // anything up to the first newline belongs to the line of the previous node
+        let kept = match node.leading.find('\n') {
+            Some(newline) => &node.leading[..newline],
+            None => "",
+        };
+        match self.nodes.get_mut(index) {
+            Some(next) => next.leading.insert_str(0, kept),
+            None => self.trailing.insert_str(0, kept),
+        }
+        node
+    }
+
+    /// Whether new keys and values should be quoted, based on the existing nodes
+    fn quoted(&self) -> bool {
+        self.nodes.is_empty() || self.nodes.iter().any(|node| node.key.is_quoted())
+    }
+
+    /// Leading trivia for new nodes, a newline with the indentation of the existing nodes
+    fn node_leading(&self) -> String {
+        match self
+            .nodes
+            .iter()
+            .map(|node| node.leading.as_str())
+            .find(|leading| leading.contains('\n'))
+        {
+            Some(leading) => format!("\n{}", leading.rsplit('\n').next().unwrap_or_default()),
+            None if self.nodes.is_empty() && self.trailing.contains('\n') => {
+                let closing_indent = self.trailing.rsplit('\n').next().unwrap_or_default();
+                let indent = if closing_indent.starts_with(' ') {
+                    "    "
+                } else {
+                    "\t"
+                };
+                format!("\n{closing_indent}{indent}")
+            }
+            None if self.nodes.is_empty() => " ".into(),
+            // a key on the same line as the previous value would continue that value
+            None => "\n".into(),
+        }
+    }
+
+    fn write<W: Write>(&self, out: &mut W) -> fmt::Result {
+        for node in &self.nodes {
+            node.write(out)?;
+        }
+        out.write_str(&self.trailing)
+    }
+}
+
+/// A vdf document that keeps all formatting.
+///
+/// ```rust
+/// # use vdf_reader::cst::Document;
+/// let mut document: Document = "\"Panel\" // a comment\n{\n\t\"xpos\" \"10\"\n}\n".parse().unwrap();
+/// document.root_mut().group_mut("Panel").unwrap().set("xpos", "20");
+/// assert_eq!("\"Panel\" // a comment\n{\n\t\"xpos\" \"20\"\n}\n", document.to_string());
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Document {
+    root: Group,
+}
+
+impl Document {
+    pub fn parse(source: &str) -> Result<Document> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            position: 0,
+        };
+        let root = parser.group(true)?;
+        Ok(Document { root })
+    }
+
+    pub fn root(&self) -> &Group {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut Group {
+        &mut self.root
+    }
+
+    /// Load the document into a [`Table`]
+    pub fn to_table(&self) -> Result<Table> {
+        Table::load_from_str(&self.to_string())
+    }
+}
+
+impl FromStr for Document {
+    type Err = crate::VdfError;
+
+    fn from_str(source: &str) -> Result<Self> {
+        Document::parse(source)
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.root.write(f)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(RawToken, Span)>> {
+    const VALID: &[Token] = &[
+        Token::Item,
+        Token::QuotedItem,
+        Token::Statement,
+        Token::QuotedStatement,
+        Token::GroupStart,
+        Token::GroupEnd,
+    ];
+
+    RawToken::lexer(source)
+        .spanned()
+        .map(|(token, span)| {
+            token
+                .map(|token| (token, span.clone()))
+                .map_err(|_| NoValidTokenError::new(VALID, span.into(), source.into()).into())
+        })
+        .collect()
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(RawToken, Span)>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn trivia(&mut self) -> String {
+        let start = self.position;
+        while matches!(self.tokens.get(self.position), Some((token, _)) if token.is_trivia()) {
+            self.position += 1;
+        }
+        self.tokens[start..self.position]
+            .iter()
+            .map(|(_, span)| &self.source[span.clone()])
+            .collect()
+    }
+
+    fn next(&mut self) -> Option<(RawToken, Span)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<RawToken> {
+        self.tokens.get(self.position).map(|(token, _)| *token)
+    }
+
+    fn text(&self, span: Span) -> Text {
+        Text {
+            raw: self.source[span].into(),
+        }
+    }
+
+    fn unexpected(
+        &self,
+        expected: &'static [Token],
+        found: Option<(RawToken, Span)>,
+    ) -> crate::VdfError {
+        let (found, span) = match found {
+            Some((token, span)) => (token.token(), span),
+            None => (None, self.source.len()..self.source.len()),
+        };
+        UnexpectedTokenError::new(expected, found, span.into(), self.source.into()).into()
+    }
+
+    fn group(&mut self, toplevel: bool) -> Result<Group> {
+        const VALID_KEY: &[Token] = &[
+            Token::Item,
+            Token::QuotedItem,
+            Token::GroupEnd,
+            Token::Statement,
+            Token::QuotedStatement,
+        ];
+        const VALID_VALUE: &[Token] = &[
+            Token::Item,
+            Token::QuotedItem,
+            Token::GroupStart,
+            Token::Statement,
+            Token::QuotedStatement,
+        ];
+
+        let mut nodes = Vec::new();
+        loop {
+            let leading = self.trivia();
+            let key = match self.next() {
+                None if toplevel => {
+                    return Ok(Group {
+                        nodes,
+                        trailing: leading,
+                    })
+                }
+                Some((RawToken::GroupEnd, _)) if !toplevel => {
+                    return Ok(Group {
+                        nodes,
+                        trailing: leading,
+                    })
+                }
+                Some((token, span)) if token.is_string() => self.text(span),
+                found => return Err(self.unexpected(VALID_KEY, found)),
+            };
+
+            let mut separator = self.trivia();
+            let mut condition = None;
+            // groups have their condition between the name and the opening brace
+            if self.peek() == Some(RawToken::Condition) {
+                let (_, span) = self.next().expect("peeked");
+                condition = Some((separator, self.source[span].to_string()));
+                separator = self.trivia();
+                match self.next() {
+                    Some((RawToken::GroupStart, _)) => {}
+                    found => return Err(self.unexpected(&[Token::GroupStart], found)),
+                }
+                nodes.push(Node {
+                    leading,
+                    key,
+                    value: NodeValue::Group(separator, self.group(false)?),
+                    condition,
+                });
+                continue;
+            }
+
+            let value = match self.next() {
+                Some((RawToken::GroupStart, _)) => NodeValue::Group(separator, self.group(false)?),
+                Some((token, span)) if token.is_string() => {
+                    let mut values = vec![(separator, self.text(span))];
+                    // multiple values on the same line create an array
+                    loop {
+                        let start = self.position;
+                        let trivia = self.trivia();
+                        match self.peek() {
+                            Some(token) if token.is_string() && !trivia.contains('\n') => {
+                                let (_, span) = self.next().expect("peeked");
+                                values.push((trivia, self.text(span)));
+                            }
+                            Some(RawToken::Condition) if !trivia.contains('\n') => {
+                                let (_, span) = self.next().expect("peeked");
+                                condition = Some((trivia, self.source[span].to_string()));
+                                break;
+                            }
+                            _ => {
+                                self.position = start;
+                                break;
+                            }
+                        }
+                    }
+                    NodeValue::Values(values)
+                }
+                found => return Err(self.unexpected(VALID_VALUE, found)),
+            };
+
+            nodes.push(Node {
+                leading,
+                key,
+                value,
+                condition,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Document;
+
+    const SOURCE: &str = r#""Resource/specificPanel.res"
+{
+    // Specify panel-specific controls here
+    empty ""
+    "xpos"  "10" // offset
+    "ypos"  "20"
+    "$envmaptint" .5 .5 .5
+    "Child" { "a" "b" }
+}
+"#;
+
+    #[test]
+    fn test_roundtrip() {
+        let document: Document = SOURCE.parse().unwrap();
+        assert_eq!(SOURCE, document.to_string());
+    }
+
+    #[test]
+    fn test_set_value() {
+        let mut document: Document = SOURCE.parse().unwrap();
+        let panel = document
+            .root_mut()
+            .group_mut("Resource/specificPanel.res")
+            .unwrap();
+        panel.set("xpos", "15");
+        panel.set("empty", "no longer empty");
+        assert_eq!(
+            SOURCE
+                .replace(r#""10""#, r#""15""#)
+                .replace(r#"empty """#, r#"empty "no longer empty""#),
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut document: Document = SOURCE.parse().unwrap();
+        let panel = document
+            .root_mut()
+            .group_mut("Resource/specificPanel.res")
+            .unwrap();
+        assert!(panel.remove("ypos"));
+        assert!(!panel.remove("missing"));
+        panel.insert(1, "wide", "100");
+        panel.push("tall", "50");
+        assert_eq!(
+            r#""Resource/specificPanel.res"
+{
+    // Specify panel-specific controls here
+    empty ""
+    "wide" "100"
+    "xpos"  "10" // offset
+    "$envmaptint" .5 .5 .5
+    "Child" { "a" "b" }
+    "tall" "50"
+}
+"#,
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn test_remove_keeps_comment() {
+        let mut document: Document = "a 1 // about a\nb 2\nc 3".parse().unwrap();
+        document.root_mut().remove("b");
+        assert_eq!("a 1 // about a\nc 3", document.to_string());
+    }
+
+    #[test]
+    fn test_insert_keeps_comment() {
+        let mut document: Document = "a 1 // about a\n".parse().unwrap();
+        document.root_mut().push("b", "2");
+        assert_eq!("a 1 // about a\nb 2\n", document.to_string());
+
+        let mut document: Document = "a 1 // about a\nc 3 // about c".parse().unwrap();
+        document.root_mut().insert(1, "b", "2");
+        document.root_mut().push("d", "4");
+        assert_eq!(
+            "a 1 // about a\nb 2\nc 3 // about c\nd 4",
+            document.to_string()
+        );
+
+        let mut document: Document = "\"Root\"\n{ // about root\n}\n".parse().unwrap();
+        let root = document.root_mut().group_mut("Root").unwrap();
+        root.push("a", "1");
+        assert_eq!(
+            "\"Root\"\n{ // about root\n\t\"a\" \"1\"\n}\n",
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn test_rename_group() {
+        let mut document: Document = SOURCE.parse().unwrap();
+        let panel = document
+            .root_mut()
+            .group_mut("Resource/specificPanel.res")
+            .unwrap();
+        panel.get_mut("Child").unwrap().rename("Renamed");
+        panel.group_mut("Renamed").unwrap().set("c", "d");
+        assert_eq!(
+            SOURCE.replace(
+                r#""Child" { "a" "b" }"#,
+                "\"Renamed\" { \"a\" \"b\"\n\"c\" \"d\" }"
+            ),
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn test_insert_group() {
+        let mut document: Document = "\"Root\"\n{\n\t\"a\" \"1\"\n}\n".parse().unwrap();
+        let root = document.root_mut().group_mut("Root").unwrap();
+        let group = root.insert_group(1, "Nested");
+        group.push("b", "2");
+        assert_eq!(
+            "\"Root\"\n{\n\t\"a\" \"1\"\n\t\"Nested\"\n\t{\n\t\t\"b\" \"2\"\n\t}\n}\n",
+            document.to_string()
+        );
+        assert_eq!(
+            Some("2"),
+            document
+                .to_table()
+                .unwrap()
+                .get("Root")
+                .and_then(|root| root.lookup("Nested.b"))
+                .and_then(|b| b.as_str())
+        );
+    }
+}
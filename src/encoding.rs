@@ -0,0 +1,188 @@
+use crate::error::EncodingError;
+use crate::reader::Reader;
+use crate::Result;
+use logos::Span;
+use std::borrow::Cow;
+
+/// The text encoding of vdf input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// Used by the localization and closecaption files of most games.
+    Utf16Le,
+    Utf16Be,
+    /// The Windows "ANSI" code page, a superset of Latin-1 used by some older mods.
+    Windows1252,
+}
+
+impl Encoding {
+    /// Detect the encoding from a byte order mark, returning the encoding and the length of the mark.
+    pub fn from_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some((Encoding::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((Encoding::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((Encoding::Utf16Be, 2)),
+            _ => None,
+        }
+    }
+
+    /// The number of bytes used to encode `text`.
+    fn encoded_len(&self, text: &str) -> usize {
+        match self {
+            Encoding::Utf8 => text.len(),
+            Encoding::Utf16Le | Encoding::Utf16Be => text.encode_utf16().count() * 2,
+            Encoding::Windows1252 => text.chars().count(),
+        }
+    }
+}
+
+/// Text decoded from bytes, remembering the encoding so spans can be mapped back to the bytes.
+///
+/// Valid UTF-8 is borrowed from the input, other encodings are converted.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DecodedText<'a> {
+    text: Cow<'a, str>,
+    encoding: Encoding,
+    bom: usize,
+}
+
+impl<'a> DecodedText<'a> {
+    /// Decode the input, detecting the encoding from its byte order mark.
+    ///
+    /// Input without a byte order mark is read as UTF-8 if it's valid UTF-8 and doesn't contain
+    /// any null characters, and decoded with the `fallback` encoding otherwise.
+    pub fn decode(bytes: &'a [u8], fallback: Encoding) -> Result<DecodedText<'a>> {
+        let (encoding, bom) =
+            Encoding::from_bom(bytes).unwrap_or_else(|| match std::str::from_utf8(bytes) {
+                Ok(text) if !text.contains('\0') => (Encoding::Utf8, 0),
+                _ => (fallback, 0),
+            });
+        let text = decode(&bytes[bom..], encoding).map_err(|error| EncodingError {
+            offset: error.offset + bom,
+            ..error
+        })?;
+        Ok(DecodedText {
+            text,
+            encoding,
+            bom,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Map an offset in the decoded text to the offset in the original bytes.
+    pub fn byte_offset(&self, offset: usize) -> usize {
+        self.bom + self.encoding.encoded_len(&self.text[..offset])
+    }
+
+    /// Map a span in the decoded text, like the span of an event or error, to the span in the original bytes.
+    pub fn byte_span(&self, span: Span) -> Span {
+        self.byte_offset(span.start)..self.byte_offset(span.end)
+    }
+
+    pub fn into_text(self) -> Cow<'a, str> {
+        self.text
+    }
+}
+
+impl<'a> From<&'a DecodedText<'_>> for Reader<'a> {
+    fn from(decoded: &'a DecodedText<'_>) -> Self {
+        Reader::from(decoded.text())
+    }
+}
+
+fn decode(bytes: &[u8], encoding: Encoding) -> Result<Cow<'_, str>, EncodingError> {
+    match encoding {
+        Encoding::Utf8 => std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|error| EncodingError::new("invalid utf-8", error.valid_up_to())),
+        Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+        Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+        Encoding::Windows1252 => Ok(bytes.iter().map(|&byte| windows_1252(byte)).collect()),
+    }
+}
+
+fn decode_utf16(
+    bytes: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+) -> Result<Cow<'static, str>, EncodingError> {
+    if bytes.len() % 2 != 0 {
+        return Err(EncodingError::new("truncated utf-16", bytes.len() - 1));
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut offset = 0;
+    for char in char::decode_utf16(units) {
+        match char {
+            Ok(char) => {
+                offset += char.len_utf16() * 2;
+                text.push(char);
+            }
+            Err(_) => return Err(EncodingError::new("unpaired utf-16 surrogate", offset)),
+        }
+    }
+    Ok(Cow::Owned(text))
+}
+
+/// Windows-1252 only differs from Latin-1 in the `0x80..0xA0` range,
+/// the five bytes that are unassigned there map to the C1 control characters like Latin-1.
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž',
+        '\u{8F}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn test_detect() {
+        let decoded = DecodedText::decode(b"\xEF\xBB\xBFkey value", Encoding::Utf8).unwrap();
+        assert_eq!("key value", decoded.text());
+        assert_eq!(Encoding::Utf8, decoded.encoding());
+        assert_eq!(7..12, decoded.byte_span(4..9));
+
+        let bytes = utf16le("\"a\" \"ü\"");
+        let decoded = DecodedText::decode(&bytes, Encoding::Utf8).unwrap();
+        assert_eq!(Encoding::Utf16Le, decoded.encoding());
+        assert_eq!("\"a\" \"ü\"", decoded.text());
+        assert_eq!(10..16, decoded.byte_span(4..8));
+
+        let decoded = DecodedText::decode(b"caf\xE9 \x80", Encoding::Windows1252).unwrap();
+        assert_eq!("café €", decoded.text());
+        assert_eq!(5..6, decoded.byte_span(6..9));
+
+        let decoded = DecodedText::decode("café".as_bytes(), Encoding::Windows1252).unwrap();
+        assert_eq!(Encoding::Utf8, decoded.encoding());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let error = DecodedText::decode(b"ab\xFFcd", Encoding::Utf8).unwrap_err();
+        assert_eq!("invalid utf-8 at offset 2", error.to_string());
+        let error = DecodedText::decode(b"\xFF\xFEa\x00b", Encoding::Utf8).unwrap_err();
+        assert_eq!("truncated utf-16 at offset 4", error.to_string());
+    }
+}
@@ -1,5 +1,7 @@
-use super::Entry;
+use super::location::attach;
+use super::{Entry, EntryDeserializer, Location};
 use crate::entry::Value;
+use crate::serde::DeserializerOptions;
 use crate::VdfError;
 use serde::de::{DeserializeSeed, SeqAccess};
 use serde::{Deserialize, Serialize};
@@ -11,11 +13,12 @@ use std::ops::{Deref, DerefMut};
 pub struct Array(Vec<Entry>);
 
 impl Array {
-    pub(crate) fn from_space_separated(str: &str) -> Self {
+    /// The items all get the location of the bracketed value.
+    pub(crate) fn from_space_separated(str: &str, location: Option<Location>) -> Self {
         let items = str
             .split(' ')
             .filter(|part| !part.is_empty())
-            .map(Value::from)
+            .map(|part| Value::from(part).with_location(location.clone()))
             .map(Entry::from)
             .collect();
         Array(items)
@@ -55,12 +58,14 @@ impl DerefMut for Array {
 
 pub(crate) struct ArraySeq {
     iter: std::vec::IntoIter<Entry>,
+    options: DeserializerOptions,
 }
 
 impl ArraySeq {
-    pub(crate) fn new(array: Array) -> Self {
+    pub(crate) fn new(array: Array, options: DeserializerOptions) -> Self {
         ArraySeq {
             iter: array.0.into_iter(),
+            options,
         }
     }
 }
@@ -77,6 +82,11 @@ impl<'de> SeqAccess<'de> for ArraySeq {
             None => return Ok(None),
         };
 
-        seed.deserialize(next).map(Some)
+        let location = next.location().cloned();
+        attach(
+            seed.deserialize(EntryDeserializer::new(next, self.options)),
+            location.as_ref(),
+        )
+        .map(Some)
     }
 }
@@ -0,0 +1,286 @@
+use super::table::{load_entries, LoadEntries};
+use super::{Array, Entry, LoadOptions, Statement, Table, Value};
+use crate::entry::string_is_array;
+use crate::writer::{Writer, WriterOptions};
+use crate::{Condition, Event, Reader, Result};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// An entry in a [`BorrowedTable`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BorrowedEntry<'a> {
+    Table(BorrowedTable<'a>),
+    Value(Cow<'a, str>),
+    Statement(Cow<'a, str>),
+}
+
+impl<'a> BorrowedEntry<'a> {
+    pub fn as_table(&self) -> Option<&BorrowedTable<'a>> {
+        match self {
+            BorrowedEntry::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedEntry::Value(value) => Some(value),
+            BorrowedEntry::Statement(statement) => Some(statement),
+            BorrowedEntry::Table(_) => None,
+        }
+    }
+
+    /// Copy all borrowed strings, so the entry no longer borrows from the source.
+    pub fn into_owned(self) -> BorrowedEntry<'static> {
+        match self {
+            BorrowedEntry::Table(table) => BorrowedEntry::Table(table.into_owned()),
+            BorrowedEntry::Value(value) => BorrowedEntry::Value(value.into_owned().into()),
+            BorrowedEntry::Statement(statement) => {
+                BorrowedEntry::Statement(statement.into_owned().into())
+            }
+        }
+    }
+}
+
+impl<'a> From<BorrowedTable<'a>> for BorrowedEntry<'a> {
+    fn from(table: BorrowedTable<'a>) -> Self {
+        BorrowedEntry::Table(table)
+    }
+}
+
+impl<'a> From<&'a str> for BorrowedEntry<'a> {
+    fn from(value: &'a str) -> Self {
+        BorrowedEntry::Value(value.into())
+    }
+}
+
+/// Converts the entry to an owned entry, `"[a b c]"` values become arrays like they do in [`Table::load`].
+impl From<BorrowedEntry<'_>> for Entry {
+    fn from(entry: BorrowedEntry<'_>) -> Self {
+        match entry {
+            BorrowedEntry::Table(table) => Entry::Table(table.into()),
+            BorrowedEntry::Value(value) if string_is_array(&value) => {
+                Array::from_space_separated(value[1..value.len() - 1].trim(), None).into()
+            }
+            BorrowedEntry::Value(value) => Entry::Value(Value::from(value)),
+            BorrowedEntry::Statement(statement) => Entry::Statement(Statement::from(statement)),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct BorrowedTableEntry<'a> {
+    key: Cow<'a, str>,
+    value: BorrowedEntry<'a>,
+    condition: Option<Condition>,
+}
+
+/// A table that borrows its keys and values from the source text.
+///
+/// Only strings that had escape sequences decoded are allocated, which makes loading large files
+/// a lot cheaper than loading a [`Table`]. Like [`TypedTable`](super::TypedTable), repeated keys and
+/// `"[a b c]"` values are kept as they are, converting into a [`Table`] combines them.
+/// Tables loaded with [`LoadOptions::with_case_insensitive`] find keys with any casing.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct BorrowedTable<'a> {
+    entries: Vec<BorrowedTableEntry<'a>>,
+    case_insensitive: bool,
+}
+
+impl<'a> BorrowedTable<'a> {
+    pub fn load_from_str(input: &'a str) -> Result<BorrowedTable<'a>> {
+        Self::load_from_str_with_options(input, &LoadOptions::default())
+    }
+
+    /// Load a borrowed table from a string, locations aren't recorded even if enabled in the options.
+    pub fn load_from_str_with_options(
+        input: &'a str,
+        options: &LoadOptions,
+    ) -> Result<BorrowedTable<'a>> {
+        let mut reader = Reader::from(input).with_escape_mode(options.escape);
+        Self::load_with_options(&mut reader, options)
+    }
+
+    /// Load a borrowed table from the events of the given reader.
+    pub fn load<R: Iterator<Item = Result<Event<'a>>>>(
+        reader: &mut R,
+    ) -> Result<BorrowedTable<'a>> {
+        Self::load_with_options(reader, &LoadOptions::default())
+    }
+
+    /// Load a borrowed table from the events of the given reader with the given options.
+    ///
+    /// Values following the value of an entry on the same line are added as entries with the same key,
+    /// locations aren't recorded even if enabled in the options.
+    pub fn load_with_options<R: Iterator<Item = Result<Event<'a>>>>(
+        reader: &mut R,
+        options: &LoadOptions,
+    ) -> Result<BorrowedTable<'a>> {
+        load_entries(reader, options)
+    }
+
+    fn push_entry(
+        &mut self,
+        key: Cow<'a, str>,
+        value: BorrowedEntry<'a>,
+        condition: Option<Condition>,
+    ) {
+        self.entries.push(BorrowedTableEntry {
+            key,
+            value,
+            condition,
+        });
+    }
+
+    /// The number of entries in the table, repeated keys are counted separately.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether keys are compared regardless of case.
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    fn same_key(&self, a: &str, b: &str) -> bool {
+        if self.case_insensitive {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    }
+
+    /// Get the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&BorrowedEntry<'a>> {
+        self.entries
+            .iter()
+            .find(|entry| self.same_key(&entry.key, key))
+            .map(|entry| &entry.value)
+    }
+
+    /// Get all entries with the given key.
+    pub fn get_all<'t>(&'t self, key: &'t str) -> impl Iterator<Item = &'t BorrowedEntry<'a>> + 't {
+        self.entries
+            .iter()
+            .filter(move |entry| self.same_key(&entry.key, key))
+            .map(|entry| &entry.value)
+    }
+
+    /// Iterate over all entries in the order they are defined in.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &BorrowedEntry<'a>)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key.as_ref(), &entry.value))
+    }
+
+    /// Iterate over all entries in the order they are defined in, together with their condition.
+    pub fn iter_with_conditions(
+        &self,
+    ) -> impl Iterator<Item = (&str, &BorrowedEntry<'a>, Option<&Condition>)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key.as_ref(), &entry.value, entry.condition.as_ref()))
+    }
+
+    /// Add an entry at the end of the table.
+    pub fn push<K: Into<Cow<'a, str>>, V: Into<BorrowedEntry<'a>>>(&mut self, key: K, value: V) {
+        self.push_entry(key.into(), value.into(), None);
+    }
+
+    /// Copy all borrowed strings, so the table no longer borrows from the source.
+    pub fn into_owned(self) -> BorrowedTable<'static> {
+        BorrowedTable {
+            entries: self
+                .entries
+                .into_iter()
+                .map(|entry| BorrowedTableEntry {
+                    key: entry.key.into_owned().into(),
+                    value: entry.value.into_owned(),
+                    condition: entry.condition,
+                })
+                .collect(),
+            case_insensitive: self.case_insensitive,
+        }
+    }
+
+    /// Write the table as vdf text.
+    pub fn write_to<W: Write>(&self, writer: &mut W, options: &WriterOptions) -> fmt::Result {
+        self.write_entries(&mut Writer::new(writer, options))
+    }
+
+    fn write_entries<W: Write>(&self, writer: &mut Writer<W>) -> fmt::Result {
+        let key_width = writer.key_width(
+            self.entries
+                .iter()
+                .filter(|entry| !matches!(entry.value, BorrowedEntry::Table(_)))
+                .map(|entry| entry.key.as_ref()),
+        );
+        for entry in &self.entries {
+            let condition = entry.condition.as_ref();
+            match &entry.value {
+                BorrowedEntry::Table(table) => {
+                    writer.start_group(&entry.key, condition)?;
+                    table.write_entries(writer)?;
+                    writer.end_group()?;
+                }
+                BorrowedEntry::Value(value) | BorrowedEntry::Statement(value) => {
+                    writer.write_key_value(&entry.key, value, condition, key_width)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> LoadEntries<'a> for BorrowedTable<'a> {
+    fn new(options: &LoadOptions) -> Self {
+        BorrowedTable {
+            entries: Vec::new(),
+            case_insensitive: options.case_insensitive,
+        }
+    }
+
+    fn push_value(
+        &mut self,
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+        statement: bool,
+        condition: Option<Condition>,
+    ) {
+        let entry = match statement {
+            true => BorrowedEntry::Statement(value),
+            false => BorrowedEntry::Value(value),
+        };
+        self.push_entry(key, entry, condition);
+    }
+
+    fn push_group(&mut self, name: Cow<'a, str>, group: Self, condition: Option<Condition>) {
+        self.push_entry(name, group.into(), condition);
+    }
+}
+
+impl Display for BorrowedTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f, &WriterOptions::default())
+    }
+}
+
+/// Converts the table to an owned table, combining repeated keys like [`Table::load`] does.
+impl From<BorrowedTable<'_>> for Table {
+    fn from(table: BorrowedTable<'_>) -> Self {
+        let mut result = Table::default().with_case_insensitive(table.case_insensitive);
+        for BorrowedTableEntry {
+            key,
+            value,
+            condition,
+        } in table.entries
+        {
+            result.append(key, Entry::from(value), condition);
+        }
+        result
+    }
+}
@@ -0,0 +1,82 @@
+use crate::error::SerdeParseError;
+use crate::VdfError;
+use logos::Span;
+use std::fmt::{self, Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The source text an entry was loaded from, shared by all entries loaded from it.
+pub(crate) struct SourceFile {
+    path: Option<PathBuf>,
+    text: String,
+}
+
+impl SourceFile {
+    pub(crate) fn new(text: &str, path: Option<&Path>) -> Arc<SourceFile> {
+        Arc::new(SourceFile {
+            path: path.map(Path::to_path_buf),
+            text: text.into(),
+        })
+    }
+}
+
+/// Where an entry was defined, recorded when loading with [`LoadOptions::with_spans`](super::LoadOptions::with_spans).
+#[derive(Clone)]
+pub struct Location {
+    span: Span,
+    source: Arc<SourceFile>,
+}
+
+impl Location {
+    pub(crate) fn new(source: &Arc<SourceFile>, span: Span) -> Self {
+        Location {
+            span,
+            source: source.clone(),
+        }
+    }
+
+    /// The byte range of the entry in the source.
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// The file the entry was loaded from, if it was loaded from a file.
+    pub fn path(&self) -> Option<&Path> {
+        self.source.path.as_deref()
+    }
+
+    /// The full text the entry was loaded from.
+    pub fn source(&self) -> &str {
+        &self.source.text
+    }
+
+    /// Point an error without a location at this location.
+    pub(crate) fn attach(&self, error: VdfError) -> VdfError {
+        match error {
+            VdfError::ParseString(e) => {
+                SerdeParseError::new(e.ty, &e.value, self.span(), self.source()).into()
+            }
+            error => error.with_source_span_if_none(self.span(), self.source()),
+        }
+    }
+}
+
+impl Debug for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.path() {
+            Some(path) => write!(f, "{}@{:?}", path.display(), self.span),
+            None => write!(f, "{:?}", self.span),
+        }
+    }
+}
+
+/// Attach the location, if any, to the error of a result.
+pub(crate) fn attach<T>(
+    result: Result<T, VdfError>,
+    location: Option<&Location>,
+) -> Result<T, VdfError> {
+    match location {
+        Some(location) => result.map_err(|error| location.attach(error)),
+        None => result,
+    }
+}
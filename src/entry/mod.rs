@@ -1,18 +1,26 @@
 mod array;
+mod borrowed;
+mod location;
 mod statement;
 mod table;
+mod typed;
 mod value;
 
 use crate::error::{ParseEntryError, ParseItemError, ParseStringError, UnknownError};
-use crate::{Item, VdfError};
+use crate::writer::{bracketed, ArrayStyle, Writer, WriterOptions};
+use crate::{Condition, Item, VdfError};
 pub use array::Array;
+pub use borrowed::{BorrowedEntry, BorrowedTable};
+pub(crate) use location::attach;
+pub use location::Location;
+use logos::Span;
 pub use statement::Statement;
 use std::any::type_name;
-use std::collections::HashMap;
-use std::fmt::Formatter;
+use std::fmt::{self, Display, Formatter};
 use std::mem::swap;
 use std::slice;
-pub use table::Table;
+pub use table::{ConditionMode, LoadOptions, Table, TableIntoIter, TableIter};
+pub use typed::{TypeInference, TypedEntry, TypedTable, TypedValue};
 pub use value::Value;
 
 /// The kinds of entry.
@@ -43,18 +51,22 @@ impl From<Item<'_>> for Entry {
 
 impl Entry {
     /// Lookup an entry with a path.
+    ///
+    /// The names in the path are separated by `.`, keys that contain a `.` themselves,
+    /// like the file names used as keys in `.res` files, are found as well.
     pub fn lookup<S: AsRef<str>>(&self, path: S) -> Option<&Entry> {
-        let mut current = self;
+        let names: Vec<&str> = path.as_ref().split('.').collect();
+        self.lookup_names(&names)
+    }
 
-        for name in path.as_ref().split('.') {
-            if let Some(entry) = current.get(name.trim()) {
-                current = entry;
-            } else {
-                return None;
-            }
+    fn lookup_names(&self, names: &[&str]) -> Option<&Entry> {
+        if names.is_empty() {
+            return Some(self);
         }
-
-        Some(current)
+        (1..=names.len()).find_map(|count| {
+            let name = names[..count].join(".");
+            self.get(name.trim())?.lookup_names(&names[count..])
+        })
     }
 
     /// Try to get the named entry.
@@ -77,6 +89,32 @@ impl Entry {
         T::from_entry(self)
     }
 
+    /// Where the entry was defined, if it was loaded with spans.
+    ///
+    /// For arrays this is the location of the first item.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Entry::Table(table) => table.location(),
+            Entry::Array(array) => array.first().and_then(Entry::location),
+            Entry::Value(value) => value.location(),
+            Entry::Statement(statement) => statement.location(),
+        }
+    }
+
+    /// The byte range of the entry in its source, if it was loaded with spans.
+    ///
+    /// For arrays this covers all items.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Entry::Array(array) => {
+                let first = array.first()?.span()?;
+                let last = array.last()?.span()?;
+                Some(first.start..last.end)
+            }
+            entry => entry.location().map(Location::span),
+        }
+    }
+
     /// Try to take the entry as a table.
     pub fn as_table(&self) -> Option<&Table> {
         if let Entry::Table(value) = self {
@@ -122,6 +160,37 @@ impl Entry {
         }
     }
 
+    /// Try to read the entry as an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_value()?.parse().ok()
+    }
+
+    /// Try to read the entry as a float, integers are converted.
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_value()?.parse().ok()
+    }
+
+    /// Try to read the entry as a boolean.
+    ///
+    /// Like KeyValues, any integer other than `0` is `true`, `"true"` and `"false"` are accepted as well.
+    pub fn as_bool(&self) -> Option<bool> {
+        let value = self.as_value()?;
+        match value.parse::<i64>() {
+            Ok(value) => Some(value != 0),
+            Err(_) => value.parse().ok(),
+        }
+    }
+
+    /// Try to read the entry as a `"r g b a"` color, the alpha defaults to 255 if it's left out.
+    pub fn as_color(&self) -> Option<[u8; 4]> {
+        typed::parse_color(self.as_value()?)
+    }
+
+    /// The type of the value, inferred the way KeyValues does.
+    pub fn typed(&self) -> Option<TypedValue> {
+        self.as_value().map(|value| TypedValue::infer(value))
+    }
+
     pub fn parse<'a, T: Deserialize<'a>>(&'a self) -> Result<T, ParseEntryError> {
         let str = self
             .as_str()
@@ -148,6 +217,54 @@ impl Entry {
         }
     }
 
+    /// Write the entry as vdf text.
+    ///
+    /// Tables are written as top level key-values and arrays as a single `"[a b c]"` value,
+    /// arrays that can't be written as a single value result in an error.
+    pub fn write_to<W: fmt::Write>(&self, writer: &mut W, options: &WriterOptions) -> fmt::Result {
+        match self {
+            Entry::Table(table) => table.write_to(writer, options),
+            Entry::Array(array) => {
+                let value = bracketed(array.iter().map(Entry::as_str)).ok_or(fmt::Error)?;
+                Writer::new(writer, options).write_root_value(&value)
+            }
+            Entry::Value(value) => Writer::new(writer, options).write_root_value(value),
+            Entry::Statement(statement) => Writer::new(writer, options).write_root_value(statement),
+        }
+    }
+
+    pub(crate) fn write_with_key<W: fmt::Write>(
+        &self,
+        writer: &mut Writer<W>,
+        key: &str,
+        condition: Option<&Condition>,
+        key_width: usize,
+    ) -> fmt::Result {
+        match self {
+            Entry::Table(table) => {
+                writer.start_group(key, condition)?;
+                table.write_entries(writer)?;
+                writer.end_group()
+            }
+            Entry::Array(array) => {
+                let value = match writer.options().array {
+                    ArrayStyle::Bracketed => bracketed(array.iter().map(Entry::as_str)),
+                    ArrayStyle::RepeatedKeys => None,
+                };
+                match value {
+                    Some(value) => writer.write_key_value(key, &value, condition, key_width),
+                    None => array.iter().try_for_each(|item| {
+                        item.write_with_key(writer, key, condition, key_width)
+                    }),
+                }
+            }
+            Entry::Value(value) => writer.write_key_value(key, value, condition, key_width),
+            Entry::Statement(statement) => {
+                writer.write_key_value(key, statement, condition, key_width)
+            }
+        }
+    }
+
     /// Try to take the entry as a slice.
     pub fn push(&mut self, value: Entry) -> Result<(), ParseEntryError> {
         let mut tmp = Entry::Value(Value::default());
@@ -163,6 +280,12 @@ impl Entry {
     }
 }
 
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f, &WriterOptions::default())
+    }
+}
+
 /// Parsable types.
 pub trait ParseItem: Sized {
     /// Try to cast the entry into a concrete type
@@ -218,6 +341,7 @@ macro_rules! from_str {
 
 use crate::entry::array::ArraySeq;
 use crate::entry::table::TableSeq;
+use crate::serde::DeserializerOptions;
 use serde::de::{DeserializeSeed, EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
@@ -344,13 +468,13 @@ impl<'de> Deserialize<'de> for Entry {
             where
                 A: MapAccess<'v>,
             {
-                let mut res = HashMap::new();
+                let mut res = Vec::new();
 
-                while let Some(entry) = map.next_entry()? {
-                    res.insert(entry.0, entry.1);
+                while let Some(entry) = map.next_entry::<String, Entry>()? {
+                    res.push(entry);
                 }
 
-                Ok(Entry::Table(res.into()))
+                Ok(Entry::Table(res.into_iter().collect()))
             }
 
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -378,12 +502,7 @@ impl<'de> Deserializer<'de> for Entry {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Entry::Table(table) => visitor.visit_map(TableSeq::new(table)),
-            Entry::Array(array) => visitor.visit_seq(ArraySeq::new(array)),
-            Entry::Value(val) => val.deserialize_any(visitor),
-            Entry::Statement(val) => visitor.visit_string(val.into()),
-        }
+        EntryDeserializer::from(self).deserialize_any(visitor)
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -597,6 +716,151 @@ impl<'de> Deserializer<'de> for Entry {
         }
     }
 
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_map(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        EntryDeserializer::from(self).deserialize_ignored_any(visitor)
+    }
+}
+
+/// Deserializes an entry, inferring the types of values with the given options.
+pub(crate) struct EntryDeserializer {
+    entry: Entry,
+    options: DeserializerOptions,
+}
+
+impl EntryDeserializer {
+    pub(crate) fn new(entry: Entry, options: DeserializerOptions) -> Self {
+        EntryDeserializer { entry, options }
+    }
+}
+
+impl From<Entry> for EntryDeserializer {
+    fn from(entry: Entry) -> Self {
+        EntryDeserializer::new(entry, DeserializerOptions::default())
+    }
+}
+
+/// Values don't depend on the options when the type is known, so those are left to the entry.
+macro_rules! forward_to_entry {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.entry.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for EntryDeserializer {
+    type Error = VdfError;
+
+    forward_to_entry! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf deserialize_option deserialize_unit deserialize_identifier
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.entry {
+            Entry::Table(table) => visitor.visit_map(TableSeq::new(table, self.options, &[])),
+            Entry::Array(array) => visitor.visit_seq(ArraySeq::new(array, self.options)),
+            Entry::Value(val) => val.deserialize_any_with(visitor, &self.options),
+            Entry::Statement(val) => visitor.visit_string(val.into()),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.entry.deserialize_unit_struct(name, visitor)
+    }
+
     fn deserialize_newtype_struct<V>(
         self,
         _name: &'static str,
@@ -612,9 +876,10 @@ impl<'de> Deserializer<'de> for Entry {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Entry::Array(arr) => visitor.visit_seq(ArraySeq::new(arr)),
-            _ => Err(UnknownError::from("array2").into()),
+        match self.entry {
+            Entry::Array(arr) => visitor.visit_seq(ArraySeq::new(arr, self.options)),
+            // a key that only occurs once is a sequence with a single item
+            entry => visitor.visit_seq(ArraySeq::new(Array::from(vec![entry]), self.options)),
         }
     }
 
@@ -622,8 +887,8 @@ impl<'de> Deserializer<'de> for Entry {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Entry::Array(arr) => visitor.visit_seq(ArraySeq::new(arr)),
+        match self.entry {
+            Entry::Array(arr) => visitor.visit_seq(ArraySeq::new(arr, self.options)),
             _ => Err(UnknownError::from("tuple").into()),
         }
     }
@@ -637,8 +902,8 @@ impl<'de> Deserializer<'de> for Entry {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Entry::Array(arr) => visitor.visit_seq(ArraySeq::new(arr)),
+        match self.entry {
+            Entry::Array(arr) => visitor.visit_seq(ArraySeq::new(arr, self.options)),
             _ => Err(UnknownError::from("tuple_struct").into()),
         }
     }
@@ -647,8 +912,8 @@ impl<'de> Deserializer<'de> for Entry {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Entry::Table(table) => visitor.visit_map(TableSeq::new(table)),
+        match self.entry {
+            Entry::Table(table) => visitor.visit_map(TableSeq::new(table, self.options, &[])),
             _ => Err(UnknownError::from("map").into()),
         }
     }
@@ -656,19 +921,22 @@ impl<'de> Deserializer<'de> for Entry {
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        match self.entry {
+            Entry::Table(table) => visitor.visit_map(TableSeq::new(table, self.options, fields)),
+            _ => Err(UnknownError::from("map").into()),
+        }
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
@@ -676,10 +944,10 @@ impl<'de> Deserializer<'de> for Entry {
     {
         struct EnVarAccess {
             variant: Value,
-            value: Entry,
+            value: EntryDeserializer,
         }
         struct EnValAccess {
-            value: Entry,
+            value: EntryDeserializer,
         }
 
         impl<'de> EnumAccess<'de> for EnVarAccess {
@@ -718,35 +986,32 @@ impl<'de> Deserializer<'de> for Entry {
 
             fn struct_variant<V>(
                 self,
-                _fields: &'static [&'static str],
+                fields: &'static [&'static str],
                 visitor: V,
             ) -> Result<V::Value, Self::Error>
             where
                 V: Visitor<'de>,
             {
-                self.value.deserialize_map(visitor)
+                self.value.deserialize_struct("", fields, visitor)
             }
         }
 
-        match self {
+        match self.entry {
             Entry::Table(table) if table.len() == 1 => {
-                let (variant, value) = HashMap::from(table).into_iter().next().unwrap();
+                let (variant, value) = table.into_iter().next().unwrap();
+                let variant = match self.options.identifier(&variant, variants) {
+                    Some(name) => name.into(),
+                    None => variant.into(),
+                };
                 visitor.visit_enum(EnVarAccess {
-                    variant: variant.into(),
-                    value,
+                    variant,
+                    value: EntryDeserializer::new(value, self.options),
                 })
             }
             _ => Err(UnknownError::from("enum").into()),
         }
     }
 
-    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de>,
-    {
-        self.deserialize_string(visitor)
-    }
-
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
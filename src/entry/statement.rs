@@ -1,16 +1,16 @@
-use super::Entry;
+use super::{Entry, Location};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::ops::Deref;
 
 /// A statement.
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize, Default)]
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
 #[serde(transparent)]
-pub struct Statement(String);
+pub struct Statement(String, #[serde(skip)] Option<Location>);
 
 impl From<Cow<'_, str>> for Statement {
     fn from(value: Cow<'_, str>) -> Self {
-        Statement(value.into())
+        Statement(value.into(), None)
     }
 }
 
@@ -21,13 +21,13 @@ impl From<Statement> for Entry {
 }
 impl From<&'_ str> for Statement {
     fn from(value: &str) -> Statement {
-        Statement(value.into())
+        Statement(value.into(), None)
     }
 }
 
 impl From<String> for Statement {
     fn from(value: String) -> Statement {
-        Statement(value)
+        Statement(value, None)
     }
 }
 
@@ -37,6 +37,26 @@ impl From<Statement> for String {
     }
 }
 
+/// Statements are compared by their text, regardless of where they were loaded from.
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Statement {}
+
+impl Statement {
+    /// Where the statement was defined, if it was loaded with spans.
+    pub fn location(&self) -> Option<&Location> {
+        self.1.as_ref()
+    }
+
+    pub(crate) fn with_location(self, location: Option<Location>) -> Self {
+        Statement(self.0, location)
+    }
+}
+
 impl Deref for Statement {
     type Target = str;
 
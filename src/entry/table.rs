@@ -1,120 +1,906 @@
-use super::{Array, Entry};
+use super::location::{attach, SourceFile};
+use super::{Array, Entry, EntryDeserializer, Location, TypeInference};
 use crate::entry::{string_is_array, Statement, Value};
-use crate::error::UnknownError;
-use crate::event::{EntryEvent, GroupStartEvent, ValueContinuationEvent};
-use crate::{Event, Item, Reader, Result, VdfError};
-use serde::de::{DeserializeSeed, MapAccess};
-use serde::{Deserialize, Serialize, Serializer};
-use std::collections::hash_map;
+use crate::error::{UnexpectedTokenError, UnknownError};
+use crate::event::{EntryEvent, GroupEndEvent, GroupStartEvent, ValueContinuationEvent};
+use crate::serde::DeserializerOptions;
+use crate::writer::{Writer, WriterOptions};
+use crate::{
+    Condition, DecodedText, Defines, Encoding, EscapeMode, Event, Item, Reader, Resolver, Result,
+    Token, VdfError,
+};
+use logos::Span;
+use serde::de::{DeserializeSeed, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::ops::{Deref, DerefMut};
+use std::convert::Infallible;
+use std::fmt::{self, Display, Formatter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// How conditions like `[$WIN32]` are handled when loading a table.
+#[derive(Clone, Debug, Default)]
+pub enum ConditionMode {
+    /// Keep all entries, storing the condition alongside the entry.
+    #[default]
+    Keep,
+    /// Only keep the entries and groups for which the condition holds with the given defines.
+    Evaluate(Defines),
+}
+
+/// Options for loading a table.
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    pub conditions: ConditionMode,
+    /// How deep `#base` and `#include` directives can be nested.
+    pub max_include_depth: usize,
+    /// How escape sequences in quoted strings are decoded.
+    pub escape: EscapeMode,
+    /// Record the location of every entry when loading from a string or file.
+    pub spans: bool,
+    /// Compare keys regardless of case, see [`Table::with_case_insensitive`].
+    pub case_insensitive: bool,
+    /// The encoding of byte input without a byte order mark, see [`DecodedText::decode`].
+    pub encoding: Encoding,
+    /// Types to infer for a [`TypedTable`](super::TypedTable) besides the ones KeyValues infers.
+    pub types: TypeInference,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            conditions: ConditionMode::default(),
+            max_include_depth: 32,
+            escape: EscapeMode::default(),
+            spans: false,
+            case_insensitive: false,
+            encoding: Encoding::default(),
+            types: TypeInference::default(),
+        }
+    }
+}
+
+impl LoadOptions {
+    pub fn with_conditions(self, conditions: ConditionMode) -> Self {
+        LoadOptions { conditions, ..self }
+    }
+
+    pub fn with_escape(self, escape: EscapeMode) -> Self {
+        LoadOptions { escape, ..self }
+    }
+
+    pub fn with_spans(self, spans: bool) -> Self {
+        LoadOptions { spans, ..self }
+    }
+
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        LoadOptions {
+            case_insensitive,
+            ..self
+        }
+    }
+
+    pub fn with_encoding(self, encoding: Encoding) -> Self {
+        LoadOptions { encoding, ..self }
+    }
+
+    pub fn with_types(self, types: TypeInference) -> Self {
+        LoadOptions { types, ..self }
+    }
+
+    pub fn with_max_include_depth(self, max_include_depth: usize) -> Self {
+        LoadOptions {
+            max_include_depth,
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TableEntry {
+    key: String,
+    value: Entry,
+    condition: Option<Condition>,
+    key_location: Option<Location>,
+}
+
+impl TableEntry {
+    fn new(key: String, value: Entry) -> Self {
+        TableEntry {
+            key,
+            value,
+            condition: None,
+            key_location: None,
+        }
+    }
+}
+
+impl PartialEq for TableEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.value == other.value && self.condition == other.condition
+    }
+}
+
+impl Eq for TableEntry {}
 
 /// A table of entries.
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize, Default)]
-#[serde(transparent)]
-pub struct Table(#[serde(serialize_with = "ordered_map")] HashMap<String, Entry>);
+///
+/// The entries are kept in the order they are defined in.
+/// Keys repeated directly after each other are combined into an [`Array`],
+/// other repeated keys are kept as separate entries at their original position.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    entries: Vec<TableEntry>,
+    location: Option<Location>,
+    case_insensitive: bool,
+}
+
+/// Tables are compared by their entries, regardless of where they were loaded from.
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for Table {}
 
 impl From<HashMap<String, Entry>> for Table {
     fn from(value: HashMap<String, Entry>) -> Self {
-        Table(value)
+        let mut entries: Vec<_> = value.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.into()
     }
 }
 
-fn ordered_map<S, K: Ord + Serialize, V: Serialize>(
-    value: &HashMap<K, V>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    use std::collections::BTreeMap;
-    let ordered: BTreeMap<_, _> = value.iter().collect();
-    ordered.serialize(serializer)
+impl From<Vec<(String, Entry)>> for Table {
+    fn from(value: Vec<(String, Entry)>) -> Self {
+        Table {
+            entries: value
+                .into_iter()
+                .map(|(key, value)| TableEntry::new(key, value))
+                .collect(),
+            location: None,
+            case_insensitive: false,
+        }
+    }
 }
 
-fn insert<K: Into<String>, V: Into<Entry>>(map: &mut HashMap<String, Entry>, key: K, value: V) {
-    let key = key.into();
-    let value = value.into();
-    let entry = map.entry(key);
-    match entry {
-        hash_map::Entry::Vacant(entry) => {
-            entry.insert(value);
+impl<K: Into<String>, V: Into<Entry>> FromIterator<(K, V)> for Table {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut table = Table::default();
+        for (key, value) in iter {
+            table.append(key, value, None);
         }
-        hash_map::Entry::Occupied(mut entry) => match entry.get_mut() {
-            Entry::Array(ref mut array) => {
-                array.push(value);
-            }
-            _ => {
-                let (key, old_value) = entry.remove_entry();
-                let mut array = Array::from(old_value);
-                array.push(value);
-                map.insert(key, Entry::Array(array));
+        table
+    }
+}
+
+fn same_key(case_insensitive: bool, a: &str, b: &str) -> bool {
+    if case_insensitive {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+/// Set the case sensitivity of all tables in an entry
+fn set_case_insensitive(entry: &mut Entry, case_insensitive: bool) {
+    match entry {
+        Entry::Table(table) => table.set_case_insensitive(case_insensitive),
+        Entry::Array(array) => {
+            for item in array.iter_mut() {
+                set_case_insensitive(item, case_insensitive);
             }
-        },
+        }
+        _ => {}
+    }
+}
+
+/// Add a value to an entry, turning the entry into an array if needed
+fn merge(existing: &mut Entry, value: Entry) {
+    match existing {
+        Entry::Array(ref mut array) => {
+            array.push(value);
+        }
+        _ => {
+            let old_value = std::mem::replace(existing, Entry::Array(Array::default()));
+            let mut array = Array::from(old_value);
+            array.push(value);
+            *existing = Entry::Array(array);
+        }
     }
 }
 
 impl Table {
     pub fn load_from_str(input: &str) -> Result<Table> {
-        let mut reader = Reader::from(input);
-        Self::load(&mut reader)
+        Self::load_from_str_with_options(input, &LoadOptions::default())
+    }
+
+    /// Load a table from a string.
+    ///
+    /// With [`LoadOptions::with_spans`] the location of every entry is recorded, so errors from
+    /// [`from_entry`](crate::from_entry) point back into `input`.
+    pub fn load_from_str_with_options(input: &str, options: &LoadOptions) -> Result<Table> {
+        Self::load_from_file_str(input, None, options)
+    }
+
+    /// Load a table from bytes, detecting the encoding from the byte order mark.
+    pub fn load_bytes(input: &[u8]) -> Result<Table> {
+        Self::load_bytes_with_options(input, &LoadOptions::default())
+    }
+
+    /// Load a table from bytes, input without a byte order mark that isn't valid UTF-8 is decoded
+    /// using [`LoadOptions::encoding`].
+    ///
+    /// Spans of entries and errors point into the decoded text, [`DecodedText::byte_span`] maps them to the bytes.
+    pub fn load_bytes_with_options(input: &[u8], options: &LoadOptions) -> Result<Table> {
+        let decoded = DecodedText::decode(input, options.encoding)?;
+        Self::load_from_str_with_options(decoded.text(), options)
+    }
+
+    pub(crate) fn load_from_file_str(
+        input: &str,
+        path: Option<&Path>,
+        options: &LoadOptions,
+    ) -> Result<Table> {
+        let mut reader = Reader::from(input).with_escape_mode(options.escape);
+        let mut loader = Loader::new(options, FirstError);
+        loader.source = options.spans.then(|| SourceFile::new(input, path));
+        loader.load(&mut reader, None)
+    }
+
+    /// Load a table from a string, continuing after errors.
+    ///
+    /// Instead of stopping at the first error, entries that can't be read are skipped and
+    /// unbalanced braces are closed or ignored. This returns everything that could be read
+    /// together with all errors found, [`ParseErrors`](crate::error::ParseErrors) reports them at once.
+    pub fn load_recovering(input: &str, options: &LoadOptions) -> (Table, Vec<VdfError>) {
+        let mut reader = Reader::from(input).with_escape_mode(options.escape);
+        let errors = Recover {
+            source: input,
+            errors: Vec::new(),
+        };
+        let mut loader = Loader::new(options, errors);
+        loader.source = options.spans.then(|| SourceFile::new(input, None));
+        let table = match loader.load(&mut reader, None) {
+            Ok(table) => table,
+            Err(never) => match never {},
+        };
+        (table, loader.errors.errors)
+    }
+
+    /// Load a table from the events of the given reader.
+    ///
+    /// This accepts a text [`Reader`] or any other source of events like a
+    /// [`BinaryReader`](crate::binary::BinaryReader).
+    pub fn load<'a, R: Iterator<Item = Result<Event<'a>>>>(reader: &mut R) -> Result<Table> {
+        Self::load_with_options(reader, &LoadOptions::default())
+    }
+
+    /// Load a table from a file, following the `#base` and `#include` directives at its top level.
+    ///
+    /// Entries from `#base` files are only added for keys that aren't set yet,
+    /// while entries from `#include` files override existing keys.
+    /// Nested groups are merged recursively in both cases.
+    pub fn load_with_includes<P: AsRef<Path>, R: Resolver>(
+        path: P,
+        resolver: &R,
+        options: &LoadOptions,
+    ) -> Result<Table> {
+        crate::include::load(path.as_ref(), resolver, options)
+    }
+
+    /// Load a table from the events of the given reader with the given options.
+    ///
+    /// Escape sequences are decoded using the escape mode of the reader.
+    pub fn load_with_options<'a, R: Iterator<Item = Result<Event<'a>>>>(
+        reader: &mut R,
+        options: &LoadOptions,
+    ) -> Result<Table> {
+        Loader::new(options, FirstError).load(reader, None)
+    }
+
+    /// Add an entry to the end of the table.
+    ///
+    /// If the last entry has the same key and condition, the value is added to it as an array instead.
+    pub(crate) fn append<K: Into<String>, V: Into<Entry>>(
+        &mut self,
+        key: K,
+        value: V,
+        condition: Option<Condition>,
+    ) {
+        self.append_located(key, value, condition, None)
+    }
+
+    fn append_located<K: Into<String>, V: Into<Entry>>(
+        &mut self,
+        key: K,
+        value: V,
+        condition: Option<Condition>,
+        key_location: Option<Location>,
+    ) {
+        let key = key.into();
+        let value = value.into();
+        let case_insensitive = self.case_insensitive;
+        match self.entries.last_mut() {
+            Some(last)
+                if same_key(case_insensitive, &last.key, &key) && last.condition == condition =>
+            {
+                merge(&mut last.value, value)
+            }
+            _ => self.entries.push(TableEntry {
+                key,
+                value,
+                condition,
+                key_location,
+            }),
+        }
+    }
+
+    /// Where the group was defined, from the opening to the closing brace, if it was loaded with spans.
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// Compare keys regardless of case, like the engine does.
+    ///
+    /// Lookups like [`get`](Self::get) and [`remove`](Self::remove) then find keys with any casing,
+    /// and keys that only differ by case are combined like repeated keys. The keys keep the spelling they
+    /// were added with. This applies to all nested tables, tables loaded with
+    /// [`LoadOptions::with_case_insensitive`] have it set from the start.
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.set_case_insensitive(case_insensitive);
+        self
+    }
+
+    fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+        for entry in &mut self.entries {
+            set_case_insensitive(&mut entry.value, case_insensitive);
+        }
+    }
+
+    /// Whether keys are compared regardless of case.
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    fn same_key(&self, a: &str, b: &str) -> bool {
+        same_key(self.case_insensitive, a, b)
+    }
+
+    /// Where the first entry with the given key was defined, if it was loaded with spans.
+    pub fn key_location(&self, key: &str) -> Option<&Location> {
+        self.entries
+            .iter()
+            .find(|entry| self.same_key(&entry.key, key))
+            .and_then(|entry| entry.key_location.as_ref())
+    }
+
+    /// The number of entries in the table, repeated keys are counted separately.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| self.same_key(&entry.key, key))
+    }
+
+    /// Get the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| self.same_key(&entry.key, key))
+            .map(|entry| &entry.value)
+    }
+
+    /// Get the first entry with the given key.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        let case_insensitive = self.case_insensitive;
+        self.entries
+            .iter_mut()
+            .find(|entry| same_key(case_insensitive, &entry.key, key))
+            .map(|entry| &mut entry.value)
+    }
+
+    /// Get all entries with the given key, in the order they are defined in.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Entry> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| self.same_key(&entry.key, key))
+            .map(|entry| &entry.value)
+    }
+
+    /// Get the condition of the first entry with the given key.
+    pub fn condition(&self, key: &str) -> Option<&Condition> {
+        self.entries
+            .iter()
+            .find(|entry| self.same_key(&entry.key, key))
+            .and_then(|entry| entry.condition.as_ref())
+    }
+
+    /// Iterate over all entries in the order they are defined in.
+    pub fn iter(&self) -> TableIter<'_> {
+        TableIter(self.entries.iter())
+    }
+
+    /// Iterate over all entries in the order they are defined in, the same as [`iter`](Self::iter).
+    pub fn iter_in_order(&self) -> TableIter<'_> {
+        self.iter()
+    }
+
+    /// Iterate over all entries in the order they are defined in, together with their condition.
+    pub fn iter_with_conditions(&self) -> impl Iterator<Item = (&str, &Entry, Option<&Condition>)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), &entry.value, entry.condition.as_ref()))
+    }
+
+    /// Iterate over all entries in the order they are defined in.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Entry)> {
+        self.entries
+            .iter_mut()
+            .map(|entry| (entry.key.as_str(), &mut entry.value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.key.as_str())
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().map(|entry| &entry.value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.entries.iter_mut().map(|entry| &mut entry.value)
+    }
+
+    /// Set the value for a key.
+    ///
+    /// The value of the first entry with the key is replaced in place, keeping its key, condition and location,
+    /// and any other entries with the key are removed.
+    /// If the key doesn't exist yet, the entry is added to the end of the table.
+    pub fn insert<K: Into<String>, V: Into<Entry>>(&mut self, key: K, value: V) -> Option<Entry> {
+        let key = key.into();
+        let value = self.adopt(value.into());
+        match self
+            .entries
+            .iter()
+            .position(|entry| self.same_key(&entry.key, &key))
+        {
+            Some(index) => {
+                let old = std::mem::replace(&mut self.entries[index].value, value);
+                let case_insensitive = self.case_insensitive;
+                let mut position = 0;
+                self.entries.retain(|entry| {
+                    let keep = position <= index || !same_key(case_insensitive, &entry.key, &key);
+                    position += 1;
+                    keep
+                });
+                Some(old)
+            }
+            None => {
+                self.entries.push(TableEntry::new(key, value));
+                None
+            }
+        }
+    }
+
+    /// Add an entry at the end of the table, without replacing existing entries with the same key.
+    pub fn push<K: Into<String>, V: Into<Entry>>(&mut self, key: K, value: V) {
+        let value = self.adopt(value.into());
+        self.entries.push(TableEntry::new(key.into(), value));
+    }
+
+    /// Add an entry with a condition at the end of the table.
+    pub fn push_with_condition<K: Into<String>, V: Into<Entry>>(
+        &mut self,
+        key: K,
+        value: V,
+        condition: Condition,
+    ) {
+        let value = self.adopt(value.into());
+        self.entries.push(TableEntry {
+            key: key.into(),
+            value,
+            condition: Some(condition),
+            key_location: None,
+        });
+    }
+
+    /// Insert an entry at the given position, without replacing existing entries with the same key.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert_at<K: Into<String>, V: Into<Entry>>(&mut self, index: usize, key: K, value: V) {
+        let value = self.adopt(value.into());
+        self.entries
+            .insert(index, TableEntry::new(key.into(), value));
+    }
+
+    /// Make nested tables of a value added to a case-insensitive table case-insensitive as well.
+    fn adopt(&self, mut value: Entry) -> Entry {
+        if self.case_insensitive {
+            set_case_insensitive(&mut value, true);
+        }
+        value
+    }
+
+    /// Remove all entries with the given key, returning the first removed entry.
+    pub fn remove(&mut self, key: &str) -> Option<Entry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| self.same_key(&entry.key, key))?;
+        let removed = self.entries.remove(index);
+        let case_insensitive = self.case_insensitive;
+        self.entries
+            .retain(|entry| !same_key(case_insensitive, &entry.key, key));
+        Some(removed.value)
+    }
+
+    /// Remove the entry at the given position.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_at(&mut self, index: usize) -> (String, Entry) {
+        let removed = self.entries.remove(index);
+        (removed.key, removed.value)
+    }
+
+    /// Only keep the entries for which the predicate returns `true`.
+    pub fn retain<F: FnMut(&str, &Entry) -> bool>(&mut self, mut f: F) {
+        self.entries.retain(|entry| f(&entry.key, &entry.value));
+    }
+
+    /// Combine all entries with the same key into a single entry at the position of the first one.
+    ///
+    /// The merged entries keep the key location of the first entry.
+    fn into_merged(self) -> Vec<TableEntry> {
+        let mut merged: Vec<TableEntry> = Vec::with_capacity(self.entries.len());
+        let mut positions: HashMap<String, usize> = HashMap::with_capacity(self.entries.len());
+        for entry in self.entries {
+            let key = match self.case_insensitive {
+                true => entry.key.to_ascii_lowercase(),
+                false => entry.key.clone(),
+            };
+            match positions.get(&key) {
+                Some(&index) => merge(&mut merged[index].value, entry.value),
+                None => {
+                    positions.insert(key, merged.len());
+                    merged.push(entry);
+                }
+            }
+        }
+        merged
+    }
+
+    /// Write the table as vdf text, the entries of the table are written as top level key-values.
+    pub fn write_to<W: Write>(&self, writer: &mut W, options: &WriterOptions) -> fmt::Result {
+        self.write_entries(&mut Writer::new(writer, options))
+    }
+
+    pub(crate) fn write_entries<W: Write>(&self, writer: &mut Writer<W>) -> fmt::Result {
+        let key_width = writer.key_width(
+            self.entries
+                .iter()
+                .filter(|entry| !matches!(entry.value, Entry::Table(_)))
+                .map(|entry| entry.key.as_str()),
+        );
+        for entry in &self.entries {
+            entry
+                .value
+                .write_with_key(writer, &entry.key, entry.condition.as_ref(), key_width)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the [`Loader`] handles errors.
+trait Errors {
+    type Error;
+
+    fn error(&mut self, error: VdfError) -> Result<(), Self::Error>;
+
+    /// The source text, if errors are collected while recovering.
+    fn recovering(&self) -> Option<&str>;
+}
+
+/// Stop at the first error.
+struct FirstError;
+
+impl Errors for FirstError {
+    type Error = VdfError;
+
+    fn error(&mut self, error: VdfError) -> Result<()> {
+        Err(error)
+    }
+
+    fn recovering(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Collect all errors and keep going.
+struct Recover<'s> {
+    source: &'s str,
+    errors: Vec<VdfError>,
+}
+
+impl Errors for Recover<'_> {
+    type Error = Infallible;
+
+    fn error(&mut self, error: VdfError) -> Result<(), Infallible> {
+        self.errors.push(error);
+        Ok(())
+    }
+
+    fn recovering(&self) -> Option<&str> {
+        Some(self.source)
+    }
+}
+
+/// Builds a table from events, optionally recording locations and collecting errors.
+struct Loader<'l, E> {
+    options: &'l LoadOptions,
+    source: Option<Arc<SourceFile>>,
+    errors: E,
+}
+
+impl<'l, E: Errors> Loader<'l, E> {
+    fn new(options: &'l LoadOptions, errors: E) -> Self {
+        Loader {
+            options,
+            source: None,
+            errors,
+        }
+    }
+
+    fn locate(&self, span: Span) -> Option<Location> {
+        self.source
+            .as_ref()
+            .map(|source| Location::new(source, span))
+    }
+
+    fn error(&mut self, error: VdfError) -> Result<(), E::Error> {
+        self.errors.error(error)
     }
 
-    /// Load a table from the given `Reader`.
-    pub fn load(reader: &mut Reader) -> Result<Table> {
-        let mut map = HashMap::new();
+    /// Load the entries of a group, `start` is the position of the opening brace or `None` for the root.
+    fn load<'a, R: Iterator<Item = Result<Event<'a>>>>(
+        &mut self,
+        reader: &mut R,
+        start: Option<usize>,
+    ) -> Result<Table, E::Error> {
+        let options = self.options;
+        let mut map = Table {
+            case_insensitive: options.case_insensitive,
+            ..Table::default()
+        };
         let mut last_key = None;
 
-        while let Some(event) = reader.event() {
-            last_key = match event? {
+        while let Some(event) = reader.next() {
+            let event = match event {
+                Ok(event) => event,
+                Err(error) => {
+                    self.error(error)?;
+                    last_key = None;
+                    continue;
+                }
+            };
+            last_key = match event {
                 Event::Entry(EntryEvent {
-                    key: Item::Item { content: key, .. },
+                    key:
+                        Item::Item {
+                            content: key,
+                            span: key_span,
+                        },
                     value,
+                    condition,
                     ..
-                }) => {
-                    let str = value.as_str();
-                    let key_clone = key.clone();
-                    if string_is_array(str) {
-                        insert(
-                            &mut map,
-                            key,
-                            Array::from_space_separated(str[1..str.len() - 1].trim()),
-                        )
-                    } else {
-                        insert(&mut map, key, Value::from(value.into_content()))
+                }) => match options.resolve(condition) {
+                    Some(condition) => {
+                        let str = value.as_str();
+                        let key_clone = key.clone();
+                        let location = self.locate(value.span());
+                        let value: Entry = if string_is_array(str) {
+                            Array::from_space_separated(str[1..str.len() - 1].trim(), location)
+                                .into()
+                        } else {
+                            Value::from(value.into_content())
+                                .with_location(location)
+                                .into()
+                        };
+                        map.append_located(key, value, condition, self.locate(key_span));
+                        Some(key_clone)
                     }
-                    Some(key_clone)
-                }
+                    None => None,
+                },
 
                 Event::Entry(EntryEvent {
-                    key: Item::Statement { content: key, .. },
+                    key:
+                        Item::Statement {
+                            content: key,
+                            span: key_span,
+                        },
                     value,
+                    condition,
                     ..
-                }) => {
-                    let key_clone = key.clone();
-                    insert(&mut map, key, Statement::from(value.into_content()));
-                    Some(key_clone)
-                }
+                }) => match options.resolve(condition) {
+                    Some(condition) => {
+                        let key_clone = key.clone();
+                        let location = self.locate(value.span());
+                        let value = Statement::from(value.into_content()).with_location(location);
+                        map.append_located(key, value, condition, self.locate(key_span));
+                        Some(key_clone)
+                    }
+                    None => None,
+                },
 
-                Event::ValueContinuation(ValueContinuationEvent { value, .. }) => {
-                    if let Some(key) = last_key.as_ref() {
-                        if let Some(last_value) = map.get_mut(key.as_ref()) {
-                            last_value.push(Value::from(value.into_content()).into())?;
+                Event::ValueContinuation(ValueContinuationEvent { value, span }) => {
+                    if last_key.is_some() {
+                        if let Some(last) = map.entries.last_mut() {
+                            let value =
+                                Value::from(value.into_content()).with_location(self.locate(span));
+                            if let Err(error) = last.value.push(value.into()) {
+                                self.error(error.into())?;
+                            }
                         }
                     }
                     last_key
                 }
 
-                Event::GroupStart(GroupStartEvent { name, .. }) => {
-                    insert(&mut map, name, Table::load(reader)?);
+                Event::GroupStart(GroupStartEvent {
+                    name,
+                    condition,
+                    span,
+                }) => {
+                    let table = self.load(reader, Some(span.start))?;
+                    if let Some(condition) = options.resolve(condition) {
+                        map.append(name, table, condition);
+                    }
                     None
                 }
 
-                Event::GroupEnd(_) => break,
+                Event::GroupEnd(GroupEndEvent { span }) => {
+                    match (start, self.errors.recovering()) {
+                        (Some(start), _) => {
+                            map.location = self.locate(start..span.end);
+                            return Ok(map);
+                        }
+                        // a closing brace without a group to close
+                        (None, Some(source)) => {
+                            let error = UnexpectedTokenError::new(
+                                KEY_TOKENS,
+                                Some(Token::GroupEnd),
+                                span.into(),
+                                source.into(),
+                            );
+                            self.error(error.into())?;
+                            None
+                        }
+                        (None, None) => break,
+                    }
+                }
+            }
+        }
+
+        if let (Some(start), Some(source)) = (start, self.errors.recovering()) {
+            let error = UnexpectedTokenError::new(
+                &[Token::GroupEnd],
+                None,
+                (start..start + 1).into(),
+                source.into(),
+            );
+            self.error(error.into())?;
+        }
+
+        Ok(map)
+    }
+}
+
+const KEY_TOKENS: &[Token] = &[
+    Token::Item,
+    Token::QuotedItem,
+    Token::Statement,
+    Token::QuotedStatement,
+];
+
+impl LoadOptions {
+    /// Decide if an entry with the given condition should be kept, and with which condition.
+    pub(crate) fn resolve(&self, condition: Option<Condition>) -> Option<Option<Condition>> {
+        match (&self.conditions, condition) {
+            (_, None) => Some(None),
+            (ConditionMode::Keep, condition) => Some(condition),
+            (ConditionMode::Evaluate(defines), Some(condition)) => {
+                condition.evaluate(defines).then_some(None)
+            }
+        }
+    }
+}
+
+/// A table that keeps every entry as it was loaded, without combining repeated keys or parsing arrays.
+pub(crate) trait LoadEntries<'a>: Sized {
+    fn new(options: &LoadOptions) -> Self;
+
+    fn push_value(
+        &mut self,
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+        statement: bool,
+        condition: Option<Condition>,
+    );
+
+    fn push_group(&mut self, name: Cow<'a, str>, group: Self, condition: Option<Condition>);
+}
+
+/// Load the entries of a group into a [`LoadEntries`] table.
+///
+/// Values following the value of an entry on the same line are added as entries with the same key.
+/// Locations aren't recorded, even if [`LoadOptions::spans`] is set.
+pub(crate) fn load_entries<'a, T: LoadEntries<'a>, R: Iterator<Item = Result<Event<'a>>>>(
+    reader: &mut R,
+    options: &LoadOptions,
+) -> Result<T> {
+    let mut table = T::new(options);
+    let mut last = None;
+
+    while let Some(event) = reader.next() {
+        last = match event? {
+            Event::Entry(EntryEvent {
+                key,
+                value,
+                condition,
+                ..
+            }) => options.resolve(condition).map(|condition| {
+                let statement = matches!(key, Item::Statement { .. });
+                let key = key.into_content();
+                table.push_value(
+                    key.clone(),
+                    value.into_content(),
+                    statement,
+                    condition.clone(),
+                );
+                (key, condition)
+            }),
+
+            Event::ValueContinuation(ValueContinuationEvent { value, .. }) => {
+                if let Some((key, condition)) = &last {
+                    table.push_value(key.clone(), value.into_content(), false, condition.clone());
+                }
+                last
+            }
+
+            Event::GroupStart(GroupStartEvent {
+                name, condition, ..
+            }) => {
+                let group = load_entries(reader, options)?;
+                if let Some(condition) = options.resolve(condition) {
+                    table.push_group(name, group, condition);
+                }
+                None
             }
+
+            Event::GroupEnd(_) => break,
         }
+    }
+
+    Ok(table)
+}
 
-        Ok(Table(map))
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f, &WriterOptions::default())
     }
 }
 
@@ -124,36 +910,138 @@ impl From<Table> for Entry {
     }
 }
 
+/// Repeated keys are combined into an [`Array`].
 impl From<Table> for HashMap<String, Entry> {
     fn from(table: Table) -> Self {
-        table.0
+        table
+            .into_merged()
+            .into_iter()
+            .map(|entry| (entry.key, entry.value))
+            .collect()
+    }
+}
+
+impl IntoIterator for Table {
+    type Item = (String, Entry);
+    type IntoIter = TableIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TableIntoIter(self.entries.into_iter())
+    }
+}
+
+impl<'a> IntoIterator for &'a Table {
+    type Item = (&'a str, &'a Entry);
+    type IntoIter = TableIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TableIter(self.entries.iter())
+    }
+}
+
+/// Owning iterator over the entries of a [`Table`], in order.
+pub struct TableIntoIter(std::vec::IntoIter<TableEntry>);
+
+impl Iterator for TableIntoIter {
+    type Item = (String, Entry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (entry.key, entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
 }
 
-impl Deref for Table {
-    type Target = HashMap<String, Entry>;
+/// Iterator over the entries of a [`Table`], in order.
+pub struct TableIter<'a>(std::slice::Iter<'a, TableEntry>);
+
+impl<'a> Iterator for TableIter<'a> {
+    type Item = (&'a str, &'a Entry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|entry| (entry.key.as_str(), &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// Serializes the table as a map in the order the entries are defined in.
+///
+/// Repeated keys that aren't directly after each other are kept at their position,
+/// so the map can contain the same key more than once.
+impl Serialize for Table {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        for entry in &self.entries {
+            map.serialize_entry(&entry.key, &entry.value)?;
+        }
+        map.end()
     }
 }
 
-impl DerefMut for Table {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl<'de> Deserialize<'de> for Table {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TableVisitor;
+
+        impl<'v> Visitor<'v> for TableVisitor {
+            type Value = Table;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                write!(formatter, "a group")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'v>,
+            {
+                let mut table = Table::default();
+                while let Some((key, value)) = map.next_entry::<String, Entry>()? {
+                    table.append(key, value, None);
+                }
+                Ok(table)
+            }
+        }
+
+        deserializer.deserialize_map(TableVisitor)
     }
 }
 
 pub(crate) struct TableSeq {
-    iter: hash_map::IntoIter<String, Entry>,
-    next_item: Option<Entry>,
+    iter: std::vec::IntoIter<TableEntry>,
+    next_item: Option<(Entry, Option<Location>)>,
+    options: DeserializerOptions,
 }
 
 impl TableSeq {
-    pub(crate) fn new(table: Table) -> Self {
+    /// Repeated keys are combined, since serde expects every key only once.
+    ///
+    /// For structs, keys that match a field regardless of case are renamed to the field if enabled in the options.
+    pub(crate) fn new(
+        mut table: Table,
+        options: DeserializerOptions,
+        fields: &'static [&'static str],
+    ) -> Self {
+        for entry in &mut table.entries {
+            if let Some(field) = options.identifier(&entry.key, fields) {
+                entry.key = field.into();
+            }
+        }
         TableSeq {
-            iter: table.0.into_iter(),
+            iter: table.into_merged().into_iter(),
             next_item: None,
+            options,
         }
     }
 }
@@ -165,26 +1053,32 @@ impl<'de> MapAccess<'de> for TableSeq {
     where
         K: DeserializeSeed<'de>,
     {
-        let (key, value) = match self.iter.next() {
-            Some(pair) => pair,
+        let entry = match self.iter.next() {
+            Some(entry) => entry,
             None => {
                 return Ok(None);
             }
         };
-        self.next_item = Some(value);
-        seed.deserialize(Value::from(key)).map(Some)
+        let key = Value::from(entry.key).with_location(entry.key_location.clone());
+        self.next_item = Some((entry.value, entry.key_location));
+        seed.deserialize(key).map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: DeserializeSeed<'de>,
     {
-        let item = match self.next_item.take() {
+        let (item, key_location) = match self.next_item.take() {
             Some(item) => item,
             None => return Err(UnknownError::from("double take value").into()),
         };
 
-        seed.deserialize(item)
+        // errors for values without a location of their own point at the key
+        let location = item.location().cloned().or(key_location);
+        attach(
+            seed.deserialize(EntryDeserializer::new(item, self.options)),
+            location.as_ref(),
+        )
     }
 }
 
@@ -201,7 +1095,91 @@ fn test_serde_table() {
     let j = r#"{foo bar}"#;
 
     assert_eq!(
-        Table(hashmap! {"foo".into() => Entry::Value("bar".into())}),
+        Table::from(hashmap! {"foo".into() => Entry::Value("bar".into())}),
         unwrap_err(crate::from_str(j))
     );
 }
+
+#[test]
+fn test_load_order() {
+    let table = Table::load_from_str(
+        r#"
+        b 1
+        a 2
+        a 3
+        c 4
+        b 5
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(vec!["b", "a", "c", "b"], table.keys().collect::<Vec<_>>());
+    assert_eq!(Some("1"), table.get("b").and_then(Entry::as_str));
+    assert_eq!(
+        vec![Some("1"), Some("5")],
+        table.get_all("b").map(Entry::as_str).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(2),
+        table.get("a").and_then(Entry::as_slice).map(<[_]>::len)
+    );
+
+    let mut table = table;
+    table.insert_at(1, "d", Value::from("6"));
+    table.insert("b", Value::from("7"));
+    assert_eq!(vec!["b", "d", "a", "c"], table.keys().collect::<Vec<_>>());
+    assert_eq!(Some("7"), table.get("b").and_then(Entry::as_str));
+}
+
+#[test]
+fn test_serialize_repeated_keys() {
+    let table = Table::load_from_str("b 1\na 2\nb 3").unwrap();
+    assert_eq!(
+        vec!["b", "a", "b"],
+        table.iter().map(|(key, _)| key).collect::<Vec<_>>()
+    );
+
+    let serialized = unwrap_err(crate::to_string(&table));
+    assert_eq!(
+        "\"b\"\t\t\"1\"\n\"a\"\t\t\"2\"\n\"b\"\t\t\"3\"\n",
+        serialized
+    );
+    assert_eq!(table, unwrap_err(Table::load_from_str(&serialized)));
+}
+
+#[test]
+fn test_insert_keeps_entry() {
+    let options = LoadOptions::default()
+        .with_spans(true)
+        .with_case_insensitive(true);
+    let mut table =
+        Table::load_from_str_with_options("\"$BaseTexture\" a [$WIN32]\nb 1", &options).unwrap();
+    let span = table.key_location("$basetexture").map(Location::span);
+    assert!(span.is_some());
+
+    table.insert("$basetexture", Value::from("c"));
+    let (key, value, condition) = table.iter_with_conditions().next().unwrap();
+    assert_eq!("$BaseTexture", key);
+    assert_eq!(Some("c"), value.as_str());
+    assert!(condition.is_some());
+    assert_eq!(span, table.key_location("$basetexture").map(Location::span));
+}
+
+#[test]
+fn test_insert_case_insensitive_nested() {
+    let mut table = Table::default().with_case_insensitive(true);
+    let mut nested = Table::default();
+    nested.push("Key", Value::from("1"));
+
+    table.push("pushed", nested.clone());
+    table.insert("inserted", nested.clone());
+    table.insert_at(0, "at", Entry::Array(vec![nested.clone().into()].into()));
+    table.push_with_condition("conditional", nested, Condition::new("$WIN32"));
+
+    let root = Entry::Table(table);
+    for path in ["pushed.key", "inserted.key", "conditional.key"] {
+        assert!(root.lookup(path).is_some(), "{path}");
+    }
+    let array = root.lookup("at").and_then(Entry::as_slice).unwrap();
+    assert!(array[0].as_table().unwrap().get("key").is_some());
+}
@@ -0,0 +1,523 @@
+use super::table::{load_entries, LoadEntries};
+use super::{Entry, LoadOptions, Statement, Table, Value};
+use crate::binary::{BinaryEntry, BinaryTable, BinaryValue};
+use crate::writer::{Writer, WriterOptions};
+use crate::{BoolInference, Condition, Event, Reader, Result};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// The type of a value, inferred from its text the way KeyValues does when loading a file.
+///
+/// The inferred type doesn't replace the text, a [`TypedTable`] keeps the original string of
+/// every value so it can be written back unchanged.
+///
+/// KeyValues itself only infers strings, integers and floats, booleans and vectors are only
+/// inferred when enabled with [`TypeInference`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TypedValue {
+    String,
+    Int(i32),
+    Float(f32),
+    /// A `0x` prefixed value with 16 hex digits.
+    Uint64(u64),
+    /// A value from binary KeyValues, text values are never inferred as colors.
+    Color([u8; 4]),
+    Bool(bool),
+    /// Three numbers separated by spaces, like `"1 0.5 0"` or `"[1 0.5 0]"`.
+    Vector([f32; 3]),
+}
+
+/// Types to infer besides the ones KeyValues infers, nothing extra is inferred by default.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TypeInference {
+    /// Which values are booleans, this takes precedence over the other types.
+    pub bools: BoolInference,
+    /// Read three numbers separated by spaces as a vector.
+    pub vectors: bool,
+}
+
+impl TypeInference {
+    pub fn with_bools(self, bools: BoolInference) -> Self {
+        TypeInference { bools, ..self }
+    }
+
+    pub fn with_vectors(self, vectors: bool) -> Self {
+        TypeInference { vectors, ..self }
+    }
+}
+
+impl TypedValue {
+    /// Infer the type of a value.
+    ///
+    /// Like KeyValues, a `0x` prefixed value with 16 hex digits is a [`TypedValue::Uint64`],
+    /// a value that is entirely an integer fitting in 32 bits is a [`TypedValue::Int`]
+    /// and any other finite number is a [`TypedValue::Float`].
+    /// Everything else, including integers too large for 32 bits, is a [`TypedValue::String`].
+    pub fn infer(value: &str) -> TypedValue {
+        if let Some(hex) = value.strip_prefix("0x").filter(|hex| hex.len() == 16) {
+            if let Ok(value) = u64::from_str_radix(hex, 16) {
+                return TypedValue::Uint64(value);
+            }
+        }
+        if let Ok(value) = value.parse() {
+            TypedValue::Int(value)
+        } else if is_integer(value) {
+            TypedValue::String
+        } else {
+            match value.parse::<f32>() {
+                Ok(value) if value.is_finite() => TypedValue::Float(value),
+                _ => TypedValue::String,
+            }
+        }
+    }
+
+    /// Infer the type of a value, also inferring the types enabled in `inference`.
+    pub fn infer_with(value: &str, inference: TypeInference) -> TypedValue {
+        if let Some(value) = inference.bools.infer(value) {
+            return TypedValue::Bool(value);
+        }
+        match TypedValue::infer(value) {
+            TypedValue::String if inference.vectors => {
+                parse_vector(value).map_or(TypedValue::String, TypedValue::Vector)
+            }
+            typed => typed,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            TypedValue::Int(value) => Some((*value).into()),
+            TypedValue::Uint64(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            TypedValue::Int(value) => Some(*value as f32),
+            TypedValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<[u8; 4]> {
+        match self {
+            TypedValue::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            TypedValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<[f32; 3]> {
+        match self {
+            TypedValue::Vector(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps the wire type, 64-bit integers that aren't a valid [`TypedValue::Uint64`] become a string.
+impl From<&BinaryValue<'_>> for TypedValue {
+    fn from(value: &BinaryValue<'_>) -> Self {
+        match value {
+            BinaryValue::String(_) | BinaryValue::Bytes(_) | BinaryValue::WideString(_) => {
+                TypedValue::String
+            }
+            BinaryValue::Int32(value) => TypedValue::Int(*value),
+            BinaryValue::Float32(value) => TypedValue::Float(*value),
+            BinaryValue::Pointer(value) => TypedValue::Uint64((*value).into()),
+            BinaryValue::Color(value) => TypedValue::Color(*value),
+            BinaryValue::Uint64(value) => TypedValue::Uint64(*value),
+            BinaryValue::Int64(value) => match u64::try_from(*value) {
+                Ok(value) => TypedValue::Uint64(value),
+                Err(_) => TypedValue::String,
+            },
+        }
+    }
+}
+
+/// Integers that overflow are kept as strings instead of being read as a float.
+fn is_integer(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Parse a color from `"r g b a"`, the alpha defaults to 255 when only three components are given.
+pub(crate) fn parse_color(value: &str) -> Option<[u8; 4]> {
+    let mut color = [255; 4];
+    let mut components = value.split_whitespace();
+    for (i, component) in color.iter_mut().enumerate() {
+        match components.next() {
+            Some(value) => *component = value.parse().ok()?,
+            None if i == 3 => break,
+            None => return None,
+        }
+    }
+    components.next().is_none().then_some(color)
+}
+
+/// Parse a vector from `"x y z"`, optionally surrounded by brackets.
+fn parse_vector(value: &str) -> Option<[f32; 3]> {
+    let value = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .unwrap_or(value);
+    let mut vector = [0.0; 3];
+    let mut components = value.split_whitespace();
+    for component in &mut vector {
+        *component = components
+            .next()?
+            .parse()
+            .ok()
+            .filter(|value: &f32| value.is_finite())?;
+    }
+    components.next().is_none().then_some(vector)
+}
+
+/// An entry in a [`TypedTable`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum TypedEntry {
+    Table(TypedTable),
+    /// A value with its original text and inferred type.
+    Value(Value, TypedValue),
+    Statement(Statement),
+}
+
+impl TypedEntry {
+    pub fn as_table(&self) -> Option<&TypedTable> {
+        match self {
+            TypedEntry::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// The original text of the value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            TypedEntry::Value(value, _) => Some(value),
+            TypedEntry::Statement(statement) => Some(statement),
+            TypedEntry::Table(_) => None,
+        }
+    }
+
+    pub fn typed(&self) -> Option<TypedValue> {
+        match self {
+            TypedEntry::Value(_, typed) => Some(*typed),
+            _ => None,
+        }
+    }
+}
+
+impl From<Value> for TypedEntry {
+    fn from(value: Value) -> Self {
+        let typed = TypedValue::infer(&value);
+        TypedEntry::Value(value, typed)
+    }
+}
+
+impl From<TypedTable> for TypedEntry {
+    fn from(table: TypedTable) -> Self {
+        TypedEntry::Table(table)
+    }
+}
+
+/// Converts the entry back to an untyped entry with the original text.
+impl From<TypedEntry> for Entry {
+    fn from(entry: TypedEntry) -> Self {
+        match entry {
+            TypedEntry::Table(table) => Entry::Table(table.into()),
+            TypedEntry::Value(value, _) => Entry::Value(value),
+            TypedEntry::Statement(statement) => Entry::Statement(statement),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct TypedTableEntry {
+    key: String,
+    value: TypedEntry,
+    condition: Option<Condition>,
+}
+
+/// A table with the type of every value inferred while loading.
+///
+/// Unlike [`Table`], repeated keys and `"[a b c]"` values are kept as they are,
+/// so writing a loaded table gives back the same entries.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TypedTable(Vec<TypedTableEntry>);
+
+impl TypedTable {
+    pub fn load_from_str(input: &str) -> Result<TypedTable> {
+        Self::load_from_str_with_options(input, &LoadOptions::default())
+    }
+
+    pub fn load_from_str_with_options(input: &str, options: &LoadOptions) -> Result<TypedTable> {
+        let mut reader = Reader::from(input).with_escape_mode(options.escape);
+        Self::load_with_options(&mut reader, options)
+    }
+
+    /// Load a typed table from the events of the given reader.
+    pub fn load<'a, R: Iterator<Item = Result<Event<'a>>>>(reader: &mut R) -> Result<TypedTable> {
+        Self::load_with_options(reader, &LoadOptions::default())
+    }
+
+    /// Load a typed table from the events of the given reader with the given options.
+    ///
+    /// Values following the value of an entry on the same line are added as entries with the same key,
+    /// locations and [`LoadOptions::case_insensitive`] are ignored.
+    pub fn load_with_options<'a, R: Iterator<Item = Result<Event<'a>>>>(
+        reader: &mut R,
+        options: &LoadOptions,
+    ) -> Result<TypedTable> {
+        let table: TypedTable = load_entries(reader, options)?;
+        Ok(match options.types == TypeInference::default() {
+            true => table,
+            false => table.with_inference(options.types),
+        })
+    }
+
+    /// Infer the type of every value again, also inferring the types enabled in `inference`.
+    ///
+    /// This replaces the types of values converted from binary KeyValues.
+    pub fn with_inference(mut self, inference: TypeInference) -> Self {
+        for entry in &mut self.0 {
+            match &mut entry.value {
+                TypedEntry::Table(table) => {
+                    *table = std::mem::take(table).with_inference(inference)
+                }
+                TypedEntry::Value(value, typed) => {
+                    *typed = TypedValue::infer_with(value, inference)
+                }
+                TypedEntry::Statement(_) => {}
+            }
+        }
+        self
+    }
+
+    fn push_entry(&mut self, key: String, value: TypedEntry, condition: Option<Condition>) {
+        self.0.push(TypedTableEntry {
+            key,
+            value,
+            condition,
+        });
+    }
+
+    /// The number of entries in the table, repeated keys are counted separately.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&TypedEntry> {
+        self.0
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| &entry.value)
+    }
+
+    /// Get all entries with the given key.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a TypedEntry> + 'a {
+        self.0
+            .iter()
+            .filter(move |entry| entry.key == key)
+            .map(|entry| &entry.value)
+    }
+
+    /// Iterate over all entries in the order they are defined in.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TypedEntry)> {
+        self.0
+            .iter()
+            .map(|entry| (entry.key.as_str(), &entry.value))
+    }
+
+    /// Add a value at the end of the table, inferring its type.
+    pub fn push<K: Into<String>, V: Into<TypedEntry>>(&mut self, key: K, value: V) {
+        self.push_entry(key.into(), value.into(), None);
+    }
+
+    /// Write the table as vdf text, values are written with their original text.
+    pub fn write_to<W: Write>(&self, writer: &mut W, options: &WriterOptions) -> fmt::Result {
+        self.write_entries(&mut Writer::new(writer, options))
+    }
+
+    fn write_entries<W: Write>(&self, writer: &mut Writer<W>) -> fmt::Result {
+        let key_width = writer.key_width(
+            self.0
+                .iter()
+                .filter(|entry| !matches!(entry.value, TypedEntry::Table(_)))
+                .map(|entry| entry.key.as_str()),
+        );
+        for entry in &self.0 {
+            let condition = entry.condition.as_ref();
+            match &entry.value {
+                TypedEntry::Table(table) => {
+                    writer.start_group(&entry.key, condition)?;
+                    table.write_entries(writer)?;
+                    writer.end_group()?;
+                }
+                TypedEntry::Value(value, _) => {
+                    writer.write_key_value(&entry.key, value, condition, key_width)?
+                }
+                TypedEntry::Statement(statement) => {
+                    writer.write_key_value(&entry.key, statement, condition, key_width)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> LoadEntries<'a> for TypedTable {
+    fn new(_options: &LoadOptions) -> Self {
+        TypedTable::default()
+    }
+
+    fn push_value(
+        &mut self,
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+        statement: bool,
+        condition: Option<Condition>,
+    ) {
+        let entry = match statement {
+            true => TypedEntry::Statement(value.into()),
+            false => Value::from(value).into(),
+        };
+        self.push_entry(key.into_owned(), entry, condition);
+    }
+
+    fn push_group(&mut self, name: Cow<'a, str>, group: Self, condition: Option<Condition>) {
+        self.push_entry(name.into_owned(), group.into(), condition);
+    }
+}
+
+impl Display for TypedTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f, &WriterOptions::default())
+    }
+}
+
+/// Converts the table using the wire type of every value instead of inferring it.
+impl From<&BinaryTable> for TypedTable {
+    fn from(table: &BinaryTable) -> Self {
+        let mut result = TypedTable::default();
+        for (key, entry) in table {
+            let entry = match entry {
+                BinaryEntry::Table(table) => TypedEntry::Table(table.into()),
+                BinaryEntry::Value(value) => {
+                    TypedEntry::Value(value.to_string().into(), value.into())
+                }
+            };
+            result.push_entry(key.into(), entry, None);
+        }
+        result
+    }
+}
+
+/// Converts the table to an untyped table, combining repeated keys like [`Table::load`] does.
+impl From<TypedTable> for Table {
+    fn from(table: TypedTable) -> Self {
+        let mut result = Table::default();
+        for TypedTableEntry {
+            key,
+            value,
+            condition,
+        } in table.0
+        {
+            result.append(key, Entry::from(value), condition);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer() {
+        assert_eq!(TypedValue::Int(12), TypedValue::infer("12"));
+        assert_eq!(TypedValue::Int(-3), TypedValue::infer("-3"));
+        assert_eq!(TypedValue::Float(1.5), TypedValue::infer("1.5"));
+        assert_eq!(TypedValue::Float(100.0), TypedValue::infer("1e2"));
+        assert_eq!(
+            TypedValue::Uint64(0x0110000100000001),
+            TypedValue::infer("0x0110000100000001")
+        );
+        assert_eq!(TypedValue::String, TypedValue::infer("76561197960265728"));
+        assert_eq!(TypedValue::String, TypedValue::infer(""));
+        assert_eq!(TypedValue::String, TypedValue::infer("1 2 3"));
+        assert_eq!(TypedValue::String, TypedValue::infer("inf"));
+        assert_eq!(TypedValue::String, TypedValue::infer("0x10"));
+    }
+
+    #[test]
+    fn test_infer_with() {
+        let inference = TypeInference::default();
+        assert_eq!(TypedValue::Int(1), TypedValue::infer_with("1", inference));
+        assert_eq!(
+            TypedValue::String,
+            TypedValue::infer_with("1 0 0", inference)
+        );
+
+        let inference = inference
+            .with_bools(BoolInference::ZeroOne)
+            .with_vectors(true);
+        assert_eq!(
+            TypedValue::Bool(true),
+            TypedValue::infer_with("1", inference)
+        );
+        assert_eq!(TypedValue::Int(2), TypedValue::infer_with("2", inference));
+        assert_eq!(
+            TypedValue::Vector([1.0, 0.5, 0.0]),
+            TypedValue::infer_with("1 0.5 0", inference)
+        );
+        assert_eq!(
+            TypedValue::Vector([1.0, 0.5, 0.0]),
+            TypedValue::infer_with("[1 0.5 0]", inference)
+        );
+        assert_eq!(TypedValue::String, TypedValue::infer_with("1 2", inference));
+        assert_eq!(
+            TypedValue::String,
+            TypedValue::infer_with("1 2 3 4", inference)
+        );
+        assert_eq!(
+            TypedValue::String,
+            TypedValue::infer_with("a b c", inference)
+        );
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Some([1, 2, 3, 4]), parse_color("1 2 3 4"));
+        assert_eq!(Some([1, 2, 3, 255]), parse_color("1 2 3"));
+        assert_eq!(None, parse_color("1 2"));
+        assert_eq!(None, parse_color("1 2 3 4 5"));
+        assert_eq!(None, parse_color("1 2 300"));
+    }
+
+    #[test]
+    fn test_binary() {
+        let mut binary = BinaryTable::default();
+        binary.push("color", BinaryValue::Color([1, 2, 3, 4]));
+        binary.push("id", BinaryValue::Uint64(5));
+        let table = TypedTable::from(&binary);
+        let color = table.get("color").unwrap();
+        assert_eq!(Some("1 2 3 4"), color.as_str());
+        assert_eq!(Some(TypedValue::Color([1, 2, 3, 4])), color.typed());
+        assert_eq!(
+            Some(TypedValue::Uint64(5)),
+            table.get("id").and_then(TypedEntry::typed)
+        );
+    }
+}
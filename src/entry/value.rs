@@ -1,6 +1,7 @@
-use super::Entry;
-use crate::entry::{string_is_array, ParseItem, Statement};
+use super::{Entry, Location};
+use crate::entry::{ParseItem, Statement};
 use crate::error::{ParseStringError, SerdeParseError};
+use crate::serde::{DeserializerOptions, Inferred};
 use crate::VdfError;
 use serde::de::{Error, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
@@ -8,30 +9,31 @@ use std::borrow::Cow;
 use std::fmt::Formatter;
 use std::ops::{Deref, DerefMut};
 
-#[derive(Clone, PartialEq, Eq, Debug, Serialize, Default)]
+#[derive(Clone, Debug, Serialize, Default)]
 #[serde(transparent)]
-pub struct Value(String);
+pub struct Value(String, #[serde(skip)] Option<Location>);
 
 impl From<Cow<'_, str>> for Value {
     fn from(value: Cow<'_, str>) -> Value {
-        Value(value.into())
+        Value(value.into(), None)
     }
 }
 impl From<&'_ str> for Value {
     fn from(value: &str) -> Value {
-        Value(value.into())
+        Value(value.into(), None)
     }
 }
 
 impl From<String> for Value {
     fn from(value: String) -> Value {
-        Value(value)
+        Value(value, None)
     }
 }
 
 impl From<Statement> for Value {
     fn from(value: Statement) -> Value {
-        Value(value.into())
+        let location = value.location().cloned();
+        Value(value.into(), location)
     }
 }
 
@@ -61,11 +63,46 @@ impl DerefMut for Value {
     }
 }
 
+/// Values are compared by their text, regardless of where they were loaded from.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Value {}
+
 impl Value {
     /// Try to convert the entry to the given type.
     pub fn to<T: ParseItem>(self) -> Result<T, ParseStringError> {
         T::from_str(&self.0)
     }
+
+    /// Where the value was defined, if it was loaded with spans.
+    pub fn location(&self) -> Option<&Location> {
+        self.1.as_ref()
+    }
+
+    pub(crate) fn with_location(self, location: Option<Location>) -> Self {
+        Value(self.0, location)
+    }
+
+    pub(crate) fn deserialize_any_with<'de, V>(
+        self,
+        visitor: V,
+        options: &DeserializerOptions,
+    ) -> Result<V::Value, VdfError>
+    where
+        V: Visitor<'de>,
+    {
+        match options.infer(&self.0) {
+            Inferred::Bool(bool) => visitor.visit_bool(bool),
+            Inferred::Int(int) => visitor.visit_i64(int),
+            Inferred::Float(float) => visitor.visit_f64(float),
+            Inferred::Array => self.deserialize_seq(visitor),
+            Inferred::String => visitor.visit_string(self.0),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Value {
@@ -125,7 +162,7 @@ impl<'de> Deserialize<'de> for Value {
             }
         }
 
-        deserializer.deserialize_str(ValueVisitor).map(Value)
+        deserializer.deserialize_str(ValueVisitor).map(Value::from)
     }
 }
 
@@ -136,16 +173,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        if let Ok(int) = i64::from_str(&self.0) {
-            return visitor.visit_i64(int);
-        }
-        if let Ok(float) = f64::from_str(&self.0) {
-            return visitor.visit_f64(float);
-        }
-        if string_is_array(&self.0) {
-            return self.deserialize_seq(visitor);
-        }
-        visitor.visit_string(self.0)
+        self.deserialize_any_with(visitor, &DeserializerOptions::default())
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -392,10 +420,10 @@ fn unwrap_err<T>(r: Result<T, crate::VdfError>) -> T {
 #[test]
 fn test_serde_value() {
     let j = r#"1"#;
-    assert_eq!(Value("1".into()), unwrap_err(crate::from_str(j)));
+    assert_eq!(Value::from("1"), unwrap_err(crate::from_str(j)));
 
     let j = r#""foo bar""#;
-    assert_eq!(Value("foo bar".into()), unwrap_err(crate::from_str(j)));
+    assert_eq!(Value::from("foo bar"), unwrap_err(crate::from_str(j)));
 }
 
 #[test]
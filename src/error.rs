@@ -5,6 +5,8 @@ use logos::Span;
 use miette::{Diagnostic, SourceSpan};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Any error that occurred while trying to parse the vdf file
@@ -44,9 +46,62 @@ pub enum VdfError {
     SerdeParse(#[from] SerdeParseError),
     #[error(transparent)]
     #[diagnostic(transparent)]
+    /// Failed to read or write the underlying data
+    Io(#[from] IoError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    /// Failed to resolve a `#base` or `#include` directive
+    Include(Box<IncludeError>),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    /// Invalid binary data
+    Binary(#[from] BinaryError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    /// Invalid KeyValues3 data
+    Kv3(#[from] Kv3Error),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    /// Input that isn't valid in its encoding
+    Encoding(#[from] EncodingError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
     Other(#[from] UnknownError),
 }
 
+impl From<std::fmt::Error> for VdfError {
+    fn from(_: std::fmt::Error) -> Self {
+        UnknownError::from("failed to format output").into()
+    }
+}
+
+impl From<std::io::Error> for VdfError {
+    fn from(value: std::io::Error) -> Self {
+        IoError::from(value).into()
+    }
+}
+
+impl From<IncludeError> for VdfError {
+    fn from(value: IncludeError) -> Self {
+        Self::Include(value.into())
+    }
+}
+
+impl From<IncludeErrorKind> for VdfError {
+    fn from(value: IncludeErrorKind) -> Self {
+        match value {
+            IncludeErrorKind::Io(error) => IoError { error }.into(),
+            IncludeErrorKind::Load(error) => *error,
+            kind => UnknownError {
+                error: kind.to_string(),
+                err_span: (0..0).into(),
+                src: String::new(),
+            }
+            .into(),
+        }
+    }
+}
+
 impl From<WrongEventTypeError> for VdfError {
     fn from(value: WrongEventTypeError) -> Self {
         Self::WrongEventType(value.into())
@@ -62,6 +117,8 @@ impl VdfError {
             VdfError::WrongEventType(e) => e.src.as_str(),
             VdfError::SerdeParse(e) => e.src.as_str(),
             VdfError::UnknownVariant(e) => e.src.as_str(),
+            VdfError::Kv3(e) => e.src.as_str(),
+            VdfError::ParseEntry(e) => e.src.as_str(),
             _ => {
                 return None;
             }
@@ -69,18 +126,36 @@ impl VdfError {
         (!src.is_empty()).then_some(src)
     }
     pub fn span(&self) -> Option<SourceSpan> {
-        let span = match self {
+        self.span_inner().filter(|span| !span.is_empty())
+    }
+
+    fn span_inner(&self) -> Option<SourceSpan> {
+        Some(match self {
             VdfError::Other(e) => e.err_span,
             VdfError::UnexpectedToken(e) => e.err_span,
             VdfError::NoValidToken(e) => e.err_span,
             VdfError::WrongEventType(e) => e.err_span,
             VdfError::SerdeParse(e) => e.err_span,
             VdfError::UnknownVariant(e) => e.err_span,
+            VdfError::Kv3(e) => e.err_span,
+            VdfError::ParseEntry(e) => e.err_span,
             _ => {
                 return None;
             }
-        };
-        (!span.is_empty()).then_some(span)
+        })
+    }
+
+    /// Move the span of an error in a part of a larger input by the `offset` of that part.
+    ///
+    /// The source is replaced by the larger input if it's available, or removed otherwise.
+    pub(crate) fn with_offset(self, offset: usize, source: Option<&str>) -> VdfError {
+        match self.span_inner() {
+            Some(span) => {
+                let start = span.offset() + offset;
+                self.with_source_span(start..start + span.len(), source.unwrap_or_default())
+            }
+            None => self,
+        }
     }
 
     pub(crate) fn with_source_span_if_none<Sp: Into<SourceSpan>, Sr: Into<String>>(
@@ -136,6 +211,12 @@ impl VdfError {
                 ..e
             }
             .into(),
+            VdfError::ParseEntry(e) => ParseEntryError {
+                src: source.into(),
+                err_span: span.into(),
+                ..e
+            }
+            .into(),
             _ => self,
         }
     }
@@ -181,6 +262,141 @@ impl From<&str> for UnknownError {
     }
 }
 
+/// All errors found while loading with [`Table::load_recovering`](crate::entry::Table::load_recovering)
+#[derive(Debug, Clone, Diagnostic, Error)]
+#[diagnostic(code(vmt_reader::multiple))]
+#[error("Found {} errors", self.errors.len())]
+pub struct ParseErrors {
+    #[related]
+    pub errors: Vec<VdfError>,
+}
+
+impl From<Vec<VdfError>> for ParseErrors {
+    fn from(errors: Vec<VdfError>) -> Self {
+        ParseErrors { errors }
+    }
+}
+
+/// Failed to read or write the underlying data
+#[derive(Debug, Clone, Diagnostic, Error)]
+#[diagnostic(code(vmt_reader::io))]
+#[error("{error}")]
+pub struct IoError {
+    pub error: Arc<std::io::Error>,
+}
+
+impl From<std::io::Error> for IoError {
+    fn from(value: std::io::Error) -> Self {
+        IoError {
+            error: Arc::new(value),
+        }
+    }
+}
+
+/// Invalid binary data
+#[derive(Debug, Clone, Diagnostic, Error)]
+#[diagnostic(code(vmt_reader::binary))]
+#[error("{error} at offset {offset}")]
+pub struct BinaryError {
+    pub error: String,
+    pub offset: usize,
+}
+
+impl BinaryError {
+    pub fn new<S: Into<String>>(error: S, offset: usize) -> Self {
+        BinaryError {
+            error: error.into(),
+            offset,
+        }
+    }
+}
+
+/// Input that isn't valid in its encoding
+#[derive(Debug, Clone, Diagnostic, Error)]
+#[diagnostic(code(vmt_reader::encoding))]
+#[error("{error} at offset {offset}")]
+pub struct EncodingError {
+    pub error: String,
+    pub offset: usize,
+}
+
+impl EncodingError {
+    pub fn new<S: Into<String>>(error: S, offset: usize) -> Self {
+        EncodingError {
+            error: error.into(),
+            offset,
+        }
+    }
+}
+
+/// Invalid KeyValues3 data
+#[derive(Debug, Clone, Diagnostic, Error)]
+#[diagnostic(code(vmt_reader::kv3))]
+#[error("{error}")]
+pub struct Kv3Error {
+    pub error: String,
+    #[label("{error}")]
+    err_span: SourceSpan,
+    #[source_code]
+    src: String,
+}
+
+impl Kv3Error {
+    pub fn new<S: Into<String>>(error: S, err_span: Span, src: &str) -> Self {
+        Kv3Error {
+            error: error.into(),
+            err_span: err_span.into(),
+            src: src.into(),
+        }
+    }
+}
+
+/// Failed to resolve a `#base` or `#include` directive
+#[derive(Debug, Clone, Diagnostic, Error)]
+#[diagnostic(code(vmt_reader::include))]
+#[error("Failed to resolve {directive} {path:?} in {}: {kind}", file.display())]
+pub struct IncludeError {
+    /// The file containing the directive
+    pub file: PathBuf,
+    /// The directive, `#base` or `#include`
+    pub directive: String,
+    /// The path as written in the directive
+    pub path: String,
+    pub kind: IncludeErrorKind,
+}
+
+impl IncludeError {
+    pub fn new(file: &Path, directive: &str, path: &str, kind: IncludeErrorKind) -> Self {
+        IncludeError {
+            file: file.into(),
+            directive: directive.into(),
+            path: path.into(),
+            kind,
+        }
+    }
+}
+
+/// The reason a `#base` or `#include` directive couldn't be resolved
+#[derive(Debug, Clone, Error)]
+pub enum IncludeErrorKind {
+    #[error("the file includes itself")]
+    Cycle,
+    #[error("more than {0} nested includes")]
+    TooDeep(usize),
+    #[error("the path is outside of the allowed directories")]
+    OutsideRoot,
+    #[error("{0}")]
+    Io(Arc<std::io::Error>),
+    #[error("{0}")]
+    Load(Box<VdfError>),
+}
+
+impl From<std::io::Error> for IncludeErrorKind {
+    fn from(value: std::io::Error) -> Self {
+        IncludeErrorKind::Io(Arc::new(value))
+    }
+}
+
 /// A token that wasn't expected was found while parsing
 #[derive(Debug, Clone, Diagnostic)]
 #[diagnostic(code(vmt_reader::unexpected_token))]
@@ -301,11 +517,25 @@ impl WrongEventTypeError {
 pub struct ParseEntryError {
     pub ty: &'static str,
     pub value: Entry,
+    #[label("Expected a {ty}")]
+    err_span: SourceSpan,
+    #[source_code]
+    src: String,
 }
 
 impl ParseEntryError {
+    /// The error points at the entry if it was loaded with spans.
     pub fn new(ty: &'static str, value: Entry) -> Self {
-        ParseEntryError { ty, value }
+        let (err_span, src) = match value.location() {
+            Some(location) => (location.span().into(), location.source().into()),
+            None => ((0..0).into(), String::new()),
+        };
+        ParseEntryError {
+            ty,
+            value,
+            err_span,
+            src,
+        }
     }
 }
 
@@ -471,6 +701,19 @@ impl serde::de::Error for VdfError {
     }
 }
 
+impl serde::ser::Error for VdfError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        VdfError::Other(UnknownError {
+            err_span: (0..0).into(),
+            src: String::new(),
+            error: msg.to_string(),
+        })
+    }
+}
+
 pub(crate) trait ResultExt {
     fn ensure_span(self, span: Span, source: &str) -> Self;
 }
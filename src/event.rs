@@ -1,3 +1,4 @@
+use crate::condition::Condition;
 use crate::error::WrongEventTypeError;
 use crate::VdfError;
 use logos::Span;
@@ -35,6 +36,19 @@ impl<'a> Item<'a> {
         }
     }
 
+    fn with_offset(self, offset: usize) -> Self {
+        match self {
+            Item::Statement { content, span } => Item::Statement {
+                content,
+                span: span.start + offset..span.end + offset,
+            },
+            Item::Item { content, span } => Item::Item {
+                content,
+                span: span.start + offset..span.end + offset,
+            },
+        }
+    }
+
     pub fn into_owned(self) -> Item<'static> {
         match self {
             Item::Statement { content, span } => Item::Statement {
@@ -76,6 +90,8 @@ pub enum EventType {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct GroupStartEvent<'a> {
     pub name: Cow<'a, str>,
+    /// The condition the group is only included under, if any.
+    pub condition: Option<Condition>,
     pub span: Span,
 }
 
@@ -83,6 +99,7 @@ impl GroupStartEvent<'_> {
     pub fn into_owned(self) -> GroupStartEvent<'static> {
         GroupStartEvent {
             name: self.name.into_owned().into(),
+            condition: self.condition,
             span: self.span,
         }
     }
@@ -131,6 +148,8 @@ impl<'a> TryFrom<Event<'a>> for GroupEndEvent {
 pub struct EntryEvent<'a> {
     pub key: Item<'a>,
     pub value: Item<'a>,
+    /// The condition the entry is only included under, if any.
+    pub condition: Option<Condition>,
     pub span: Span,
 }
 
@@ -139,6 +158,7 @@ impl EntryEvent<'_> {
         EntryEvent {
             key: self.key.into_owned(),
             value: self.value.into_owned(),
+            condition: self.condition,
             span: self.span,
         }
     }
@@ -195,6 +215,30 @@ impl Event<'_> {
         }
     }
 
+    /// Move all spans of the event by `offset` bytes.
+    pub(crate) fn with_offset(self, offset: usize) -> Self {
+        let shift = |span: Span| span.start + offset..span.end + offset;
+        match self {
+            Event::GroupStart(event) => Event::GroupStart(GroupStartEvent {
+                span: shift(event.span),
+                ..event
+            }),
+            Event::GroupEnd(event) => Event::GroupEnd(GroupEndEvent {
+                span: shift(event.span),
+            }),
+            Event::Entry(event) => Event::Entry(EntryEvent {
+                key: event.key.with_offset(offset),
+                value: event.value.with_offset(offset),
+                condition: event.condition,
+                span: shift(event.span),
+            }),
+            Event::ValueContinuation(event) => Event::ValueContinuation(ValueContinuationEvent {
+                value: event.value.with_offset(offset),
+                span: shift(event.span),
+            }),
+        }
+    }
+
     pub fn ty(&self) -> EventType {
         match self {
             Event::GroupStart(GroupStartEvent { .. }) => EventType::GroupStart,
@@ -0,0 +1,206 @@
+use crate::entry::{Entry, LoadOptions, Table};
+use crate::error::{IncludeError, IncludeErrorKind};
+use crate::{DecodedText, Encoding, Result, VdfError};
+use std::path::{Component, Path, PathBuf};
+
+/// Loads the files referenced by `#base` and `#include` directives.
+pub trait Resolver {
+    /// Find the file referenced as `path`.
+    ///
+    /// `from` is the file containing the directive, or `None` for the file passed to
+    /// [`Table::load_with_includes`]. The returned path is used to detect include cycles,
+    /// so every file should always resolve to the same path.
+    fn resolve(&self, from: Option<&Path>, path: &str) -> Result<PathBuf, IncludeErrorKind>;
+
+    /// Read a file returned by [`Resolver::resolve`].
+    ///
+    /// `encoding` is the [`LoadOptions::encoding`] used for files without a byte order mark that aren't UTF-8.
+    fn read(&self, path: &Path, encoding: Encoding) -> std::io::Result<String>;
+}
+
+/// Resolves files from the filesystem, relative to the file containing the directive.
+///
+/// Only files inside one of the allowed root directories can be loaded.
+#[derive(Clone, Debug)]
+pub struct FileResolver {
+    roots: Vec<PathBuf>,
+}
+
+impl FileResolver {
+    /// Create a resolver that allows loading any file inside `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FileResolver {
+            roots: vec![root.into()],
+        }
+    }
+
+    /// Allow loading files inside another directory.
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.roots.push(root.into());
+        self
+    }
+
+    fn is_allowed(&self, path: &Path, canonical: bool) -> bool {
+        self.roots.iter().any(|root| {
+            let root = if canonical {
+                root.canonicalize()
+            } else {
+                absolute(root).map(|root| normalize(&root))
+            };
+            root.is_ok_and(|root| path.starts_with(root))
+        })
+    }
+}
+
+impl Resolver for FileResolver {
+    fn resolve(&self, from: Option<&Path>, path: &str) -> Result<PathBuf, IncludeErrorKind> {
+        // valve tools write paths with either kind of slash
+        let path = path.replace('\\', "/");
+        let path = match from.and_then(Path::parent) {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+
+        // check before touching the filesystem, so we don't leak which files exist outside the roots
+        if !self.is_allowed(&normalize(&absolute(&path)?), false) {
+            return Err(IncludeErrorKind::OutsideRoot);
+        }
+        // check again after resolving symlinks
+        let path = path.canonicalize()?;
+        if !self.is_allowed(&path, true) {
+            return Err(IncludeErrorKind::OutsideRoot);
+        }
+        Ok(path)
+    }
+
+    /// Files are decoded like [`Table::load_bytes_with_options`] does.
+    fn read(&self, path: &Path, encoding: Encoding) -> std::io::Result<String> {
+        let bytes = std::fs::read(path)?;
+        DecodedText::decode(&bytes, encoding)
+            .map(|decoded| decoded.into_text().into_owned())
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+}
+
+fn absolute(path: &Path) -> std::io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.into())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+/// Remove `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Directive {
+    Base,
+    Include,
+}
+
+impl Directive {
+    fn from_key(key: &str) -> Option<Self> {
+        if key.eq_ignore_ascii_case("#base") {
+            Some(Directive::Base)
+        } else if key.eq_ignore_ascii_case("#include") {
+            Some(Directive::Include)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) fn load<R: Resolver>(path: &Path, resolver: &R, options: &LoadOptions) -> Result<Table> {
+    let path = resolver.resolve(None, &path.to_string_lossy())?;
+    load_file(path, resolver, options, &mut Vec::new())
+}
+
+fn load_file<R: Resolver>(
+    path: PathBuf,
+    resolver: &R,
+    options: &LoadOptions,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Table> {
+    let source = resolver.read(&path, options.encoding)?;
+    let mut table = Table::load_from_file_str(&source, Some(&path), options)?;
+
+    let mut directives = Vec::new();
+    for (key, entry) in &table {
+        if let Some(directive) = Directive::from_key(key) {
+            let paths = match entry {
+                Entry::Array(array) => array.iter().filter_map(Entry::as_str).collect(),
+                entry => entry.as_str().into_iter().collect::<Vec<_>>(),
+            };
+            directives.extend(
+                paths
+                    .into_iter()
+                    .map(|target| (key.to_string(), directive, target.to_string())),
+            );
+        }
+    }
+    table.retain(|key, _| Directive::from_key(key).is_none());
+
+    stack.push(path);
+    for (key, directive, target) in directives {
+        let from = stack.last().expect("we just pushed the path").clone();
+        let error = |kind| VdfError::from(IncludeError::new(&from, &key, &target, kind));
+
+        if stack.len() > options.max_include_depth {
+            return Err(error(IncludeErrorKind::TooDeep(options.max_include_depth)));
+        }
+        let resolved = resolver.resolve(Some(&from), &target).map_err(error)?;
+        if stack.contains(&resolved) {
+            return Err(error(IncludeErrorKind::Cycle));
+        }
+
+        let included = load_file(resolved, resolver, options, stack).map_err(|e| match e {
+            // errors from nested directives already name the file they occurred in
+            e @ VdfError::Include(_) => e,
+            VdfError::Io(e) => error(IncludeErrorKind::Io(e.error)),
+            e => error(IncludeErrorKind::Load(Box::new(e))),
+        })?;
+        match directive {
+            Directive::Base => merge_base(&mut table, included),
+            Directive::Include => merge_include(&mut table, included),
+        }
+    }
+    stack.pop();
+
+    Ok(table)
+}
+
+/// Add the entries from `base` for keys that aren't set in `table` yet.
+fn merge_base(table: &mut Table, base: Table) {
+    for (key, value) in base {
+        match (table.get_mut(&key), value) {
+            (Some(Entry::Table(existing)), Entry::Table(value)) => merge_base(existing, value),
+            (Some(_), _) => {}
+            (None, value) => table.push(key, value),
+        }
+    }
+}
+
+/// Add the entries from `include` to `table`, overriding existing keys.
+fn merge_include(table: &mut Table, include: Table) {
+    for (key, value) in include {
+        match (table.get_mut(&key), value) {
+            (Some(Entry::Table(existing)), Entry::Table(value)) => merge_include(existing, value),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,812 @@
+//! Binary KeyValues3, as stored in the DATA blocks of compiled Source 2 resources.
+//!
+//! Supported containers:
+//!
+//! - `VKV\x03`: the legacy container, with the encoding and format guids followed by the
+//!   uncompressed, LZ4 or block compressed data. All values are stored inline.
+//! - `\x01KV3`: a single buffer, uncompressed or LZ4 compressed.
+//! - `\x02KV3` to `\x04KV3`: a single buffer, uncompressed, LZ4 or zstd compressed, with binary
+//!   blobs stored in separate blocks after the buffer. Version 4 adds a section for two byte values.
+//! - `\x05KV3`: like version 4, but split over two buffers that are compressed separately. The header
+//!   has the compressed and decompressed size of the second buffer and the size of its sections.
+//!   The first buffer has the strings, the keys of objects and the types, the second the values.
+//!
+//! Except for the legacy container, values are split by size into separate sections of the
+//! buffer, followed by the string table and the types of all values.
+//!
+//! Sizes in the headers aren't trusted: decompressed sizes are limited by the size of the compressed
+//! data, and the number of values and nesting depth are limited so crafted input can't exhaust memory or the stack.
+
+use super::{Kv3Document, Kv3Format, Kv3Header, Kv3Object, Kv3Value};
+use crate::binary::cursor::Cursor;
+use crate::error::BinaryError;
+use crate::Result;
+use std::io::Read;
+
+const MAGIC_LEGACY: u32 = 0x03564B56;
+const MAGIC_V1: u32 = 0x4B563301;
+const MAGIC_V2: u32 = 0x4B563302;
+const MAGIC_V3: u32 = 0x4B563303;
+const MAGIC_V4: u32 = 0x4B563304;
+const MAGIC_V5: u32 = 0x4B563305;
+
+const TRAILER: u32 = 0xFFEEDD00;
+
+/// The most compressed data is expected to grow, larger decompressed sizes are rejected before allocating.
+const MAX_COMPRESSION_RATIO: usize = 1024;
+/// How deep arrays and objects can be nested.
+const MAX_DEPTH: usize = 64;
+
+const ENCODING_UNCOMPRESSED: &str = "1b860500-f7d8-40c1-ad82-75a48267e714";
+const ENCODING_BLOCK_COMPRESSED: &str = "95791a46-95bc-4f6c-a70b-05bca1b7dfd2";
+const ENCODING_LZ4: &str = "6847348a-63a1-4f5c-a197-53806fd9b119";
+
+const FLAGS: &[&str] = &[
+    "",
+    "resource",
+    "resource_name",
+    "panorama",
+    "soundevent",
+    "subclass",
+    "entity_name",
+];
+
+fn error<T>(error: impl Into<String>, offset: usize) -> Result<T> {
+    Err(BinaryError::new(error, offset).into())
+}
+
+/// Format a guid stored in the mixed endian layout used by .NET and Windows.
+fn guid(bytes: [u8; 16]) -> String {
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{}",
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8],
+        bytes[9],
+        bytes[10..]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    )
+}
+
+/// Check a decompressed size from a header against the size of the compressed data.
+fn decompressed_size(compressed: &[u8], size: usize) -> Result<usize> {
+    if size > compressed.len().saturating_mul(MAX_COMPRESSION_RATIO) {
+        return error(
+            format!(
+                "decompressed size of {size} bytes is too large for {} bytes of compressed data",
+                compressed.len()
+            ),
+            0,
+        );
+    }
+    Ok(size)
+}
+
+fn lz4(data: &[u8], size: usize) -> Result<Vec<u8>> {
+    let mut out = vec![0; decompressed_size(data, size)?];
+    match lz4_flex::block::decompress_into(data, &mut out) {
+        Ok(written) if written == size => Ok(out),
+        Ok(written) => error(
+            format!("LZ4 data decompressed to {written} bytes, expected {size}"),
+            0,
+        ),
+        Err(e) => error(format!("invalid LZ4 data: {e}"), 0),
+    }
+}
+
+fn zstd(data: &[u8], size: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(decompressed_size(data, size)?);
+    let decoder = match ruzstd::StreamingDecoder::new(data) {
+        Ok(decoder) => decoder,
+        Err(e) => return error(format!("invalid zstd data: {e}"), 0),
+    };
+    // stop after one byte more than expected, that's enough to detect the size mismatch
+    if let Err(e) = decoder.take(size as u64 + 1).read_to_end(&mut out) {
+        return error(format!("invalid zstd data: {e}"), 0);
+    }
+    if out.len() != size {
+        return error(
+            format!(
+                "zstd data decompressed to {} bytes, expected {size}",
+                out.len()
+            ),
+            0,
+        );
+    }
+    Ok(out)
+}
+
+/// The LZ77 style compression used by the legacy container.
+///
+/// Every 16 bit mask is followed by 16 items, a set bit marks a back reference of 3 to 18 bytes
+/// into the previous output and a cleared bit a literal byte.
+fn block_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(data, 0);
+    let flags: [u8; 4] = cursor.bytes()?;
+    if flags[3] & 0x80 != 0 {
+        return Ok(cursor.remaining().to_vec());
+    }
+    let size = u32::from_le_bytes([flags[0], flags[1], flags[2], 0]) as usize;
+
+    let mut out = Vec::with_capacity(decompressed_size(cursor.remaining(), size)?);
+    while out.len() < size {
+        let mask = u16::from_le_bytes(cursor.bytes()?);
+        for bit in 0..16 {
+            if mask & (1 << bit) != 0 {
+                let reference = u16::from_le_bytes(cursor.bytes()?);
+                let offset = (reference >> 4) as usize + 1;
+                let length = (reference & 0x0F) as usize + 3;
+                if offset > out.len() {
+                    return cursor.error("back reference before the start of the data");
+                }
+                // the referenced bytes can overlap with the bytes being written
+                let start = out.len() - offset;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            } else {
+                out.push(cursor.bytes::<1>()?[0]);
+            }
+            if out.len() >= size {
+                break;
+            }
+        }
+    }
+    out.truncate(size);
+    Ok(out)
+}
+
+/// Skip padding so the cursor is aligned to `alignment` bytes from the start of the buffer.
+fn align(cursor: &mut Cursor, alignment: usize) -> Result<()> {
+    let padding = (alignment - cursor.position() % alignment) % alignment;
+    cursor.slice(padding).map(|_| ())
+}
+
+/// Split off the next `length` bytes of the buffer as a separate section.
+fn section<'a>(cursor: &mut Cursor<'a>, length: usize) -> Result<Cursor<'a>> {
+    Ok(Cursor::new(cursor.slice(length)?, 0))
+}
+
+fn count(value: u32) -> usize {
+    value as usize
+}
+
+/// The value sections of a buffer.
+struct Sections<'a> {
+    bytes: Cursor<'a>,
+    shorts: Cursor<'a>,
+    ints: Cursor<'a>,
+    longs: Cursor<'a>,
+}
+
+impl<'a> Sections<'a> {
+    fn read(
+        buffer: &mut Cursor<'a>,
+        bytes: usize,
+        shorts: usize,
+        ints: usize,
+        longs: usize,
+    ) -> Result<Self> {
+        let bytes = section(buffer, bytes)?;
+        align(buffer, 2)?;
+        let shorts = section(buffer, shorts.saturating_mul(2))?;
+        align(buffer, 4)?;
+        let ints = section(buffer, ints.saturating_mul(4))?;
+        align(buffer, 8)?;
+        let longs = section(buffer, longs.saturating_mul(8))?;
+        Ok(Sections {
+            bytes,
+            shorts,
+            ints,
+            longs,
+        })
+    }
+}
+
+struct Decoder<'a> {
+    strings: Vec<String>,
+    types: Cursor<'a>,
+    sections: Sections<'a>,
+    /// Blobs stored outside of the buffer, with the length of every blob.
+    blobs: Option<(Cursor<'a>, std::vec::IntoIter<usize>)>,
+    /// Version 5 stores the number of strings and the keys of objects apart from the values.
+    keys: Option<Cursor<'a>>,
+    /// The legacy container stores everything inline, including the types.
+    legacy: bool,
+    /// How many more values can be decoded, since some types take no space a typed array could otherwise be huge.
+    remaining_values: usize,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Create a decoder for a buffer of `size` bytes, the strings and types are set once they are read.
+    fn new(sections: Sections<'a>, legacy: bool, size: usize) -> Self {
+        Decoder {
+            strings: Vec::new(),
+            types: Cursor::new(&[], 0),
+            sections,
+            blobs: None,
+            keys: None,
+            legacy,
+            remaining_values: size.saturating_add(1).saturating_mul(MAX_COMPRESSION_RATIO),
+            depth: 0,
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let sections = &mut self.sections;
+        match (self.legacy, N) {
+            (true, _) => sections.ints.bytes(),
+            (false, 1) => sections.bytes.bytes(),
+            (false, 2) => sections.shorts.bytes(),
+            (false, 4) => sections.ints.bytes(),
+            (false, _) => sections.longs.bytes(),
+        }
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        self.bytes().map(i32::from_le_bytes)
+    }
+
+    fn length(&mut self) -> Result<usize> {
+        self.bytes().map(u32::from_le_bytes).map(count)
+    }
+
+    fn key_ints(&mut self) -> &mut Cursor<'a> {
+        self.keys.as_mut().unwrap_or(&mut self.sections.ints)
+    }
+
+    fn string_count(&mut self) -> Result<usize> {
+        self.key_ints().u32().map(count)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let id = self.int()?;
+        self.string_by_id(id)
+    }
+
+    fn key(&mut self) -> Result<String> {
+        let id = self.key_ints().bytes().map(i32::from_le_bytes)?;
+        self.string_by_id(id)
+    }
+
+    fn string_by_id(&mut self, id: i32) -> Result<String> {
+        if id == -1 {
+            return Ok(String::new());
+        }
+        match usize::try_from(id).ok().and_then(|id| self.strings.get(id)) {
+            Some(string) => Ok(string.clone()),
+            None => self
+                .key_ints()
+                .error(format!("string id {id} out of range")),
+        }
+    }
+
+    fn ty(&mut self) -> Result<(u8, u8)> {
+        let types = if self.legacy {
+            &mut self.sections.ints
+        } else {
+            &mut self.types
+        };
+        let [ty] = types.bytes()?;
+        if ty & 0x80 == 0 {
+            return Ok((ty, 0));
+        }
+        let [flag] = types.bytes()?;
+        Ok((ty & if self.legacy { 0x7F } else { 0x3F }, flag))
+    }
+
+    fn value(&mut self) -> Result<Kv3Value> {
+        let (ty, flag) = self.ty()?;
+        self.typed_value(ty, flag)
+    }
+
+    fn typed_value(&mut self, ty: u8, flag: u8) -> Result<Kv3Value> {
+        let Some(remaining_values) = self.remaining_values.checked_sub(1) else {
+            return self
+                .types
+                .error("too many KeyValues3 values for the size of the data");
+        };
+        self.remaining_values = remaining_values;
+        let value = match ty {
+            8 | 9 | 10 | 24 => {
+                if self.depth >= MAX_DEPTH {
+                    return self.types.error("KeyValues3 values are nested too deep");
+                }
+                self.depth += 1;
+                let value = self.container(ty);
+                self.depth -= 1;
+                value?
+            }
+            ty => self.scalar(ty)?,
+        };
+        Ok(match flag {
+            0 => value,
+            flag => match FLAGS.get(flag as usize) {
+                Some(flag) => Kv3Value::Flagged(flag.to_string(), Box::new(value)),
+                None => return self.types.error(format!("unknown KeyValues3 flag {flag}")),
+            },
+        })
+    }
+
+    fn container(&mut self, ty: u8) -> Result<Kv3Value> {
+        Ok(match ty {
+            8 => {
+                let length = self.length()?;
+                let values = (0..length).map(|_| self.value()).collect::<Result<_>>()?;
+                Kv3Value::Array(values)
+            }
+            9 => {
+                let length = self.length()?;
+                let mut object = Kv3Object::default();
+                for _ in 0..length {
+                    let key = self.key()?;
+                    let value = self.value()?;
+                    object.0.push((key, value));
+                }
+                Kv3Value::Object(object)
+            }
+            10 | 24 => {
+                let length = if ty == 10 {
+                    self.length()?
+                } else {
+                    self.bytes::<1>()?[0] as usize
+                };
+                let (ty, flag) = self.ty()?;
+                let values = (0..length)
+                    .map(|_| self.typed_value(ty, flag))
+                    .collect::<Result<_>>()?;
+                Kv3Value::Array(values)
+            }
+            ty => unreachable!("type {ty} isn't a container"),
+        })
+    }
+
+    fn scalar(&mut self, ty: u8) -> Result<Kv3Value> {
+        Ok(match ty {
+            1 => Kv3Value::Null,
+            2 => Kv3Value::Bool(self.bytes::<1>()?[0] != 0),
+            3 => Kv3Value::Int(i64::from_le_bytes(self.bytes()?)),
+            4 => {
+                let value = u64::from_le_bytes(self.bytes()?);
+                i64::try_from(value).map_or(Kv3Value::UInt(value), Kv3Value::Int)
+            }
+            5 => Kv3Value::Float(f64::from_le_bytes(self.bytes()?)),
+            6 => Kv3Value::String(self.string()?),
+            7 => Kv3Value::Binary(self.blob()?),
+            11 => Kv3Value::Int(self.int()?.into()),
+            12 => Kv3Value::Int(u32::from_le_bytes(self.bytes()?).into()),
+            13 => Kv3Value::Bool(true),
+            14 => Kv3Value::Bool(false),
+            15 => Kv3Value::Int(0),
+            16 => Kv3Value::Int(1),
+            17 => Kv3Value::Float(0.0),
+            18 => Kv3Value::Float(1.0),
+            19 => Kv3Value::Float(f32::from_le_bytes(self.bytes()?).into()),
+            20 => Kv3Value::Int(i16::from_le_bytes(self.bytes()?).into()),
+            21 => Kv3Value::Int(u16::from_le_bytes(self.bytes()?).into()),
+            23 => Kv3Value::Int(self.bytes::<1>()?[0].into()),
+            ty => return self.types.error(format!("unknown KeyValues3 type {ty}")),
+        })
+    }
+
+    fn blob(&mut self) -> Result<Vec<u8>> {
+        if let Some((data, lengths)) = &mut self.blobs {
+            return match lengths.next() {
+                Some(length) => Ok(data.slice(length)?.to_vec()),
+                None => data.error("more binary blobs than blocks"),
+            };
+        }
+        let length = self.length()?;
+        let bytes = if self.legacy {
+            &mut self.sections.ints
+        } else {
+            &mut self.sections.bytes
+        };
+        Ok(bytes.slice(length)?.to_vec())
+    }
+}
+
+fn strings(cursor: &mut Cursor, count: usize) -> Result<Vec<String>> {
+    (0..count)
+        .map(|_| cursor.string().map(String::from))
+        .collect()
+}
+
+fn header(format: [u8; 16]) -> Kv3Header {
+    Kv3Header {
+        format: Kv3Format::new("generic", guid(format)),
+        ..Kv3Header::default()
+    }
+}
+
+fn read_legacy(cursor: &mut Cursor) -> Result<Kv3Document> {
+    let encoding = guid(cursor.bytes()?);
+    let format = cursor.bytes()?;
+    let buffer = match encoding.as_str() {
+        ENCODING_UNCOMPRESSED => cursor.remaining().to_vec(),
+        ENCODING_BLOCK_COMPRESSED => block_decompress(cursor.remaining())?,
+        ENCODING_LZ4 => {
+            let size = count(cursor.u32()?);
+            lz4(cursor.remaining(), size)?
+        }
+        encoding => return cursor.error(format!("unknown KeyValues3 encoding {encoding}")),
+    };
+
+    let buffer_size = buffer.len();
+    let mut buffer = Cursor::new(&buffer, 0);
+    let string_count = count(buffer.u32()?);
+    let strings = strings(&mut buffer, string_count)?;
+    let sections = Sections {
+        bytes: Cursor::new(&[], 0),
+        shorts: Cursor::new(&[], 0),
+        ints: buffer,
+        longs: Cursor::new(&[], 0),
+    };
+    let mut decoder = Decoder::new(sections, true, buffer_size);
+    decoder.strings = strings;
+    Ok(Kv3Document {
+        header: header(format),
+        root: decoder.value()?,
+    })
+}
+
+fn read_v1(cursor: &mut Cursor) -> Result<Kv3Document> {
+    let format = cursor.bytes()?;
+    let compression = cursor.u32()?;
+    let bytes = count(cursor.u32()?);
+    let ints = count(cursor.u32()?);
+    let longs = count(cursor.u32()?);
+    let buffer = match compression {
+        0 => {
+            let size = count(cursor.u32()?);
+            cursor.slice(size)?.to_vec()
+        }
+        1 => {
+            let size = count(cursor.u32()?);
+            lz4(cursor.remaining(), size)?
+        }
+        method => return cursor.error(format!("unknown KeyValues3 compression {method}")),
+    };
+
+    let buffer_size = buffer.len();
+    let mut buffer = Cursor::new(&buffer, 0);
+    let sections = Sections::read(&mut buffer, bytes, 0, ints, longs)?;
+    let mut decoder = Decoder::new(sections, false, buffer_size);
+    // the first integer is the number of strings
+    let string_count = decoder.length()?;
+    decoder.strings = strings(&mut buffer, string_count)?;
+    // the types fill the rest of the buffer, except for the trailer
+    let types_length = buffer.remaining().len().saturating_sub(4);
+    decoder.types = section(&mut buffer, types_length)?;
+    if buffer.u32()? != TRAILER {
+        return buffer.error("invalid KeyValues3 trailer");
+    }
+
+    Ok(Kv3Document {
+        header: header(format),
+        root: decoder.value()?,
+    })
+}
+
+/// Decompress a buffer, zstd compresses the blocks that follow the last buffer in the same frame.
+fn decompress(
+    cursor: &Cursor,
+    compression: u32,
+    compressed: &[u8],
+    size: usize,
+    blocks_size: usize,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    match compression {
+        0 => Ok((compressed.to_vec(), Vec::new())),
+        1 => Ok((lz4(compressed, size)?, Vec::new())),
+        2 => {
+            let Some(total_size) = size.checked_add(blocks_size) else {
+                return cursor.error("KeyValues3 buffer size overflows");
+            };
+            let mut buffer = zstd(compressed, total_size)?;
+            let blocks = buffer.split_off(size);
+            Ok((buffer, blocks))
+        }
+        method => cursor.error(format!("unknown KeyValues3 compression {method}")),
+    }
+}
+
+/// The second buffer of version 5, which holds the values.
+struct ValueBuffer {
+    compressed_size: usize,
+    size: usize,
+    bytes: usize,
+    shorts: usize,
+    ints: usize,
+    longs: usize,
+}
+
+impl ValueBuffer {
+    fn read(cursor: &mut Cursor) -> Result<Self> {
+        let compressed_size = count(cursor.u32()?);
+        let size = count(cursor.u32()?);
+        let bytes = count(cursor.u32()?);
+        let shorts = count(cursor.u32()?);
+        let ints = count(cursor.u32()?);
+        let longs = count(cursor.u32()?);
+        // the number of objects and arrays, for preallocating
+        let _object_count = cursor.u32()?;
+        let _array_count = cursor.u32()?;
+        Ok(ValueBuffer {
+            compressed_size,
+            size,
+            bytes,
+            shorts,
+            ints,
+            longs,
+        })
+    }
+}
+
+fn read_v2(cursor: &mut Cursor, version: u32) -> Result<Kv3Document> {
+    let format = cursor.bytes()?;
+    let compression = cursor.u32()?;
+    let _dictionary_id = u16::from_le_bytes(cursor.bytes()?);
+    let frame_size = u16::from_le_bytes(cursor.bytes()?) as usize;
+    let bytes = count(cursor.u32()?);
+    let ints = count(cursor.u32()?);
+    let longs = count(cursor.u32()?);
+    let strings_and_types_size = count(cursor.u32()?);
+    let _object_count = u16::from_le_bytes(cursor.bytes()?);
+    let _array_count = u16::from_le_bytes(cursor.bytes()?);
+    let size = count(cursor.u32()?);
+    let compressed_size = count(cursor.u32()?);
+    let block_count = count(cursor.u32()?);
+    let block_total_size = count(cursor.u32()?);
+    let shorts = if version >= 4 {
+        let shorts = count(cursor.u32()?);
+        let _unknown = cursor.u32()?;
+        shorts
+    } else {
+        0
+    };
+    let value_buffer = if version >= 5 {
+        Some(ValueBuffer::read(cursor)?)
+    } else {
+        None
+    };
+
+    let compressed = cursor.slice(compressed_size)?;
+    let (buffer, values, mut blocks) = match &value_buffer {
+        Some(value_buffer) => {
+            let compressed_values = cursor.slice(value_buffer.compressed_size)?;
+            let (buffer, _) = decompress(cursor, compression, compressed, size, 0)?;
+            let (values, blocks) = decompress(
+                cursor,
+                compression,
+                compressed_values,
+                value_buffer.size,
+                block_total_size,
+            )?;
+            (buffer, values, blocks)
+        }
+        None => {
+            let (buffer, blocks) =
+                decompress(cursor, compression, compressed, size, block_total_size)?;
+            (buffer, Vec::new(), blocks)
+        }
+    };
+    if compression == 0 {
+        blocks = cursor.remaining().to_vec();
+    }
+
+    let buffer_size = buffer.len().saturating_add(values.len());
+    let mut buffer = Cursor::new(&buffer, 0);
+    let sections = Sections::read(&mut buffer, bytes, shorts, ints, longs)?;
+    let mut decoder = match &value_buffer {
+        Some(value_buffer) => {
+            let mut values = Cursor::new(&values, 0);
+            let value_sections = Sections::read(
+                &mut values,
+                value_buffer.bytes,
+                value_buffer.shorts,
+                value_buffer.ints,
+                value_buffer.longs,
+            )?;
+            let mut decoder = Decoder::new(value_sections, false, buffer_size);
+            decoder.keys = Some(sections.ints);
+            decoder
+        }
+        None => Decoder::new(sections, false, buffer_size),
+    };
+    let strings_start = buffer.position();
+    let string_count = decoder.string_count()?;
+    decoder.strings = strings(&mut buffer, string_count)?;
+    let strings_length = buffer.position() - strings_start;
+    let Some(types_length) = strings_and_types_size.checked_sub(strings_length) else {
+        return buffer.error("string table larger than the strings and types");
+    };
+    decoder.types = section(&mut buffer, types_length)?;
+
+    let lengths = (0..block_count)
+        .map(|_| buffer.u32().map(count))
+        .collect::<Result<Vec<_>>>()?;
+    if buffer.u32()? != TRAILER {
+        return buffer.error("invalid KeyValues3 trailer");
+    }
+    if compression == 1 && block_count > 0 {
+        // every block is compressed in chunks of at most `frame_size` bytes,
+        // with the compressed size of every chunk following the trailer
+        let mut compressed = Cursor::new(cursor.remaining(), 0);
+        for &length in &lengths {
+            let mut block = Vec::with_capacity(decompressed_size(compressed.remaining(), length)?);
+            while block.len() < length {
+                let chunk_size = count(u16::from_le_bytes(buffer.bytes()?).into());
+                let chunk = compressed.slice(chunk_size)?;
+                let mut out = vec![0; frame_size.min(length - block.len())];
+                let written = lz4_flex::block::decompress_into_with_dict(chunk, &mut out, &block)
+                    .or_else(|e| compressed.error(format!("invalid LZ4 data: {e}")))?;
+                if written == 0 {
+                    return compressed.error("empty LZ4 chunk");
+                }
+                block.extend_from_slice(&out[..written]);
+            }
+            blocks.extend(block);
+        }
+    }
+    if block_count > 0 {
+        decoder.blobs = Some((Cursor::new(&blocks, 0), lengths.into_iter()));
+    }
+
+    Ok(Kv3Document {
+        header: header(format),
+        root: decoder.value()?,
+    })
+}
+
+pub(super) fn read(data: &[u8]) -> Result<Kv3Document> {
+    let mut cursor = Cursor::new(data, 0);
+    match cursor.u32()? {
+        MAGIC_LEGACY => read_legacy(&mut cursor),
+        MAGIC_V1 => read_v1(&mut cursor),
+        magic @ (MAGIC_V2 | MAGIC_V3 | MAGIC_V4 | MAGIC_V5) => read_v2(&mut cursor, magic & 0xFF),
+        magic => error(format!("unknown KeyValues3 magic 0x{magic:08x}"), 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guid() {
+        assert_eq!(
+            ENCODING_UNCOMPRESSED,
+            guid([
+                0x00, 0x05, 0x86, 0x1B, 0xD8, 0xF7, 0xC1, 0x40, 0xAD, 0x82, 0x75, 0xA4, 0x82, 0x67,
+                0xE7, 0x14
+            ])
+        );
+    }
+
+    #[test]
+    fn test_block_decompress() {
+        // "abc" followed by a back reference of 6 bytes at offset 3
+        let data = [9, 0, 0, 0, 0b1000, 0, b'a', b'b', b'c', 0x23, 0x00];
+        assert_eq!(b"abcabcabc", block_decompress(&data).unwrap().as_slice());
+
+        let data = [0, 0, 0, 0x80, b'r', b'a', b'w'];
+        assert_eq!(b"raw", block_decompress(&data).unwrap().as_slice());
+
+        let data = [4, 0, 0, 0, 0b1, 0, 0x00, 0x00];
+        assert!(block_decompress(&data).is_err());
+    }
+
+    #[test]
+    fn test_lz4_blocks() {
+        let blob = b"binary";
+        let mut buffer = Vec::new();
+        for int in [1u32, 1, 0] {
+            buffer.extend(int.to_le_bytes());
+        }
+        // align the (empty) eight byte section
+        buffer.extend([0; 4]);
+        buffer.extend(b"data\0");
+        buffer.extend([9, 7]);
+        buffer.extend(6u32.to_le_bytes());
+        buffer.extend(TRAILER.to_le_bytes());
+        let chunks = [
+            lz4_flex::block::compress(&blob[..4]),
+            lz4_flex::block::compress(&blob[4..]),
+        ];
+        for chunk in &chunks {
+            buffer.extend((chunk.len() as u16).to_le_bytes());
+        }
+        let compressed = lz4_flex::block::compress(&buffer);
+
+        let mut data = MAGIC_V2.to_le_bytes().to_vec();
+        data.extend([0; 16]);
+        data.extend(1u32.to_le_bytes());
+        data.extend(0u16.to_le_bytes());
+        data.extend(4u16.to_le_bytes());
+        for value in [0u32, 3, 0, 7] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend([1, 0, 0, 0]);
+        for value in [buffer.len(), compressed.len(), 1, blob.len()] {
+            data.extend((value as u32).to_le_bytes());
+        }
+        data.extend(compressed);
+        for chunk in chunks {
+            data.extend(chunk);
+        }
+
+        let document = read(&data).unwrap();
+        assert_eq!(
+            Some(&Kv3Value::Binary(blob.to_vec())),
+            document.root.get("data")
+        );
+    }
+
+    fn legacy(buffer: &[u8]) -> Vec<u8> {
+        let mut data = MAGIC_LEGACY.to_le_bytes().to_vec();
+        data.extend([
+            0x00, 0x05, 0x86, 0x1B, 0xD8, 0xF7, 0xC1, 0x40, 0xAD, 0x82, 0x75, 0xA4, 0x82, 0x67,
+            0xE7, 0x14,
+        ]);
+        data.extend([0; 16]);
+        // no strings
+        data.extend(0u32.to_le_bytes());
+        data.extend(buffer);
+        data
+    }
+
+    #[test]
+    fn test_crafted_sizes() {
+        let mut data = MAGIC_V1.to_le_bytes().to_vec();
+        data.extend([0; 16]);
+        for value in [1, 0, 0, 0, u32::MAX] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend([0; 6]);
+        assert!(read(&data).is_err());
+
+        let mut data = MAGIC_V2.to_le_bytes().to_vec();
+        data.extend([0; 16]);
+        data.extend(2u32.to_le_bytes());
+        data.extend([0; 4]);
+        for value in [0, 0, 0, 0, 0, u32::MAX, 4, 0, u32::MAX] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend([0; 4]);
+        assert!(read(&data).is_err());
+
+        // a typed array of four billion `true` values
+        let mut buffer = vec![10];
+        buffer.extend(u32::MAX.to_le_bytes());
+        buffer.push(13);
+        let error = read(&legacy(&buffer)).unwrap_err();
+        assert!(error.to_string().starts_with("too many KeyValues3 values"));
+    }
+
+    #[test]
+    fn test_nested_too_deep() {
+        let mut buffer = Vec::new();
+        for _ in 0..100_000 {
+            buffer.push(8);
+            buffer.extend(1u32.to_le_bytes());
+        }
+        buffer.push(1);
+        let error = read(&legacy(&buffer)).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("KeyValues3 values are nested too deep"));
+
+        let mut buffer = Vec::new();
+        for _ in 0..8 {
+            buffer.push(8);
+            buffer.extend(1u32.to_le_bytes());
+        }
+        buffer.push(1);
+        assert!(read(&legacy(&buffer)).is_ok());
+    }
+}
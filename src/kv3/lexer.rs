@@ -0,0 +1,112 @@
+use logos::{Lexer, Logos};
+use parse_display::Display;
+
+/// Skip until the end of a delimited token, failing if it is never closed.
+fn until(lex: &mut Lexer<Kv3Token>, end: &str) -> bool {
+    match lex.remainder().find(end) {
+        Some(position) => {
+            lex.bump(position + end.len());
+            true
+        }
+        None => false,
+    }
+}
+
+/// KeyValues3 text token.
+#[derive(PartialEq, Debug, Logos, Display, Clone, Copy)]
+#[logos(skip r"[ \t\f\r\n]+")] // whitespace
+#[logos(skip r"//[^\n]*")] // comments
+pub enum Kv3Token {
+    /// The `<!-- kv3 ... -->` header.
+    #[token("<!--", |lex| until(lex, "-->"))]
+    #[display("header")]
+    Header,
+    #[token("/*", |lex| until(lex, "*/"))]
+    #[display("comment")]
+    Comment,
+    #[token("{")]
+    #[display("start of object")]
+    ObjectStart,
+    #[token("}")]
+    #[display("end of object")]
+    ObjectEnd,
+    #[token("[")]
+    #[display("start of array")]
+    ArrayStart,
+    #[token("]")]
+    #[display("end of array")]
+    ArrayEnd,
+    #[token("=")]
+    #[display("'='")]
+    Assign,
+    #[token(",")]
+    #[display("','")]
+    Comma,
+    #[token(":")]
+    #[display("':'")]
+    Colon,
+    /// A bare key, flag or `true`, `false` and `null`.
+    #[regex("[A-Za-z_][A-Za-z0-9_.]*")]
+    #[display("identifier")]
+    Identifier,
+    #[regex(r"[-+]?([0-9]+\.?[0-9]*|\.[0-9]+)([eE][-+]?[0-9]+)?")]
+    #[display("number")]
+    Number,
+    #[regex("\"([^\"\\\\]|\\\\.)*\"")]
+    #[display("string")]
+    String,
+    /// A `"""` delimited string spanning multiple lines.
+    #[token("\"\"\"", |lex| until(lex, "\"\"\""))]
+    #[display("multi-line string")]
+    MultiLineString,
+    /// Hex encoded bytes like `#[ 00 01 ff ]`.
+    #[regex(r"#\[[0-9a-fA-F \t\f\r\n]*\]")]
+    #[display("binary blob")]
+    Binary,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Kv3Token;
+    use logos::Logos;
+
+    fn get_tokens(input: &str) -> Vec<(Kv3Token, &str)> {
+        Kv3Token::lexer(input)
+            .spanned()
+            .map(|(res, span)| (res.unwrap(), &input[span]))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            vec![
+                (Kv3Token::Header, "<!-- kv3 -->"),
+                (Kv3Token::ObjectStart, "{"),
+                (Kv3Token::Identifier, "key"),
+                (Kv3Token::Assign, "="),
+                (Kv3Token::Identifier, "resource"),
+                (Kv3Token::Colon, ":"),
+                (Kv3Token::String, r#""a\"b""#),
+                (Kv3Token::Comment, "/* multi\nline */"),
+                (Kv3Token::ArrayStart, "["),
+                (Kv3Token::Number, "-1.5e3"),
+                (Kv3Token::Comma, ","),
+                (Kv3Token::Number, "2"),
+                (Kv3Token::ArrayEnd, "]"),
+                (Kv3Token::MultiLineString, "\"\"\"\nfoo \"bar\"\n\"\"\""),
+                (Kv3Token::Binary, "#[ 00 ff ]"),
+                (Kv3Token::ObjectEnd, "}"),
+            ],
+            get_tokens(
+                "<!-- kv3 -->{ key = resource:\"a\\\"b\" /* multi\nline */ [-1.5e3, 2] // comment\n\"\"\"\nfoo \"bar\"\n\"\"\" #[ 00 ff ] }"
+            )
+        );
+    }
+
+    #[test]
+    fn test_unterminated() {
+        assert!(Kv3Token::lexer("/* foo").next().unwrap().is_err());
+        assert!(Kv3Token::lexer("\"\"\" foo").next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! KeyValues3, the successor of vdf used by Source 2 games.
+//!
+//! KeyValues3 text starts with a `<!-- kv3 ... -->` header naming the encoding and format,
+//! followed by a single value, usually an object:
+//!
+//! ```text
+//! <!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+//! {
+//!     name = "example"
+//!     enabled = true
+//!     scale = [ 1.0, 2.0 ]
+//!     model = resource:"models/example.vmdl"
+//! }
+//! ```
+//!
+//! Unlike vdf, values are typed. A [`Kv3Value`] can be converted into an [`Entry`] to use it
+//! with the rest of the crate, [`from_str`](crate::from_str) detects the header and deserializes
+//! KeyValues3 text through that conversion.
+//!
+//! Binary KeyValues3 from compiled resources is decoded into the same tree with
+//! [`Kv3Document::from_bytes`].
+
+mod binary;
+mod lexer;
+mod parser;
+mod writer;
+
+use crate::entry::{Array, Entry, Table};
+use crate::Result;
+use serde::de::DeserializeOwned;
+use std::fmt::{self, Display, Formatter};
+
+pub use lexer::Kv3Token;
+
+/// The encoding or format named in the header, with the guid identifying its version.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Kv3Format {
+    pub name: String,
+    pub version: String,
+}
+
+impl Kv3Format {
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, version: V) -> Self {
+        Kv3Format {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+}
+
+/// The `<!-- kv3 ... -->` header.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Kv3Header {
+    pub encoding: Kv3Format,
+    pub format: Kv3Format,
+}
+
+impl Default for Kv3Header {
+    fn default() -> Self {
+        Kv3Header {
+            encoding: Kv3Format::new("text", "e21c7f3c-8a33-41c5-9977-a76d3a32aa0d"),
+            format: Kv3Format::new("generic", "7412167c-06e9-4698-aff2-e63eb59037e7"),
+        }
+    }
+}
+
+impl Display for Kv3Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!-- kv3 encoding:{}:version{{{}}} format:{}:version{{{}}} -->",
+            self.encoding.name, self.encoding.version, self.format.name, self.format.version
+        )
+    }
+}
+
+/// A typed KeyValues3 value.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Kv3Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    /// An unsigned integer too large for [`Kv3Value::Int`]
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<Kv3Value>),
+    Object(Kv3Object),
+    /// A value with a flag like `resource:"path"` or `subclass:{ ... }`.
+    Flagged(String, Box<Kv3Value>),
+}
+
+impl Kv3Value {
+    pub fn as_object(&self) -> Option<&Kv3Object> {
+        match self.unflagged() {
+            Kv3Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Kv3Value]> {
+        match self.unflagged() {
+            Kv3Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unflagged() {
+            Kv3Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.unflagged() {
+            Kv3Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.unflagged() {
+            Kv3Value::Int(value) => Some(*value),
+            Kv3Value::UInt(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.unflagged() {
+            Kv3Value::Float(value) => Some(*value),
+            Kv3Value::Int(value) => Some(*value as f64),
+            Kv3Value::UInt(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    /// The flag of the value, if any.
+    pub fn flag(&self) -> Option<&str> {
+        match self {
+            Kv3Value::Flagged(flag, _) => Some(flag),
+            _ => None,
+        }
+    }
+
+    /// The value without its flag.
+    pub fn unflagged(&self) -> &Kv3Value {
+        match self {
+            Kv3Value::Flagged(_, value) => value.unflagged(),
+            value => value,
+        }
+    }
+
+    /// Get an entry by key, if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Kv3Value> {
+        self.as_object()?.get(key)
+    }
+}
+
+impl From<Kv3Object> for Kv3Value {
+    fn from(object: Kv3Object) -> Self {
+        Kv3Value::Object(object)
+    }
+}
+
+/// Converts the value to vdf, booleans become `1` or `0`, `null` an empty string and flags are dropped.
+impl From<Kv3Value> for Entry {
+    fn from(value: Kv3Value) -> Self {
+        match value {
+            Kv3Value::Null => Entry::Value("".into()),
+            Kv3Value::Bool(value) => Entry::Value(if value { "1" } else { "0" }.into()),
+            Kv3Value::Int(value) => Entry::Value(value.to_string().into()),
+            Kv3Value::UInt(value) => Entry::Value(value.to_string().into()),
+            Kv3Value::Float(value) => Entry::Value(value.to_string().into()),
+            Kv3Value::String(value) => Entry::Value(value.into()),
+            Kv3Value::Binary(value) => Entry::Value(writer::hex(&value).into()),
+            Kv3Value::Array(values) => Entry::Array(Array::from(
+                values.into_iter().map(Entry::from).collect::<Vec<_>>(),
+            )),
+            Kv3Value::Object(object) => Entry::Table(object.into()),
+            Kv3Value::Flagged(_, value) => Entry::from(*value),
+        }
+    }
+}
+
+/// The members of a KeyValues3 object, in the order they are defined in.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Kv3Object(Vec<(String, Kv3Value)>);
+
+impl Kv3Object {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Kv3Value> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Kv3Value> {
+        self.0
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Iterate over all members in the order they are defined in.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Kv3Value)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Set the value for a key, replacing the existing value or adding it to the end of the object.
+    pub fn insert<K: Into<String>, V: Into<Kv3Value>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<Kv3Value> {
+        let key = key.into();
+        let value = value.into();
+        match self.get_mut(&key) {
+            Some(existing) => Some(std::mem::replace(existing, value)),
+            None => {
+                self.0.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Kv3Value> {
+        let index = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(index).1)
+    }
+}
+
+impl<K: Into<String>, V: Into<Kv3Value>> FromIterator<(K, V)> for Kv3Object {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut object = Kv3Object::default();
+        for (key, value) in iter {
+            object.insert(key, value);
+        }
+        object
+    }
+}
+
+impl From<Kv3Object> for Table {
+    fn from(object: Kv3Object) -> Self {
+        object
+            .0
+            .into_iter()
+            .map(|(key, value)| (key, Entry::from(value)))
+            .collect()
+    }
+}
+
+/// A KeyValues3 document, the header and the root value.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Kv3Document {
+    pub header: Kv3Header,
+    pub root: Kv3Value,
+}
+
+impl Default for Kv3Value {
+    fn default() -> Self {
+        Kv3Value::Object(Kv3Object::default())
+    }
+}
+
+impl Kv3Document {
+    /// Parse KeyValues3 text, a missing header is treated as the default text header.
+    pub fn parse(input: &str) -> Result<Kv3Document> {
+        parser::parse(input)
+    }
+
+    /// Decode binary KeyValues3, like the DATA block of a compiled Source 2 resource.
+    pub fn from_bytes(data: &[u8]) -> Result<Kv3Document> {
+        binary::read(data)
+    }
+}
+
+/// Writes the document as KeyValues3 text.
+impl Display for Kv3Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header)?;
+        writer::write_value(f, &self.root, 0)?;
+        writeln!(f)
+    }
+}
+
+/// Check if the input starts with a KeyValues3 header.
+pub fn is_kv3(input: &str) -> bool {
+    input.trim_start().starts_with("<!-- kv3")
+}
+
+/// Deserialize a type from KeyValues3 text.
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T> {
+    crate::from_entry(Kv3Document::parse(input)?.root.into())
+}
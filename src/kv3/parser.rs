@@ -0,0 +1,340 @@
+use super::{Kv3Document, Kv3Format, Kv3Header, Kv3Object, Kv3Token, Kv3Value};
+use crate::error::Kv3Error;
+use crate::Result;
+use logos::{Lexer, Logos, Span};
+
+/// How deep arrays, objects and flags can be nested.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    source: &'a str,
+    lexer: Lexer<'a, Kv3Token>,
+    peeked: Option<(Kv3Token, Span)>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, error: impl Into<String>, span: Span) -> Result<T> {
+        Err(Kv3Error::new(error, span, self.source).into())
+    }
+
+    fn next(&mut self) -> Result<Option<(Kv3Token, Span)>> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(Some(peeked));
+        }
+        loop {
+            match self.lexer.next() {
+                Some(Ok(Kv3Token::Comment)) => {}
+                Some(Ok(token)) => return Ok(Some((token, self.lexer.span()))),
+                Some(Err(_)) => return self.error("invalid token", self.lexer.span()),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<Kv3Token>> {
+        if self.peeked.is_none() {
+            self.peeked = self.next()?;
+        }
+        Ok(self.peeked.as_ref().map(|(token, _)| *token))
+    }
+
+    fn expect(&mut self, expected: Kv3Token) -> Result<Span> {
+        match self.next()? {
+            Some((token, span)) if token == expected => Ok(span),
+            Some((token, span)) => self.error(format!("expected {expected}, found {token}"), span),
+            None => self.error(format!("expected {expected}"), self.end()),
+        }
+    }
+
+    fn end(&self) -> Span {
+        self.source.len()..self.source.len()
+    }
+
+    fn header(&mut self) -> Result<Kv3Header> {
+        if self.peek()? != Some(Kv3Token::Header) {
+            return Ok(Kv3Header::default());
+        }
+        let span = self.expect(Kv3Token::Header)?;
+        let content = &self.source[span.start + 4..span.end - 3];
+        let mut parts = content.split_whitespace();
+        if parts.next() != Some("kv3") {
+            return self.error("not a kv3 header", span);
+        }
+
+        let mut header = Kv3Header::default();
+        for part in parts {
+            let mut fields = part.splitn(3, ':');
+            let (Some(kind), Some(name), Some(version)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return self.error(format!("invalid header field {part:?}"), span);
+            };
+            let version = version
+                .strip_prefix("version{")
+                .and_then(|version| version.strip_suffix('}'))
+                .unwrap_or(version);
+            let format = Kv3Format::new(name, version);
+            match kind {
+                "encoding" => header.encoding = format,
+                "format" => header.format = format,
+                _ => {}
+            }
+        }
+        Ok(header)
+    }
+
+    /// Parse a value that contains other values, the span is that of the token that opens it.
+    fn nested<T>(&mut self, span: Span, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return self.error("values are nested too deep", span);
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn value(&mut self) -> Result<Kv3Value> {
+        let Some((token, span)) = self.next()? else {
+            return self.error("expected value", self.end());
+        };
+        let slice = &self.source[span.clone()];
+        Ok(match token {
+            Kv3Token::ObjectStart => Kv3Value::Object(self.nested(span, Self::object)?),
+            Kv3Token::ArrayStart => Kv3Value::Array(self.nested(span, Self::array)?),
+            Kv3Token::String => Kv3Value::String(unescape(&slice[1..slice.len() - 1])),
+            Kv3Token::MultiLineString => Kv3Value::String(multi_line(&slice[3..slice.len() - 3])),
+            Kv3Token::Number => match number(slice) {
+                Some(value) => value,
+                None => return self.error("invalid number", span),
+            },
+            Kv3Token::Binary => match binary(&slice[2..slice.len() - 1]) {
+                Some(value) => Kv3Value::Binary(value),
+                None => return self.error("invalid binary blob", span),
+            },
+            Kv3Token::Identifier if self.peek()? == Some(Kv3Token::Colon) => {
+                self.next()?;
+                let value = self.nested(span, Self::value)?;
+                Kv3Value::Flagged(slice.into(), Box::new(value))
+            }
+            Kv3Token::Identifier => match slice {
+                "true" => Kv3Value::Bool(true),
+                "false" => Kv3Value::Bool(false),
+                "null" => Kv3Value::Null,
+                _ => return self.error(format!("unknown literal {slice:?}"), span),
+            },
+            token => return self.error(format!("expected value, found {token}"), span),
+        })
+    }
+
+    fn object(&mut self) -> Result<Kv3Object> {
+        let mut object = Kv3Object::default();
+        loop {
+            let Some((token, span)) = self.next()? else {
+                return self.error("unterminated object", self.end());
+            };
+            let key = match token {
+                Kv3Token::ObjectEnd => return Ok(object),
+                Kv3Token::Comma => continue,
+                Kv3Token::Identifier => self.source[span].to_string(),
+                Kv3Token::String => unescape(&self.source[span.start + 1..span.end - 1]),
+                token => return self.error(format!("expected key, found {token}"), span),
+            };
+            self.expect(Kv3Token::Assign)?;
+            let value = self.value()?;
+            object.0.push((key, value));
+        }
+    }
+
+    fn array(&mut self) -> Result<Vec<Kv3Value>> {
+        let mut array = Vec::new();
+        loop {
+            match self.peek()? {
+                Some(Kv3Token::ArrayEnd) => {
+                    self.next()?;
+                    return Ok(array);
+                }
+                None => return self.error("unterminated array", self.end()),
+                _ => array.push(self.value()?),
+            }
+            match self.next()? {
+                Some((Kv3Token::Comma, _)) => {}
+                Some((Kv3Token::ArrayEnd, _)) => return Ok(array),
+                Some((token, span)) => {
+                    return self.error(format!("expected ',' or end of array, found {token}"), span)
+                }
+                None => return self.error("unterminated array", self.end()),
+            }
+        }
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Multi-line strings start and end on their own line, those line breaks aren't part of the value.
+fn multi_line(value: &str) -> String {
+    let value = value
+        .strip_prefix("\r\n")
+        .or_else(|| value.strip_prefix('\n'))
+        .unwrap_or(value);
+    let value = value
+        .strip_suffix("\r\n")
+        .or_else(|| value.strip_suffix('\n'))
+        .unwrap_or(value);
+    value.to_string()
+}
+
+fn number(value: &str) -> Option<Kv3Value> {
+    if value.contains(['.', 'e', 'E']) {
+        return value.parse().ok().map(Kv3Value::Float);
+    }
+    let value = value.strip_prefix('+').unwrap_or(value);
+    match value.parse() {
+        Ok(value) => Some(Kv3Value::Int(value)),
+        Err(_) => value.parse().ok().map(Kv3Value::UInt),
+    }
+}
+
+fn binary(value: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = value
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+pub(super) fn parse(source: &str) -> Result<Kv3Document> {
+    let mut parser = Parser {
+        source,
+        lexer: Kv3Token::lexer(source),
+        peeked: None,
+        depth: 0,
+    };
+    let header = parser.header()?;
+    let root = parser.value()?;
+    if let Some((token, span)) = parser.next()? {
+        return parser.error(format!("expected end of document, found {token}"), span);
+    }
+    Ok(Kv3Document { header, root })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values() {
+        let document = parse(
+            r#"<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:modeldoc28:version{fb63b6ca-f435-4aa0-a2c7-c66ddc651dca} -->
+            {
+                // comment
+                int = -5
+                big = 18446744073709551615
+                float = 1.5
+                bool = true
+                null = null
+                "quoted key" = "a \"b\"\n"
+                /* block
+                   comment */
+                text = """
+line one
+line two
+"""
+                model = resource:"models/a.vmdl"
+                blob = #[ 00 01 ff ]
+                array = [ 1, "two", [ ], { a = 1 }, ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!("modeldoc28", document.header.format.name);
+        assert_eq!(
+            "fb63b6ca-f435-4aa0-a2c7-c66ddc651dca",
+            document.header.format.version
+        );
+
+        let root = &document.root;
+        assert_eq!(Some(&Kv3Value::Int(-5)), root.get("int"));
+        assert_eq!(Some(&Kv3Value::UInt(u64::MAX)), root.get("big"));
+        assert_eq!(Some(&Kv3Value::Float(1.5)), root.get("float"));
+        assert_eq!(Some(&Kv3Value::Bool(true)), root.get("bool"));
+        assert_eq!(Some(&Kv3Value::Null), root.get("null"));
+        assert_eq!(
+            Some("a \"b\"\n"),
+            root.get("quoted key").and_then(Kv3Value::as_str)
+        );
+        assert_eq!(
+            Some("line one\nline two"),
+            root.get("text").and_then(Kv3Value::as_str)
+        );
+        assert_eq!(Some("resource"), root.get("model").and_then(Kv3Value::flag));
+        assert_eq!(
+            Some("models/a.vmdl"),
+            root.get("model").and_then(Kv3Value::as_str)
+        );
+        assert_eq!(Some(&Kv3Value::Binary(vec![0, 1, 255])), root.get("blob"));
+        let array = root.get("array").and_then(Kv3Value::as_array).unwrap();
+        assert_eq!(4, array.len());
+        assert_eq!(Some(&Kv3Value::Int(1)), array[3].get("a"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = parse("{ a = 1").unwrap_err();
+        assert_eq!("unterminated object", error.to_string());
+        let error = parse("{ a = foo }").unwrap_err();
+        assert_eq!("unknown literal \"foo\"", error.to_string());
+        assert_eq!(Some((6..9).into()), error.span());
+        assert!(parse("{ a 1 }").is_err());
+        assert!(parse("{ a = [1 2] }").is_err());
+        assert!(parse("{} {}").is_err());
+        assert!(parse("{ a = #[ 0 ] }").is_err());
+    }
+
+    #[test]
+    fn test_depth() {
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(parse(&nested).is_ok());
+
+        let nested = format!(
+            "{{ a = {}{} }}",
+            "[".repeat(MAX_DEPTH),
+            "]".repeat(MAX_DEPTH)
+        );
+        let error = parse(&nested).unwrap_err();
+        assert_eq!("values are nested too deep", error.to_string());
+        let offset = 6 + MAX_DEPTH - 1;
+        assert_eq!(Some((offset..offset + 1).into()), error.span());
+
+        let error = parse(&"[".repeat(200_000)).unwrap_err();
+        assert_eq!("values are nested too deep", error.to_string());
+        assert_eq!(Some((MAX_DEPTH..MAX_DEPTH + 1).into()), error.span());
+
+        let flags = format!("{{ a = {}1 }}", "flag:".repeat(200_000));
+        assert!(parse(&flags).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use super::Kv3Value;
+use std::fmt::{Result, Write};
+
+fn indent<W: Write>(out: &mut W, level: usize) -> Result {
+    for _ in 0..level {
+        out.write_char('\t')?;
+    }
+    Ok(())
+}
+
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_bare_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && !matches!(key, "true" | "false" | "null")
+}
+
+fn write_string<W: Write>(out: &mut W, value: &str) -> Result {
+    if value.contains('\n') && !value.contains("\"\"\"") {
+        write!(out, "\"\"\"\n{value}\n\"\"\"")
+    } else {
+        write_quoted(out, value)
+    }
+}
+
+fn write_quoted<W: Write>(out: &mut W, value: &str) -> Result {
+    out.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\t' => out.write_str("\\t")?,
+            '\r' => out.write_str("\\r")?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Whether the value is written on the line of its key or starts on the next line.
+fn is_block(value: &Kv3Value) -> bool {
+    match value.unflagged() {
+        Kv3Value::Object(_) => true,
+        Kv3Value::Array(values) => values.iter().any(is_block),
+        _ => false,
+    }
+}
+
+/// Write a value, nested lines are indented by `level` tabs.
+pub(super) fn write_value<W: Write>(out: &mut W, value: &Kv3Value, level: usize) -> Result {
+    match value {
+        Kv3Value::Null => out.write_str("null"),
+        Kv3Value::Bool(value) => write!(out, "{value}"),
+        Kv3Value::Int(value) => write!(out, "{value}"),
+        Kv3Value::UInt(value) => write!(out, "{value}"),
+        // debug formatting always includes a decimal point or exponent
+        Kv3Value::Float(value) => write!(out, "{value:?}"),
+        Kv3Value::String(value) => write_string(out, value),
+        Kv3Value::Binary(value) => write!(out, "#[ {} ]", hex(value)),
+        Kv3Value::Flagged(flag, value) => {
+            write!(out, "{flag}:")?;
+            write_value(out, value, level)
+        }
+        Kv3Value::Array(values) if !values.iter().any(is_block) => {
+            out.write_char('[')?;
+            for (i, value) in values.iter().enumerate() {
+                out.write_str(if i == 0 { " " } else { ", " })?;
+                write_value(out, value, level)?;
+            }
+            out.write_str(if values.is_empty() { "]" } else { " ]" })
+        }
+        Kv3Value::Array(values) => {
+            out.write_str("[\n")?;
+            for value in values {
+                indent(out, level + 1)?;
+                write_value(out, value, level + 1)?;
+                out.write_str(",\n")?;
+            }
+            indent(out, level)?;
+            out.write_char(']')
+        }
+        Kv3Value::Object(object) => {
+            out.write_str("{\n")?;
+            for (key, value) in object.iter() {
+                indent(out, level + 1)?;
+                if is_bare_key(key) {
+                    out.write_str(key)?;
+                } else {
+                    write_quoted(out, key)?;
+                }
+                if is_block(value) {
+                    out.write_str(" =\n")?;
+                    indent(out, level + 1)?;
+                } else {
+                    out.write_str(" = ")?;
+                }
+                write_value(out, value, level + 1)?;
+                out.write_char('\n')?;
+            }
+            indent(out, level)?;
+            out.write_char('}')
+        }
+    }
+}
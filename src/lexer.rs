@@ -1,9 +1,14 @@
-use logos::Logos;
+use crate::EscapeMode;
+use logos::{Lexer, Logos};
 use parse_display::Display;
 use std::str;
 
 /// Parser token.
+///
+/// The escape mode is kept as the extras of the lexer, with [`EscapeMode::Raw`] a quoted string
+/// ends at the first `"`, otherwise `\"` doesn't end the string.
 #[derive(PartialEq, Debug, Logos, Display, Clone)]
+#[logos(extras = EscapeMode)]
 #[logos(skip r"[ \t\f\r\n]+")] // whitespace
 #[logos(skip r"//[^\n]*")] // comments
 pub enum Token {
@@ -16,11 +21,11 @@ pub enum Token {
     #[display("end of group")]
     GroupEnd,
     /// An enclosed or bare item.
-    #[regex("[^# \t\n{}\"][^ \t\n\r{}]*", priority = 0)]
+    #[regex("[^# \t\n{}\"\\[][^ \t\n\r{}]*", priority = 0)]
     #[display("item")]
     Item,
     /// An enclosed or bare item.
-    #[regex("\"([^\"\\\\]|\\\\.)*\"")]
+    #[token("\"", quoted)]
     #[display("quoted item")]
     QuotedItem,
     /// An enclosed or bare statement.
@@ -28,9 +33,73 @@ pub enum Token {
     #[display("statement")]
     Statement,
     /// An enclosed or bare statement.
-    #[regex("\"#([^\"\\\\]|\\\\.)*\"")]
     #[display("quoted statement")]
     QuotedStatement,
+    /// A condition like `[$WIN32]` or `[!$X360]`.
+    ///
+    /// A `[` that doesn't start a condition is the start of an item.
+    #[token("[", |lex| bracketed(lex, Token::Condition, Token::Item))]
+    #[display("condition")]
+    Condition,
+}
+
+/// Lex the rest of a quoted string, a string starting with `#` is a statement.
+fn quoted(lex: &mut Lexer<Token>) -> Result<Token, ()> {
+    let rest = lex.remainder().as_bytes();
+    let raw = lex.extras == EscapeMode::Raw;
+    let mut i = 0;
+    while let Some(&byte) = rest.get(i) {
+        match byte {
+            b'"' => {
+                lex.bump(i + 1);
+                return Ok(match rest.first() {
+                    Some(b'#') => Token::QuotedStatement,
+                    _ => Token::QuotedItem,
+                });
+            }
+            // a backslash can't escape a newline
+            b'\\' if !raw && rest.get(i + 1) == Some(&b'\n') => break,
+            b'\\' if !raw => i += 2,
+            _ => i += 1,
+        }
+    }
+    // unterminated, the reader reports this for the line the string starts on
+    lex.bump(rest.len());
+    Err(())
+}
+
+/// Lex a token starting with `[`, which is a condition like `[$WIN32]` unless a longer item matches.
+///
+/// This is done by hand because a long condition that could also be an item can overflow the stack
+/// in the generated lexer of debug builds.
+pub(crate) fn bracketed<'s, T: Logos<'s, Source = str>>(
+    lex: &mut Lexer<'s, T>,
+    condition: T,
+    item: T,
+) -> T {
+    let rest = lex.remainder().as_bytes();
+    let item_len = rest
+        .iter()
+        .position(|byte| matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'{' | b'}'))
+        .unwrap_or(rest.len());
+    let condition_len = rest
+        .strip_prefix(b"!")
+        .unwrap_or(rest)
+        .starts_with(b"$")
+        .then(|| rest.iter().position(|&byte| byte == b']' || byte == b'\n'))
+        .flatten()
+        .filter(|&end| rest[end] == b']')
+        .map(|end| end + 1);
+    match condition_len {
+        Some(len) if len >= item_len => {
+            lex.bump(len);
+            condition
+        }
+        _ => {
+            lex.bump(item_len);
+            item
+        }
+    }
 }
 
 #[cfg(test)]
@@ -38,12 +107,12 @@ mod tests {
     use super::Token;
     use logos::Logos;
 
-    fn get_token(input: &str) -> Option<Result<Token, <Token as Logos>::Error>> {
+    fn get_token(input: &str) -> Option<Result<Token, <Token as Logos<'_>>::Error>> {
         let mut lex = Token::lexer(input);
         lex.next()
     }
 
-    fn get_tokens(input: &str) -> Result<Vec<(Token, &str)>, <Token as Logos>::Error> {
+    fn get_tokens(input: &str) -> Result<Vec<(Token, &str)>, <Token as Logos<'_>>::Error> {
         Token::lexer(input)
             .spanned()
             .map(|(res, span)| res.map(|token| (token, &input[span])))
@@ -80,6 +149,32 @@ mod tests {
         assert_eq!(get_token("\"te\\\"st\""), Some(Ok(Token::QuotedItem)));
         assert_eq!(get_token("\"te\\st\""), Some(Ok(Token::QuotedItem)));
         assert_eq!(get_token("\"#te\\\"st\""), Some(Ok(Token::QuotedStatement)));
+
+        assert_eq!(get_token("[$WIN32]"), Some(Ok(Token::Condition)));
+        assert_eq!(get_token("[!$X360]"), Some(Ok(Token::Condition)));
+        assert_eq!(get_token("[$WIN32||$OSX]"), Some(Ok(Token::Condition)));
+        assert_eq!(get_token("[1 2 3]"), Some(Ok(Token::Item)));
+        assert_eq!(
+            get_tokens("[$A] [$B]x [!$C [$D"),
+            Ok(vec![
+                (Token::Condition, "[$A]"),
+                (Token::Item, "[$B]x"),
+                (Token::Item, "[!$C"),
+                (Token::Item, "[$D"),
+            ])
+        );
+        assert_eq!(
+            get_tokens("[$A\n]"),
+            Ok(vec![(Token::Item, "[$A"), (Token::Item, "]"),])
+        );
+        assert_eq!(
+            get_tokens("[ [] [$"),
+            Ok(vec![
+                (Token::Item, "["),
+                (Token::Item, "[]"),
+                (Token::Item, "[$"),
+            ])
+        );
     }
 
     #[test]
@@ -1,15 +1,35 @@
+pub mod binary;
+mod condition;
+pub mod cst;
+mod encoding;
 pub mod entry;
 pub mod error;
 mod event;
+mod include;
+pub mod kv3;
 mod lexer;
+pub mod localization;
 mod reader;
+mod ser;
 mod serde;
+mod stream;
 mod tokenizer;
+pub mod vmt;
+mod writer;
 
 pub use error::VdfError;
 
 pub type Result<T, E = VdfError> = std::result::Result<T, E>;
-pub use crate::serde::{from_entry, from_str};
+pub use crate::condition::{Condition, Defines};
+pub use crate::encoding::{DecodedText, Encoding};
+pub use crate::include::{FileResolver, Resolver};
+pub use crate::ser::{to_string, to_string_with_options, to_writer, to_writer_with_options};
+pub use crate::serde::{
+    from_entry, from_entry_with_options, from_str, from_str_with_options, BoolInference,
+    Deserializer, DeserializerOptions,
+};
 pub use event::{EntryEvent, Event, GroupEndEvent, GroupStartEvent, Item};
 pub use lexer::Token;
-pub use reader::Reader;
+pub use reader::{EscapeMode, Reader};
+pub use stream::StreamReader;
+pub use writer::{ArrayStyle, IndentStyle, QuoteStyle, WriterOptions};
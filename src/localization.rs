@@ -0,0 +1,365 @@
+//! Localization files like `resource/tf_english.txt` or `resource/closecaption_english.txt`.
+//!
+//! ```text
+//! "lang"
+//! {
+//!     "Language"  "German"
+//!     "Tokens"
+//!     {
+//!         "TF_Class_Scout"            "Scout"
+//!         "[english]TF_Class_Scout"   "Scout"
+//!         "TF_Quit"                   "Beenden"   [$WIN32]
+//!         "TF_Quit"                   "Verlassen" [$X360]
+//!     }
+//! }
+//! ```
+//!
+//! Translations have an `[english]` entry for every token with the english text it was translated
+//! from, which [`Localization::compare`] uses to find translations that are out of date.
+//! Like the engine, token names are compared regardless of case.
+
+use crate::entry::{Entry, LoadOptions, Table};
+use crate::error::UnknownError;
+use crate::{Condition, DecodedText, Defines, Result};
+use std::collections::HashMap;
+
+/// The prefix of entries holding the english text a token was translated from.
+const ENGLISH_PREFIX: &str = "[english]";
+
+/// A text of a token, with the condition it is defined under.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LocalizedString {
+    pub text: String,
+    pub condition: Option<Condition>,
+}
+
+/// A token with its text.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LocalizedToken {
+    pub name: String,
+    /// The definitions of the token, tokens with a different text per platform have more than one.
+    pub strings: Vec<LocalizedString>,
+    /// The english text the token was translated from, from the `[english]` entry.
+    pub english: Option<String>,
+}
+
+impl LocalizedToken {
+    /// The text without a condition, or the first text if every definition has a condition.
+    pub fn text(&self) -> &str {
+        self.strings
+            .iter()
+            .find(|string| string.condition.is_none())
+            .or(self.strings.first())
+            .map_or("", |string| string.text.as_str())
+    }
+
+    /// The first text whose condition holds with the given defines.
+    pub fn text_for(&self, defines: &Defines) -> Option<&str> {
+        self.strings
+            .iter()
+            .find(|string| match &string.condition {
+                Some(condition) => condition.evaluate(defines),
+                None => true,
+            })
+            .map(|string| string.text.as_str())
+    }
+
+    /// Add a definition, replacing the definition with the same condition.
+    fn define(&mut self, string: LocalizedString) {
+        match self
+            .strings
+            .iter_mut()
+            .find(|existing| existing.condition == string.condition)
+        {
+            Some(existing) => *existing = string,
+            None => self.strings.push(string),
+        }
+    }
+}
+
+/// The tokens of a localization file.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Localization {
+    language: String,
+    tokens: Vec<LocalizedToken>,
+    /// Index of every token by its lowercase name.
+    index: HashMap<String, usize>,
+    fallback: Option<Box<Localization>>,
+}
+
+impl Localization {
+    pub fn new<S: Into<String>>(language: S) -> Self {
+        Localization {
+            language: language.into(),
+            ..Localization::default()
+        }
+    }
+
+    pub fn load_from_str(input: &str) -> Result<Localization> {
+        Self::load_from_str_with_options(input, &LoadOptions::default())
+    }
+
+    /// Load a localization file, with [`ConditionMode::Evaluate`](crate::entry::ConditionMode::Evaluate)
+    /// only the tokens for the given platform are kept.
+    ///
+    /// Keys are always compared regardless of case, like the engine does for localization files.
+    pub fn load_from_str_with_options(input: &str, options: &LoadOptions) -> Result<Localization> {
+        let options = options.clone().with_case_insensitive(true);
+        Self::from_table(&Table::load_from_str_with_options(input, &options)?)
+    }
+
+    /// Load a localization file from bytes, these are usually UTF-16 with a byte order mark.
+    pub fn load_bytes(input: &[u8]) -> Result<Localization> {
+        Self::load_bytes_with_options(input, &LoadOptions::default())
+    }
+
+    pub fn load_bytes_with_options(input: &[u8], options: &LoadOptions) -> Result<Localization> {
+        let decoded = DecodedText::decode(input, options.encoding)?;
+        Self::load_from_str_with_options(decoded.text(), options)
+    }
+
+    /// Read the tokens from a loaded `"lang"` table.
+    pub fn from_table(table: &Table) -> Result<Localization> {
+        if !table.is_case_insensitive() {
+            return Self::from_table(&table.clone().with_case_insensitive(true));
+        }
+        let lang = get_table(table, "lang")
+            .ok_or_else(|| UnknownError::from("localization file has no \"lang\" group"))?;
+        let tokens = get_table(lang, "Tokens")
+            .ok_or_else(|| UnknownError::from("localization file has no \"Tokens\" group"))?;
+        let language = lang
+            .get("Language")
+            .and_then(Entry::as_str)
+            .unwrap_or_default();
+
+        let mut localization = Localization::new(language);
+        let mut english = Vec::new();
+        for (key, entry, condition) in tokens.iter_with_conditions() {
+            for text in values(entry) {
+                match strip_prefix_ignore_case(key, ENGLISH_PREFIX) {
+                    Some(name) => english.push((name, text)),
+                    None => localization.define(
+                        key,
+                        LocalizedString {
+                            text: text.into(),
+                            condition: condition.cloned(),
+                        },
+                    ),
+                }
+            }
+        }
+        // the `[english]` entry can come before or after the token
+        for (name, text) in english {
+            if let Some(token) = localization.token_mut(name) {
+                token.english = Some(text.into());
+            }
+        }
+        Ok(localization)
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The number of tokens, not counting the fallback.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Use the tokens of another language, usually english, for tokens that aren't translated.
+    pub fn with_fallback(self, fallback: Localization) -> Self {
+        Localization {
+            fallback: Some(Box::new(fallback)),
+            ..self
+        }
+    }
+
+    /// Get the text of a token, with or without the leading `#`, falling back to the fallback language.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        match self.token(name) {
+            Some(token) => Some(token.text()),
+            None => self.fallback.as_ref()?.get(name),
+        }
+    }
+
+    /// Get the text of a token for the platform with the given defines.
+    pub fn get_for(&self, name: &str, defines: &Defines) -> Option<&str> {
+        match self.token(name).and_then(|token| token.text_for(defines)) {
+            Some(text) => Some(text),
+            None => self.fallback.as_ref()?.get_for(name, defines),
+        }
+    }
+
+    /// Get a token of this language by name, with or without the leading `#`.
+    pub fn token(&self, name: &str) -> Option<&LocalizedToken> {
+        let index = *self.index.get(&index_key(name))?;
+        Some(&self.tokens[index])
+    }
+
+    fn token_mut(&mut self, name: &str) -> Option<&mut LocalizedToken> {
+        let index = *self.index.get(&index_key(name))?;
+        Some(&mut self.tokens[index])
+    }
+
+    /// Iterate over the tokens of this language in the order they are defined in.
+    pub fn iter(&self) -> impl Iterator<Item = &LocalizedToken> {
+        self.tokens.iter()
+    }
+
+    /// Set the text of a token, replacing the text with the same condition if the token already exists.
+    pub fn define(&mut self, name: &str, string: LocalizedString) {
+        let name = name.strip_prefix('#').unwrap_or(name);
+        match self.token_mut(name) {
+            Some(token) => token.define(string),
+            None => {
+                self.index.insert(index_key(name), self.tokens.len());
+                self.tokens.push(LocalizedToken {
+                    name: name.into(),
+                    strings: vec![string],
+                    english: None,
+                });
+            }
+        }
+    }
+
+    /// Add the tokens from another file of the same language, tokens from `other` replace existing texts.
+    pub fn merge(&mut self, other: Localization) {
+        for token in other.tokens {
+            for string in token.strings {
+                self.define(&token.name, string);
+            }
+            if token.english.is_some() {
+                if let Some(existing) = self.token_mut(&token.name) {
+                    existing.english = token.english;
+                }
+            }
+        }
+    }
+
+    /// Compare the tokens against a reference language, usually english.
+    pub fn compare(&self, reference: &Localization) -> LocalizationReport {
+        let mut report = LocalizationReport::default();
+        for token in reference.iter() {
+            match self.token(&token.name) {
+                None => report.missing.push(token.name.clone()),
+                Some(translated) => {
+                    if translated
+                        .english
+                        .as_deref()
+                        .is_some_and(|english| english != token.text())
+                    {
+                        report.outdated.push(token.name.clone());
+                    }
+                }
+            }
+        }
+        report.extra = self
+            .iter()
+            .filter(|token| reference.token(&token.name).is_none())
+            .map(|token| token.name.clone())
+            .collect();
+        report
+    }
+}
+
+/// The differences between a translation and the reference language.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LocalizationReport {
+    /// Tokens of the reference that aren't translated.
+    pub missing: Vec<String>,
+    /// Tokens that don't exist in the reference.
+    pub extra: Vec<String>,
+    /// Tokens whose `[english]` text differs from the current text in the reference.
+    pub outdated: Vec<String>,
+}
+
+impl LocalizationReport {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.outdated.is_empty()
+    }
+}
+
+fn index_key(name: &str) -> String {
+    name.strip_prefix('#').unwrap_or(name).to_ascii_lowercase()
+}
+
+fn get_table<'a>(table: &'a Table, key: &str) -> Option<&'a Table> {
+    table.get(key).and_then(Entry::as_table)
+}
+
+fn strip_prefix_ignore_case<'a>(key: &'a str, prefix: &str) -> Option<&'a str> {
+    key.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &key[prefix.len()..])
+}
+
+/// The texts of a token, a token defined more than once is loaded as an array.
+fn values(entry: &Entry) -> Vec<&str> {
+    match entry.as_slice() {
+        Some(entries) => entries.iter().filter_map(Entry::as_str).collect(),
+        None => entry.as_str().into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GERMAN: &str = r#"
+        "lang"
+        {
+            "Language" "German"
+            "Tokens"
+            {
+                "[english]TF_Scout" "Scout"
+                "TF_Scout"          "Aufklärer"
+                "TF_Quit"           "Beenden"   [!$X360]
+                "TF_Quit"           "Verlassen" [$X360]
+                "TF_Old"            "Alt"
+                "TF_Old"            "Neu"
+            }
+        }
+    "#;
+
+    #[test]
+    fn test_tokens() {
+        let localization = Localization::load_from_str(GERMAN).unwrap();
+        assert_eq!("German", localization.language());
+        assert_eq!(3, localization.len());
+        assert_eq!(Some("Aufklärer"), localization.get("#tf_scout"));
+        assert_eq!(
+            Some("Scout"),
+            localization.token("TF_Scout").unwrap().english.as_deref()
+        );
+        assert_eq!(Some("Neu"), localization.get("TF_Old"));
+
+        assert_eq!(Some("Beenden"), localization.get("TF_Quit"));
+        let defines = Defines::from_iter(["X360"]);
+        assert_eq!(Some("Verlassen"), localization.get_for("TF_Quit", &defines));
+    }
+
+    #[test]
+    fn test_missing_tokens() {
+        let error = Localization::load_from_str(r#""lang" { "Language" "German" }"#).unwrap_err();
+        assert_eq!(
+            "localization file has no \"Tokens\" group",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_key_case() {
+        let source =
+            "\"LANG\" {\n\"language\" \"German\"\n\"tokens\" { \"TF_Scout\" \"Aufklärer\" }\n}";
+        let localization = Localization::load_from_str(source).unwrap();
+        assert_eq!("German", localization.language());
+        assert_eq!(Some("Aufklärer"), localization.get("TF_Scout"));
+
+        let table = Table::load_from_str(source).unwrap();
+        assert!(Localization::from_table(&table).is_ok());
+    }
+}
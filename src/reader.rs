@@ -1,4 +1,5 @@
 use super::{Result, Token};
+use crate::condition::Condition;
 use crate::error::{NoValidTokenError, UnexpectedTokenError};
 use crate::event::{
     EntryEvent, Event, EventType, GroupEndEvent, GroupStartEvent, Item, ValueContinuationEvent,
@@ -11,25 +12,120 @@ pub struct Reader<'a> {
     pub source: &'a str,
     pub last_event: Option<EventType>,
     lexer: SpannedIter<'a, Token>,
+    peeked: Option<Option<SpannedToken<'a>>>,
+    span: Span,
+    escape: EscapeMode,
+    /// How far the lexer has read into the source.
+    lexed_end: usize,
 }
 
+type SpannedToken<'a> = (Result<Token, <Token as Logos<'a>>::Error>, Span);
+
 impl<'a> From<&'a str> for Reader<'a> {
     fn from(content: &'a str) -> Self {
         Reader {
             source: content,
             last_event: None,
             lexer: Lexer::new(content).spanned(),
+            peeked: None,
+            span: 0..0,
+            escape: EscapeMode::default(),
+            lexed_end: 0,
         }
     }
 }
 
 impl<'a> Reader<'a> {
-    fn token(&mut self) -> Option<(Result<Token, <Token as Logos>::Error>, Span)> {
-        self.lexer.next()
+    /// Set how escape sequences in quoted strings are decoded.
+    ///
+    /// With [`EscapeMode::Raw`] a quoted string ends at the first `"`, even if it follows a backslash.
+    pub fn with_escape_mode(self, escape: EscapeMode) -> Self {
+        let mut lexer = Lexer::with_extras(self.source, escape);
+        lexer.bump(self.lexed_end);
+        Reader {
+            escape,
+            lexer: lexer.spanned(),
+            ..self
+        }
+    }
+
+    fn token(&mut self) -> Option<SpannedToken<'a>> {
+        let token = self.peeked.take().unwrap_or_else(|| self.next_token());
+        if let Some((_, span)) = &token {
+            self.span = span.clone();
+        }
+        token
+    }
+
+    fn next_token(&mut self) -> Option<SpannedToken<'a>> {
+        let token = self.lexer.next();
+        self.lexed_end = match &token {
+            Some((_, span)) => span.end,
+            None => self.source.len(),
+        };
+        match token {
+            // an unterminated quote runs until the end of the input, only report the line
+            // it starts on and continue reading from the next line
+            Some((Err(error), span)) if self.source[span.clone()].starts_with('"') => {
+                let end = match self.source[span.clone()].find('\n') {
+                    Some(newline) => span.start + newline,
+                    None => span.end,
+                };
+                let mut lexer = Lexer::with_extras(self.source, self.escape);
+                lexer.bump(end);
+                self.lexer = lexer.spanned();
+                Some((Err(error), span.start..end))
+            }
+            token => token,
+        }
+    }
+
+    /// Put back a `}` that was found instead of a value, so reading can continue by closing the group.
+    fn push_back_group_end(&mut self, token: &Token, span: &Span) {
+        if *token == Token::GroupEnd {
+            self.peeked = Some(Some((Ok(Token::GroupEnd), span.clone())));
+        }
+    }
+
+    fn peek(&mut self) -> Option<&SpannedToken<'a>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.next_token());
+        }
+        self.peeked.as_ref().and_then(Option::as_ref)
+    }
+
+    /// Consume the next token if it's a condition.
+    fn condition(&mut self) -> Option<(Condition, Span)> {
+        match self.peek() {
+            Some((Ok(Token::Condition), _)) => {
+                self.token();
+                Some((Condition::new(self.slice()), self.span()))
+            }
+            _ => None,
+        }
+    }
+
+    fn slice(&self) -> &'a str {
+        &self.source[self.span.clone()]
     }
 
     pub fn span(&self) -> Span {
-        self.lexer.span()
+        self.span.clone()
+    }
+
+    /// How far the lexer has looked into the source, including tokens that were only peeked at.
+    ///
+    /// When reading from a part of a larger input, events are only complete if this is before the end.
+    pub(crate) fn lexed_end(&self) -> usize {
+        self.lexed_end
+    }
+
+    /// The end of the last token that was used for an event, tokens that were put back aren't counted.
+    pub(crate) fn consumed(&self) -> usize {
+        match &self.peeked {
+            Some(Some((_, span))) if span.start < self.span.end => span.start,
+            _ => self.span.end,
+        }
     }
 
     /// Get the next event, this does copies.
@@ -70,22 +166,22 @@ impl<'a> Reader<'a> {
             }
 
             Some((Ok(Token::Item), span)) => Item::Item {
-                content: string(self.lexer.slice()),
+                content: string(self.slice()),
                 span,
             },
 
             Some((Ok(Token::QuotedItem), span)) => Item::Item {
-                content: quoted_string(self.lexer.slice()),
+                content: quoted_string(self.slice(), self.escape),
                 span,
             },
 
             Some((Ok(Token::Statement), span)) => Item::Statement {
-                content: string(self.lexer.slice()),
+                content: string(self.slice()),
                 span,
             },
 
             Some((Ok(Token::QuotedStatement), span)) => Item::Statement {
-                content: quoted_string(self.lexer.slice()),
+                content: quoted_string(self.slice(), self.escape),
                 span,
             },
 
@@ -123,12 +219,48 @@ impl<'a> Reader<'a> {
             Token::QuotedStatement,
         ];
 
+        // groups have their condition between the name and the opening brace
+        if let Some((condition, _)) = self.condition() {
+            return match self.token() {
+                Some((Ok(Token::GroupStart), span)) => {
+                    Some(Ok(Event::GroupStart(GroupStartEvent {
+                        name: key.into_content(),
+                        condition: Some(condition),
+                        span,
+                    })))
+                }
+                Some((Err(_), span)) => Some(Err(NoValidTokenError::new(
+                    &[Token::GroupStart],
+                    span.into(),
+                    self.source.into(),
+                )
+                .into())),
+                None => Some(Err(UnexpectedTokenError::new(
+                    &[Token::GroupStart],
+                    None,
+                    self.span().into(),
+                    self.source.into(),
+                )
+                .into())),
+                Some((Ok(token), span)) => {
+                    self.push_back_group_end(&token, &span);
+                    Some(Err(UnexpectedTokenError::new(
+                        &[Token::GroupStart],
+                        Some(token),
+                        span.into(),
+                        self.source.into(),
+                    )
+                    .into()))
+                }
+            };
+        }
+
         let value = match self.token() {
             None => {
                 return Some(Err(UnexpectedTokenError::new(
                     VALID_VALUE,
                     None,
-                    self.lexer.span().into(),
+                    self.span().into(),
                     self.source.into(),
                 )
                 .into()));
@@ -146,43 +278,55 @@ impl<'a> Reader<'a> {
             Some((Ok(Token::GroupStart), span)) => {
                 return Some(Ok(Event::GroupStart(GroupStartEvent {
                     name: key.into_content(),
+                    condition: None,
                     span,
                 })))
             }
 
             Some((Ok(Token::QuotedItem), span)) => Item::Item {
-                content: quoted_string(self.lexer.slice()),
+                content: quoted_string(self.slice(), self.escape),
                 span,
             },
 
             Some((Ok(Token::Item), span)) => Item::Item {
-                content: string(self.lexer.slice()),
+                content: string(self.slice()),
                 span,
             },
 
             Some((Ok(Token::QuotedStatement), span)) => Item::Statement {
-                content: quoted_string(self.lexer.slice()),
+                content: quoted_string(self.slice(), self.escape),
                 span,
             },
 
             Some((Ok(Token::Statement), span)) => Item::Statement {
-                content: string(self.lexer.slice()),
+                content: string(self.slice()),
                 span,
             },
 
             Some((Ok(token), span)) => {
+                self.push_back_group_end(&token, &span);
                 return Some(Err(UnexpectedTokenError::new(
                     VALID_VALUE,
                     Some(token),
                     span.into(),
                     self.source.into(),
                 )
-                .into()))
+                .into()));
             }
         };
 
-        let span = key.span().start..value.span().end;
-        Some(Ok(Event::Entry(EntryEvent { key, value, span })))
+        let condition = self.condition();
+        let end = match &condition {
+            Some((_, span)) => span.end,
+            None => value.span().end,
+        };
+        let span = key.span().start..end;
+        Some(Ok(Event::Entry(EntryEvent {
+            key,
+            value,
+            condition: condition.map(|(condition, _)| condition),
+            span,
+        })))
     }
 }
 
@@ -194,32 +338,52 @@ impl<'a> Iterator for Reader<'a> {
     }
 }
 
-pub(crate) fn quoted_string(source: &str) -> Cow<str> {
+/// How escape sequences in quoted strings are handled.
+///
+/// This mirrors the `UsesEscapeSequences` flag of Valve's `KeyValues`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum EscapeMode {
+    /// Decode `\n`, `\t`, `\r`, `\\` and `\"`.
+    Full,
+    /// Only decode `\\` and `\"`, any other backslash is kept as-is.
+    ///
+    /// This allows windows paths like `"C:\test\no newline"` to be used without escaping them.
+    #[default]
+    QuotesOnly,
+    /// Keep the string exactly as written.
+    Raw,
+}
+
+pub(crate) fn quoted_string(source: &str, mode: EscapeMode) -> Cow<'_, str> {
     let source = &source[1..source.len() - 1];
 
-    if source.contains(r#"\""#) || source.contains(r#"\\"#) {
-        let mut buffer = source.bytes();
-        let mut string = Vec::with_capacity(buffer.len());
-
-        while let Some(byte) = buffer.next() {
-            if byte == b'\\' {
-                match buffer.next() {
-                    Some(b'\\') => string.push(b'\\'),
-                    Some(b'"') => string.push(b'"'),
-                    Some(byte) => string.extend_from_slice(&[b'\\', byte]),
-                    None => break,
-                }
-            } else {
-                string.push(byte);
+    if mode == EscapeMode::Raw || !source.contains('\\') {
+        return source.into();
+    }
+
+    let mut string = String::with_capacity(source.len());
+    let mut chars = source.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            string.push(char);
+            continue;
+        }
+        match (chars.next(), mode) {
+            (Some('\\'), _) => string.push('\\'),
+            (Some('"'), _) => string.push('"'),
+            (Some('n'), EscapeMode::Full) => string.push('\n'),
+            (Some('t'), EscapeMode::Full) => string.push('\t'),
+            (Some('r'), EscapeMode::Full) => string.push('\r'),
+            (Some(char), _) => {
+                string.push('\\');
+                string.push(char);
             }
+            (None, _) => break,
         }
-
-        String::from_utf8(string).unwrap().into()
-    } else {
-        source.into()
     }
+    string.into()
 }
 
-fn string(source: &str) -> Cow<str> {
+fn string(source: &str) -> Cow<'_, str> {
     source.into()
 }
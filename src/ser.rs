@@ -0,0 +1,633 @@
+use crate::binary::{BinaryTable, BinaryType, BinaryValue};
+use crate::error::UnknownError;
+use crate::writer::{can_be_raw, Writer, WriterOptions};
+use crate::{EscapeMode, VdfError};
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+};
+use std::fmt::Write;
+
+type Result<T, E = VdfError> = std::result::Result<T, E>;
+
+/// Serialize the value as vdf text.
+///
+/// Structs and maps become groups, `Vec<T>` becomes repeated keys, tuples and fixed size arrays
+/// become a `"[a b c]"` string and `None` values are skipped.
+///
+/// A root struct or map is written as top level key-values and a root enum as a group named after the variant.
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    to_string_with_options(value, &WriterOptions::default())
+}
+
+/// Serialize the value as vdf text with the given formatting options.
+///
+/// See [`to_string`] for details on how types are mapped.
+pub fn to_string_with_options<T>(value: &T, options: &WriterOptions) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    let node = value.serialize(NodeSerializer)?;
+    if options.escape == EscapeMode::Raw {
+        node.check_raw()?;
+    }
+    let mut out = String::new();
+    write_root(&mut Writer::new(&mut out, options), &node)?;
+    Ok(out)
+}
+
+/// Serialize the value as vdf text into the writer.
+///
+/// See [`to_string`] for details on how types are mapped.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: std::io::Write,
+    T: Serialize + ?Sized,
+{
+    to_writer_with_options(writer, value, &WriterOptions::default())
+}
+
+/// Serialize the value as vdf text into the writer with the given formatting options.
+///
+/// See [`to_string`] for details on how types are mapped.
+pub fn to_writer_with_options<W, T>(mut writer: W, value: &T, options: &WriterOptions) -> Result<()>
+where
+    W: std::io::Write,
+    T: Serialize + ?Sized,
+{
+    let out = to_string_with_options(value, options)?;
+    writer.write_all(out.as_bytes())?;
+    Ok(())
+}
+
+/// Intermediate representation of a serialized value.
+#[derive(Debug)]
+enum Node {
+    /// A value with the wire type used when writing binary data
+    Value(String, BinaryType),
+    Group(Vec<(String, Node)>),
+    /// A sequence, written as the same key repeated for every item
+    Repeated(Vec<Node>),
+    /// A `None` value, the key is omitted
+    Skip,
+}
+
+impl Node {
+    /// Check that all keys and values can be written without escaping.
+    fn check_raw(&self) -> Result<()> {
+        let check = |value: &str| match can_be_raw(value) {
+            true => Ok(()),
+            false => Err(error(&format!(
+                "{value:?} can't be written without escaping"
+            ))),
+        };
+        match self {
+            Node::Value(value, _) => check(value),
+            Node::Group(entries) => entries.iter().try_for_each(|(key, node)| {
+                check(key)?;
+                node.check_raw()
+            }),
+            Node::Repeated(items) => items.iter().try_for_each(Node::check_raw),
+            Node::Skip => Ok(()),
+        }
+    }
+}
+
+fn error(msg: &str) -> VdfError {
+    UnknownError::from(msg).into()
+}
+
+fn write_root<W: Write>(writer: &mut Writer<W>, node: &Node) -> Result<()> {
+    match node {
+        Node::Value(value, _) => writer.write_root_value(value)?,
+        Node::Group(entries) => write_group(writer, entries)?,
+        Node::Repeated(_) => return Err(error("a sequence can't be serialized at the root")),
+        Node::Skip => {}
+    }
+    Ok(())
+}
+
+/// Serialize the value into a binary table, see [`to_string`] for details on how types are mapped.
+pub(crate) fn to_binary_table<T>(value: &T) -> Result<BinaryTable>
+where
+    T: Serialize + ?Sized,
+{
+    match value.serialize(NodeSerializer)? {
+        Node::Group(entries) => binary_group(entries),
+        Node::Skip => Ok(BinaryTable::default()),
+        _ => Err(error("only groups can be serialized as binary data")),
+    }
+}
+
+fn binary_group(entries: Vec<(String, Node)>) -> Result<BinaryTable> {
+    let mut table = BinaryTable::default();
+    for (key, node) in entries {
+        binary_entry(&mut table, key, node)?;
+    }
+    Ok(table)
+}
+
+fn binary_entry(table: &mut BinaryTable, key: String, node: Node) -> Result<()> {
+    match node {
+        Node::Value(value, ty) => table.push(key, BinaryValue::parse(&value, ty)?),
+        Node::Group(entries) => table.push(key, binary_group(entries)?),
+        Node::Repeated(items) => {
+            for item in items {
+                if let Node::Repeated(_) = item {
+                    return Err(error("nested sequences can't be serialized"));
+                }
+                binary_entry(table, key.clone(), item)?;
+            }
+        }
+        Node::Skip => {}
+    }
+    Ok(())
+}
+
+fn write_group<W: Write>(writer: &mut Writer<W>, entries: &[(String, Node)]) -> Result<()> {
+    let key_width = writer.key_width(entries.iter().map(|(key, _)| key.as_str()));
+    for (key, node) in entries {
+        write_entry(writer, key, node, key_width)?;
+    }
+    Ok(())
+}
+
+fn write_entry<W: Write>(
+    writer: &mut Writer<W>,
+    key: &str,
+    node: &Node,
+    key_width: usize,
+) -> Result<()> {
+    match node {
+        Node::Value(value, _) => writer.write_key_value(key, value, None, key_width)?,
+        Node::Group(entries) => {
+            writer.start_group(key, None)?;
+            write_group(writer, entries)?;
+            writer.end_group()?;
+        }
+        Node::Repeated(items) => {
+            for item in items {
+                if let Node::Repeated(_) = item {
+                    return Err(error("nested sequences can't be serialized"));
+                }
+                write_entry(writer, key, item, key_width)?;
+            }
+        }
+        Node::Skip => {}
+    }
+    Ok(())
+}
+
+struct NodeSerializer;
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = TupleSerializer;
+    type SerializeTupleStruct = TupleSerializer;
+    type SerializeTupleVariant = TupleSerializer;
+    type SerializeMap = GroupSerializer;
+    type SerializeStruct = GroupSerializer;
+    type SerializeStructVariant = GroupSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Node> {
+        Ok(Node::Value(
+            if v { "1" } else { "0" }.into(),
+            BinaryType::Int32,
+        ))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Int32))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Int32))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Int32))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Int64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Int32))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Int32))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node> {
+        let ty = if i32::try_from(v).is_ok() {
+            BinaryType::Int32
+        } else {
+            BinaryType::Uint64
+        };
+        Ok(Node::Value(v.to_string(), ty))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Uint64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::Float32))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node> {
+        // binary data only has 32-bit floats, values that don't read back the same are written as a string
+        let value = v.to_string();
+        let ty = if (v as f32).to_string() == value {
+            BinaryType::Float32
+        } else {
+            BinaryType::String
+        };
+        Ok(Node::Value(value, ty))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node> {
+        Ok(Node::Value(v.to_string(), BinaryType::String))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node> {
+        Ok(Node::Value(v.into(), BinaryType::String))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Node> {
+        Err(error("bytes can't be serialized"))
+    }
+
+    fn serialize_none(self) -> Result<Node> {
+        Ok(Node::Skip)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Node>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node> {
+        Ok(Node::Value(String::new(), BinaryType::String))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node> {
+        Ok(Node::Value(variant.into(), BinaryType::String))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Node>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(Node::Group(vec![(variant.into(), value.serialize(self)?)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<TupleSerializer> {
+        Ok(TupleSerializer {
+            variant: None,
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<TupleSerializer> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<TupleSerializer> {
+        Ok(TupleSerializer {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<GroupSerializer> {
+        Ok(GroupSerializer {
+            variant: None,
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<GroupSerializer> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<GroupSerializer> {
+        Ok(GroupSerializer {
+            variant: Some(variant),
+            entries: Vec::with_capacity(len),
+            key: None,
+        })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Node>,
+}
+
+impl SerializeSeq for SeqSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(value.serialize(NodeSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(Node::Repeated(self.items))
+    }
+}
+
+/// Tuples are serialized as a single `[a b c]` string
+struct TupleSerializer {
+    variant: Option<&'static str>,
+    items: Vec<String>,
+}
+
+impl TupleSerializer {
+    fn push<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        match value.serialize(NodeSerializer)? {
+            Node::Value(value, _) if value.is_empty() || value.contains(char::is_whitespace) => {
+                Err(error("tuple items can't be empty or contain whitespace"))
+            }
+            Node::Value(value, _) => {
+                self.items.push(value);
+                Ok(())
+            }
+            _ => Err(error("tuple items have to be plain values")),
+        }
+    }
+
+    fn finish(self) -> Node {
+        let value = Node::Value(format!("[{}]", self.items.join(" ")), BinaryType::String);
+        match self.variant {
+            Some(variant) => Node::Group(vec![(variant.into(), value)]),
+            None => value,
+        }
+    }
+}
+
+impl SerializeTuple for TupleSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeTupleStruct for TupleSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeTupleVariant for TupleSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(self.finish())
+    }
+}
+
+struct GroupSerializer {
+    variant: Option<&'static str>,
+    entries: Vec<(String, Node)>,
+    key: Option<String>,
+}
+
+impl GroupSerializer {
+    fn finish(self) -> Node {
+        let group = Node::Group(self.entries);
+        match self.variant {
+            Some(variant) => Node::Group(vec![(variant.into(), group)]),
+            None => group,
+        }
+    }
+}
+
+impl SerializeMap for GroupSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        match key.serialize(NodeSerializer)? {
+            Node::Value(key, _) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(error("map keys have to be plain values")),
+        }
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| error("serialize_value called before serialize_key"))?;
+        self.entries.push((key, value.serialize(NodeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeStruct for GroupSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.entries
+            .push((key.into(), value.serialize(NodeSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(self.finish())
+    }
+}
+
+impl SerializeStructVariant for GroupSerializer {
+    type Ok = Node;
+    type Error = VdfError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_string;
+    use crate::from_str;
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_struct() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Inner {
+            a: f32,
+            b: bool,
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Test {
+            int: u32,
+            nested: Inner,
+            seq: Vec<u8>,
+            tuple: (u8, String),
+            optional: Option<String>,
+        }
+
+        let value = Test {
+            int: 1,
+            nested: Inner { a: 1.5, b: true },
+            seq: vec![1, 2],
+            tuple: (3, "foo".into()),
+            optional: None,
+        };
+        let out = to_string(&value).unwrap();
+        assert_eq!(
+            "\"int\"\t\t\"1\"\n\
+            \"nested\"\n{\n\t\"a\"\t\t\"1.5\"\n\t\"b\"\t\t\"1\"\n}\n\
+            \"seq\"\t\t\"1\"\n\"seq\"\t\t\"2\"\n\
+            \"tuple\"\t\t\"[3 foo]\"\n",
+            out
+        );
+        assert_eq!(value, from_str::<Test>(&out).unwrap());
+    }
+
+    #[test]
+    fn test_enum_root() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum E {
+            Unit,
+            Newtype(u32),
+            Struct { a: u32 },
+        }
+
+        for value in [E::Unit, E::Newtype(1), E::Struct { a: 2 }] {
+            let out = to_string(&value).unwrap();
+            assert_eq!(value, from_str::<E>(&out).unwrap());
+        }
+        assert_eq!(
+            "\"Struct\"\n{\n\t\"a\"\t\t\"2\"\n}\n",
+            to_string(&E::Struct { a: 2 }).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_escaped() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Test {
+            path: String,
+            quoted: String,
+        }
+
+        let value = Test {
+            path: r#"C:\test\"#.into(),
+            quoted: r#"say "hi""#.into(),
+        };
+        let out = to_string(&value).unwrap();
+        assert_eq!(value, from_str::<Test>(&out).unwrap());
+    }
+}
@@ -1,19 +1,202 @@
-use crate::entry::{string_is_array, Entry, ParseItem};
+use crate::entry::{string_is_array, Entry, EntryDeserializer, ParseItem};
 use crate::error::{ExpectToken, NoValidTokenError, ResultExt, SerdeParseError};
 use crate::tokenizer::{SpannedToken, Tokenizer};
-use crate::{Token, VdfError};
+use crate::{EscapeMode, Token, VdfError};
 use logos::Span;
 use serde::de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
 use serde::Deserialize;
 use std::borrow::Cow;
+use std::collections::VecDeque;
 
 type Result<T, E = VdfError> = std::result::Result<T, E>;
 
+/// Which values are read as booleans when the type isn't known up front, like in untagged enums.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolInference {
+    /// Never infer booleans.
+    #[default]
+    None,
+    /// `0` and `1` are booleans, this takes precedence over reading them as numbers.
+    ZeroOne,
+    /// `true` and `false`, case-insensitive.
+    TrueFalse,
+    /// `yes` and `no`, case-insensitive.
+    YesNo,
+}
+
+/// Options for how keys and values are interpreted when deserializing.
+///
+/// vdf doesn't distinguish between strings, numbers and booleans, so deserializing into
+/// types that accept anything, like untagged enums or [`Entry`], has to guess the type from the text.
+/// Types that ask for a specific type, like a `String` or `u32` field, aren't affected by those options.
+#[derive(Clone, Copy, Debug)]
+pub struct DeserializerOptions {
+    pub bools: BoolInference,
+    /// Read numbers with leading zeros, like `007`, as strings.
+    pub keep_leading_zeros: bool,
+    /// Read hexadecimal numbers like `0x1F` as integers.
+    pub hex: bool,
+    /// Read numbers with an exponent, like `1e5`, as floats.
+    pub exponents: bool,
+    /// Read values like `"[1 2 3]"` as arrays.
+    pub arrays: bool,
+    /// Match struct fields and enum variants regardless of case, like the engine does.
+    pub case_insensitive: bool,
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        DeserializerOptions {
+            bools: BoolInference::default(),
+            keep_leading_zeros: false,
+            hex: false,
+            exponents: true,
+            arrays: true,
+            case_insensitive: false,
+        }
+    }
+}
+
+impl DeserializerOptions {
+    pub fn with_bools(self, bools: BoolInference) -> Self {
+        DeserializerOptions { bools, ..self }
+    }
+
+    pub fn with_keep_leading_zeros(self, keep_leading_zeros: bool) -> Self {
+        DeserializerOptions {
+            keep_leading_zeros,
+            ..self
+        }
+    }
+
+    pub fn with_hex(self, hex: bool) -> Self {
+        DeserializerOptions { hex, ..self }
+    }
+
+    pub fn with_exponents(self, exponents: bool) -> Self {
+        DeserializerOptions { exponents, ..self }
+    }
+
+    pub fn with_arrays(self, arrays: bool) -> Self {
+        DeserializerOptions { arrays, ..self }
+    }
+
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        DeserializerOptions {
+            case_insensitive,
+            ..self
+        }
+    }
+
+    /// The field or variant name serde expects for a key, if it only differs by case.
+    pub(crate) fn identifier(&self, key: &str, expected: &[&'static str]) -> Option<&'static str> {
+        if !self.case_insensitive || expected.contains(&key) {
+            return None;
+        }
+        expected
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(key))
+            .copied()
+    }
+
+    /// Check if two keys are the same.
+    pub(crate) fn same_key(&self, a: &str, b: &str) -> bool {
+        if self.case_insensitive {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    }
+
+    /// Guess the type of a value.
+    pub(crate) fn infer(&self, value: &str) -> Inferred {
+        if let Some(bool) = self.infer_bool(value) {
+            return Inferred::Bool(bool);
+        }
+
+        let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+        let leading_zero = digits.len() > 1
+            && digits.starts_with('0')
+            && digits[1..].starts_with(|c: char| c.is_ascii_digit());
+        if self.keep_leading_zeros && leading_zero {
+            return Inferred::String;
+        }
+
+        if let Ok(int) = i64::from_str(value) {
+            return Inferred::Int(int);
+        }
+        if self.hex {
+            if let Some(hex) = value
+                .strip_prefix("0x")
+                .or_else(|| value.strip_prefix("0X"))
+            {
+                if let Ok(int) = i64::from_str_radix(hex, 16) {
+                    return Inferred::Int(int);
+                }
+            }
+        }
+        if self.exponents || !value.contains(['e', 'E']) {
+            if let Ok(float) = f64::from_str(value) {
+                return Inferred::Float(float);
+            }
+        }
+        if self.arrays && string_is_array(value) {
+            return Inferred::Array;
+        }
+        Inferred::String
+    }
+
+    fn infer_bool(&self, value: &str) -> Option<bool> {
+        self.bools.infer(value)
+    }
+}
+
+impl BoolInference {
+    /// Read a value as a boolean if it's one of the accepted values.
+    pub(crate) fn infer(self, value: &str) -> Option<bool> {
+        let (yes, no) = match self {
+            BoolInference::None => return None,
+            BoolInference::ZeroOne => {
+                return match value {
+                    "1" => Some(true),
+                    "0" => Some(false),
+                    _ => None,
+                }
+            }
+            BoolInference::TrueFalse => ("true", "false"),
+            BoolInference::YesNo => ("yes", "no"),
+        };
+        if value.eq_ignore_ascii_case(yes) {
+            Some(true)
+        } else if value.eq_ignore_ascii_case(no) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// The type a value was inferred as.
+pub(crate) enum Inferred {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Array,
+    String,
+}
+
+/// A deserializer for vdf text.
 pub struct Deserializer<'de> {
     tokenizer: Tokenizer<'de>,
     peeked: Option<Result<SpannedToken, Span>>,
+    /// Tokens that were read ahead and are handed out before reading from the tokenizer.
+    replay: VecDeque<Result<SpannedToken, Span>>,
     last_key: Cow<'de, str>,
     last_span: Span,
+    escape: EscapeMode,
+    options: DeserializerOptions,
+    /// The field or variant names serde expects for the next identifier.
+    identifiers: &'static [&'static str],
 }
 
 const STRING_ITEMS: &[Token] = &[
@@ -23,39 +206,74 @@ const STRING_ITEMS: &[Token] = &[
     Token::QuotedStatement,
 ];
 
+/// Items of a sequence made from repeated keys, either values or groups.
+const SEQ_ITEMS: &[Token] = &[
+    Token::Item,
+    Token::QuotedItem,
+    Token::Statement,
+    Token::QuotedStatement,
+    Token::GroupStart,
+];
+
 impl<'de> Deserializer<'de> {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &'de str) -> Self {
         Deserializer {
             tokenizer: Tokenizer::from_str(input),
             peeked: None,
+            replay: VecDeque::new(),
             last_key: "".into(),
             last_span: 0..0,
+            escape: EscapeMode::default(),
+            options: DeserializerOptions::default(),
+            identifiers: &[],
+        }
+    }
+
+    /// Set how escape sequences in quoted strings are decoded, this has to be set before deserializing.
+    ///
+    /// With [`EscapeMode::Raw`] a quoted string ends at the first `"`, even if it follows a backslash.
+    pub fn with_escape_mode(self, escape: EscapeMode) -> Self {
+        Deserializer {
+            tokenizer: Tokenizer::with_escape_mode(self.source(), escape),
+            escape,
+            ..self
         }
     }
 
-    pub fn source(&self) -> &'de str {
+    /// Set how the types of values are inferred when the type isn't known up front.
+    pub fn with_options(self, options: DeserializerOptions) -> Self {
+        Deserializer { options, ..self }
+    }
+
+    pub(crate) fn source(&self) -> &'de str {
         self.tokenizer.source()
     }
 
-    pub fn next(&mut self) -> Option<Result<SpannedToken, Span>> {
-        self.peeked
-            .take()
-            .or_else(|| self.tokenizer.next())
-            .map(|r| {
-                r.map(|t| {
-                    self.last_span = t.span.clone();
-                    t
-                })
-                .map_err(|span| {
-                    self.last_span = span.clone();
-                    span
-                })
+    fn string(&self, token: &SpannedToken) -> Cow<'de, str> {
+        token.string(self.source(), self.escape)
+    }
+
+    fn next_token(&mut self) -> Option<Result<SpannedToken, Span>> {
+        self.replay.pop_front().or_else(|| self.tokenizer.next())
+    }
+
+    pub(crate) fn next(&mut self) -> Option<Result<SpannedToken, Span>> {
+        self.peeked.take().or_else(|| self.next_token()).map(|r| {
+            r.map(|t| {
+                self.last_span = t.span.clone();
+                t
+            })
+            .map_err(|span| {
+                self.last_span = span.clone();
+                span
             })
+        })
     }
 
-    pub fn peek(&mut self) -> Option<Result<SpannedToken, Span>> {
+    pub(crate) fn peek(&mut self) -> Option<Result<SpannedToken, Span>> {
         if self.peeked.is_none() {
-            self.peeked = self.tokenizer.next();
+            self.peeked = self.next_token();
         }
         self.peeked.clone()
     }
@@ -64,13 +282,13 @@ impl<'de> Deserializer<'de> {
         self.peek().and_then(|r| r.ok()).map(|token| token.span)
     }
 
-    pub fn push_peeked(&mut self, token: SpannedToken) {
+    pub(crate) fn push_peeked(&mut self, token: SpannedToken) {
         self.peeked = Some(Ok(token))
     }
 
     fn read_str(&mut self) -> Result<(Cow<'de, str>, Span)> {
         let token = self.next().expect_token(STRING_ITEMS, self.source())?;
-        Ok((token.string(self.source()), token.span))
+        Ok((self.string(&token), token.span))
     }
 
     fn parse<T: ParseItem>(&mut self) -> Result<T> {
@@ -82,21 +300,195 @@ impl<'de> Deserializer<'de> {
     fn set_last_key(&mut self, key: Cow<'de, str>) {
         self.last_key = key;
     }
+
+    /// Deserialize a map or struct, `fields` are the struct fields or empty for maps.
+    fn deserialize_group<V>(
+        &mut self,
+        visitor: V,
+        fields: &'static [&'static str],
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // as a special case we allow a map without a `{` at the start of the file to create a top level struct
+        let toplevel = match self
+            .peek()
+            .expect_token(&[Token::GroupStart], self.source())
+        {
+            Ok(_) => {
+                let _ = self.next();
+                false
+            }
+            Err(VdfError::UnexpectedToken(e)) => {
+                if self.tokenizer.count > 1 {
+                    return Err(e.into());
+                }
+                true
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let value = visitor.visit_map(TableWalker::new(self, toplevel).with_fields(fields))?;
+        Ok(value)
+    }
+
+    /// Read the remaining tokens of the current group, up to and including the closing brace.
+    fn group_tokens(&mut self) -> Vec<Result<SpannedToken, Span>> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        while let Some(token) = self.peeked.take().or_else(|| self.next_token()) {
+            let done = match &token {
+                Ok(SpannedToken {
+                    token: Token::GroupStart,
+                    ..
+                }) => {
+                    depth += 1;
+                    false
+                }
+                Ok(SpannedToken {
+                    token: Token::GroupEnd,
+                    ..
+                }) if depth == 0 => true,
+                Ok(SpannedToken {
+                    token: Token::GroupEnd,
+                    ..
+                }) => {
+                    depth -= 1;
+                    false
+                }
+                Ok(_) => false,
+                Err(_) => true,
+            };
+            tokens.push(token);
+            if done {
+                break;
+            }
+        }
+        tokens
+    }
+
+    /// Move the later entries of the current group that have the same key as the value being read
+    /// directly after it, so repeated keys can be read as a sequence even if other keys are between them.
+    ///
+    /// The other entries keep their order.
+    fn group_repeated_key(&mut self, key: &str) {
+        let tokens = self.group_tokens();
+        let source = self.source();
+
+        // split the tokens into entries, a key followed by its value or group
+        // and any values on the same line after it
+        let first_end = value_end(&tokens, 0, source);
+        let mut repeated = Vec::new();
+        let mut others = Vec::new();
+        let mut i = first_end;
+        while let Some(Ok(token)) = tokens.get(i) {
+            if !STRING_ITEMS.contains(&token.token) {
+                break;
+            }
+            let end = value_end(&tokens, i + 1, source);
+            if self
+                .options
+                .same_key(&token.string(source, self.escape), key)
+            {
+                repeated.push(i..end);
+            } else {
+                others.push(i..end);
+            }
+            i = end;
+        }
+
+        let interleaved = others
+            .first()
+            .zip(repeated.last())
+            .is_some_and(|(other, repeated)| other.start < repeated.start);
+        let tokens = if interleaved {
+            let mut reordered = tokens[..first_end].to_vec();
+            for range in repeated.into_iter().chain(others) {
+                reordered.extend(tokens[range].iter().cloned());
+            }
+            reordered.extend(tokens[i..].iter().cloned());
+            reordered
+        } else {
+            tokens
+        };
+
+        for token in tokens.into_iter().rev() {
+            self.replay.push_front(token);
+        }
+    }
+}
+
+/// The index after a value or group starting at `start`, including any values on the same line after it.
+fn value_end(tokens: &[Result<SpannedToken, Span>], start: usize, source: &str) -> usize {
+    let mut i = start;
+    let mut depth = 0;
+    while let Some(Ok(token)) = tokens.get(i) {
+        i += 1;
+        match token.token {
+            Token::GroupStart => depth += 1,
+            Token::GroupEnd => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            break;
+        }
+    }
+    while let (Some(Ok(previous)), Some(Ok(token))) = (
+        i.checked_sub(1).and_then(|previous| tokens.get(previous)),
+        tokens.get(i),
+    ) {
+        let same_line = !source[previous.span.end..token.span.start].contains('\n');
+        if !same_line || !STRING_ITEMS.contains(&token.token) {
+            break;
+        }
+        i += 1;
+    }
+    i
 }
 
+/// Deserialize a type from vdf text, or KeyValues3 text if it starts with a kv3 header.
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_str(s);
+    from_str_with_options(s, &DeserializerOptions::default())
+}
+
+/// Deserialize a type from vdf text, or KeyValues3 text, with the given options.
+pub fn from_str_with_options<'a, T>(s: &'a str, options: &DeserializerOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    if crate::kv3::is_kv3(s) {
+        return from_entry_with_options(crate::kv3::Kv3Document::parse(s)?.root.into(), options);
+    }
+    let mut deserializer = Deserializer::from_str(s).with_options(*options);
     T::deserialize(&mut deserializer)
 }
 
+/// Deserialize a type from an entry.
+///
+/// If the entry was loaded with [`LoadOptions::with_spans`](crate::entry::LoadOptions::with_spans),
+/// errors point at the part of the source the failing entry was loaded from.
 pub fn from_entry<'a, T>(entry: Entry) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    T::deserialize(entry)
+    from_entry_with_options(entry, &DeserializerOptions::default())
+}
+
+/// Deserialize a type from an entry with the given options.
+pub fn from_entry_with_options<'a, T>(entry: Entry, options: &DeserializerOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let location = entry.location().cloned();
+    crate::entry::attach(
+        T::deserialize(EntryDeserializer::new(entry, *options)),
+        location.as_ref(),
+    )
 }
 
 const VALUE_TOKEN: &[Token] = &[
@@ -119,26 +511,21 @@ impl<'de> de::Deserializer<'de> for &'_ mut Deserializer<'de> {
         let span = token.span.clone();
         match token.token {
             Token::Item | Token::QuotedItem | Token::Statement | Token::QuotedStatement => {
-                let str = token.string(self.source());
-                // note: we don't check for bool as we can't distinguish those from numbers
-                if let Ok(int) = i64::from_str(str.as_ref()) {
-                    return visitor.visit_i64(int).ensure_span(span, self.source());
-                }
-                if let Ok(float) = f64::from_str(str.as_ref()) {
-                    return visitor.visit_f64(float).ensure_span(span, self.source());
-                }
-                if string_is_array(&str) {
-                    self.push_peeked(token);
-                    return self
-                        .deserialize_seq(visitor)
-                        .ensure_span(span, self.source());
-                }
-                match str {
-                    Cow::Borrowed(str) => visitor
-                        .visit_borrowed_str(str)
-                        .ensure_span(span, self.source()),
-                    Cow::Owned(str) => visitor.visit_string(str).ensure_span(span, self.source()),
+                let str = self.string(&token);
+                match self.options.infer(&str) {
+                    Inferred::Bool(bool) => visitor.visit_bool(bool),
+                    Inferred::Int(int) => visitor.visit_i64(int),
+                    Inferred::Float(float) => visitor.visit_f64(float),
+                    Inferred::Array => {
+                        self.push_peeked(token);
+                        self.deserialize_seq(visitor)
+                    }
+                    Inferred::String => match str {
+                        Cow::Borrowed(str) => visitor.visit_borrowed_str(str),
+                        Cow::Owned(str) => visitor.visit_string(str),
+                    },
                 }
+                .ensure_span(span, self.source())
             }
             Token::GroupStart => {
                 let res = visitor.visit_map(TableWalker::new(self, false));
@@ -348,10 +735,11 @@ impl<'de> de::Deserializer<'de> for &'_ mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let token = self.peek().expect_token(STRING_ITEMS, self.source())?;
+        let token = self.peek().expect_token(SEQ_ITEMS, self.source())?;
         let value_str = &self.source()[token.span.clone()];
-        if (value_str.starts_with("\"[") && value_str.ends_with("]\""))
-            || (value_str.starts_with("\"{") && value_str.ends_with("}\""))
+        if token.token != Token::GroupStart
+            && ((value_str.starts_with("\"[") && value_str.ends_with("]\""))
+                || (value_str.starts_with("\"{") && value_str.ends_with("}\"")))
         {
             let _ = self.next();
             let seq = &value_str[2..value_str.len() - 2].trim();
@@ -359,6 +747,7 @@ impl<'de> de::Deserializer<'de> for &'_ mut Deserializer<'de> {
             visitor.visit_seq(StringArrayWalker::new(self.source(), seq, span))
         } else {
             let key = self.last_key.clone();
+            self.group_repeated_key(&key);
             visitor.visit_seq(SeqWalker::new(self, key))
         }
     }
@@ -386,46 +775,25 @@ impl<'de> de::Deserializer<'de> for &'_ mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        // as a special case we allow a map without a `{` at the start of the file to create a top level struct
-        let toplevel = match self
-            .peek()
-            .expect_token(&[Token::GroupStart], self.source())
-        {
-            Ok(_) => {
-                let _ = self.next();
-                false
-            }
-            Err(VdfError::UnexpectedToken(e)) => {
-                if self.tokenizer.count > 1 {
-                    return Err(e.into());
-                }
-                true
-            }
-            Err(e) => {
-                return Err(e);
-            }
-        };
-
-        let value = visitor.visit_map(TableWalker::new(self, toplevel))?;
-        Ok(value)
+        self.deserialize_group(visitor, &[])
     }
 
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        self.deserialize_group(visitor, fields)
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
@@ -433,7 +801,7 @@ impl<'de> de::Deserializer<'de> for &'_ mut Deserializer<'de> {
     {
         let variant_token = self.peek().and_then(|r| r.ok());
         visitor
-            .visit_enum(Enum::new(self))
+            .visit_enum(Enum::new(self, variants))
             .map_err(|e| match (variant_token, &e) {
                 (Some(variant_token), VdfError::UnknownVariant(_)) => {
                     e.with_source_span(variant_token.span.start..self.last_span.end, self.source())
@@ -446,7 +814,11 @@ impl<'de> de::Deserializer<'de> for &'_ mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        let (identifier, _) = self.read_str()?;
+        match self.options.identifier(&identifier, self.identifiers) {
+            Some(name) => visitor.visit_str(name),
+            None => visitor.visit_str(identifier.as_ref()),
+        }
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
@@ -461,6 +833,7 @@ struct TableWalker<'source, 'a> {
     de: &'a mut Deserializer<'source>,
     done: bool,
     toplevel: bool,
+    fields: &'static [&'static str],
 }
 
 const KEY_TOKEN: &[Token] = &[
@@ -477,9 +850,14 @@ impl<'source, 'a> TableWalker<'source, 'a> {
             de,
             done: false,
             toplevel,
+            fields: &[],
         }
     }
 
+    fn with_fields(self, fields: &'static [&'static str]) -> Self {
+        TableWalker { fields, ..self }
+    }
+
     fn source(&self) -> &'source str {
         self.de.source()
     }
@@ -536,9 +914,12 @@ impl<'de> MapAccess<'de> for TableWalker<'de, '_> {
             Err(e) => return Err(e),
         };
 
-        self.de.set_last_key(key.string(self.source()));
+        self.de.set_last_key(self.de.string(&key));
         self.de.push_peeked(key);
-        seed.deserialize(&mut *self.de).map(Some)
+        self.de.identifiers = self.fields;
+        let key = seed.deserialize(&mut *self.de);
+        self.de.identifiers = &[];
+        key.map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -597,10 +978,11 @@ impl<'de> SeqAccess<'de> for SeqWalker<'de, '_> {
 
         let value_span = self.table.de.last_span.clone();
         let newline = match self.table.de.peek_span() {
-            Some(next_span) => {
-                let whitespace = &self.source()[value_span.end..next_span.start];
-                whitespace.contains('\n')
-            }
+            // entries with the same key can be moved before the value when grouping repeated keys
+            Some(next_span) => self
+                .source()
+                .get(value_span.end..next_span.start)
+                .map_or(true, |whitespace| whitespace.contains('\n')),
             _ => false,
         };
 
@@ -614,8 +996,8 @@ impl<'de> SeqAccess<'de> for SeqWalker<'de, '_> {
                 Err(e) => return Err(e),
             };
 
-            let key = key_token.string(self.source());
-            if key != self.key {
+            let key = self.table.de.string(&key_token);
+            if !self.table.de.options.same_key(&key, &self.key) {
                 self.table.de.push_peeked(key_token);
                 self.done = true;
             }
@@ -676,13 +1058,15 @@ where
 
 struct Enum<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
+    variants: &'static [&'static str],
     enclosed: bool,
 }
 
 impl<'a, 'de> Enum<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+    fn new(de: &'a mut Deserializer<'de>, variants: &'static [&'static str]) -> Self {
         Enum {
             de,
+            variants,
             enclosed: false,
         }
     }
@@ -710,8 +1094,10 @@ impl<'de> EnumAccess<'de> for Enum<'_, 'de> {
             self.enclosed = true;
             let _ = self.de.next();
         }
-        let val = seed.deserialize(&mut *self.de)?;
-        Ok((val, self))
+        self.de.identifiers = self.variants;
+        let val = seed.deserialize(&mut *self.de);
+        self.de.identifiers = &[];
+        Ok((val?, self))
     }
 }
 
@@ -750,11 +1136,11 @@ impl<'de> VariantAccess<'de> for Enum<'_, 'de> {
         Ok(val)
     }
 
-    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let val = de::Deserializer::deserialize_map(&mut *self.de, visitor)?;
+        let val = self.de.deserialize_group(visitor, fields)?;
         if self.enclosed {
             self.de
                 .next()
@@ -907,4 +1293,36 @@ mod tests {
         }"#;
         assert_eq!(expected, unwrap_err(from_str(j)));
     }
+
+    #[test]
+    fn test_infer() {
+        use super::{BoolInference, DeserializerOptions, Inferred};
+
+        let default = DeserializerOptions::default();
+        assert!(matches!(default.infer("1"), Inferred::Int(1)));
+        assert!(matches!(default.infer("007"), Inferred::Int(7)));
+        assert!(matches!(default.infer("1e5"), Inferred::Float(_)));
+        assert!(matches!(default.infer("0x1F"), Inferred::String));
+        assert!(matches!(default.infer("true"), Inferred::String));
+        assert!(matches!(default.infer("[1 2]"), Inferred::Array));
+
+        let options = DeserializerOptions::default()
+            .with_bools(BoolInference::ZeroOne)
+            .with_keep_leading_zeros(true)
+            .with_hex(true)
+            .with_exponents(false)
+            .with_arrays(false);
+        assert!(matches!(options.infer("1"), Inferred::Bool(true)));
+        assert!(matches!(options.infer("2"), Inferred::Int(2)));
+        assert!(matches!(options.infer("007"), Inferred::String));
+        assert!(matches!(options.infer("-01.5"), Inferred::String));
+        assert!(matches!(options.infer("0.5"), Inferred::Float(_)));
+        assert!(matches!(options.infer("1e5"), Inferred::String));
+        assert!(matches!(options.infer("0x1F"), Inferred::Int(31)));
+        assert!(matches!(options.infer("[1 2]"), Inferred::String));
+
+        let options = DeserializerOptions::default().with_bools(BoolInference::YesNo);
+        assert!(matches!(options.infer("No"), Inferred::Bool(false)));
+        assert!(matches!(options.infer("0"), Inferred::Int(0)));
+    }
 }
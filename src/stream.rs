@@ -0,0 +1,177 @@
+use crate::event::{Event, EventType};
+use crate::reader::{EscapeMode, Reader};
+use crate::Result;
+use std::io::{BufRead, BufReader, Read};
+
+/// How much input is read at once before trying to parse the next event.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A VDF reader that pulls its input from an [`io::BufRead`](std::io::BufRead) in chunks.
+///
+/// Only the part of the input that hasn't been turned into events yet is kept in memory,
+/// so large files or pipes can be read without loading them entirely.
+/// Events are owned and their spans are byte offsets into the full input, like the spans of errors.
+/// Since only part of the input is buffered, errors after the first chunk don't include the source text.
+///
+/// The input is read by line, values continuing an entry on the same line are
+/// reported the same way as by [`Reader`].
+pub struct StreamReader<R> {
+    input: R,
+    buffer: String,
+    /// Start of the unparsed input in the buffer.
+    offset: usize,
+    /// Offset of the buffer in the full input.
+    position: usize,
+    last_event: Option<EventType>,
+    escape: EscapeMode,
+    eof: bool,
+    chunk_size: usize,
+}
+
+impl<R: BufRead> StreamReader<R> {
+    pub fn new(input: R) -> Self {
+        StreamReader {
+            input,
+            buffer: String::new(),
+            offset: 0,
+            position: 0,
+            last_event: None,
+            escape: EscapeMode::default(),
+            eof: false,
+            chunk_size: CHUNK_SIZE,
+        }
+    }
+
+    /// Set how escape sequences in quoted strings are decoded.
+    pub fn with_escape_mode(self, escape: EscapeMode) -> Self {
+        StreamReader { escape, ..self }
+    }
+
+    /// Drop the consumed input and read at least another chunk, unless the end of the input is reached.
+    fn fill(&mut self) -> std::io::Result<()> {
+        self.buffer.drain(..self.offset);
+        self.position += self.offset;
+        self.offset = 0;
+
+        let target = self.buffer.len() + self.chunk_size;
+        while self.buffer.len() < target {
+            if self.input.read_line(&mut self.buffer)? == 0 {
+                self.eof = true;
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> StreamReader<BufReader<R>> {
+    /// Create a stream reader for an unbuffered input.
+    pub fn from_reader(input: R) -> Self {
+        StreamReader::new(BufReader::new(input))
+    }
+}
+
+impl<R: BufRead> Iterator for StreamReader<R> {
+    type Item = Result<Event<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let window = &self.buffer[self.offset..];
+            let mut reader = Reader::from(window).with_escape_mode(self.escape);
+            reader.last_event = self.last_event;
+            let event = reader.event();
+
+            // if the lexer reached the end of the buffer, the last token or the whitespace after it
+            // might continue in the part of the input that hasn't been read yet
+            if self.eof || reader.lexed_end() < window.len() {
+                let start = self.position + self.offset;
+                self.offset += reader.consumed();
+                self.last_event = reader.last_event;
+                return event.map(|event| match event {
+                    Ok(event) => Ok(event.into_owned().with_offset(start)),
+                    // the source can only be shown while the start of the input is buffered
+                    Err(error) => {
+                        let source = (self.position == 0).then_some(self.buffer.as_str());
+                        Err(error.with_offset(start, source))
+                    }
+                });
+            }
+
+            if let Err(error) = self.fill() {
+                return Some(Err(error.into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Table;
+
+    /// Read a line at a time to check that events split over multiple reads are handled.
+    fn line_by_line(input: &str) -> StreamReader<&[u8]> {
+        StreamReader {
+            chunk_size: 1,
+            ..StreamReader::new(input.as_bytes())
+        }
+    }
+
+    #[test]
+    fn test_same_events() {
+        let inputs = [
+            "\"root\"\n{\n\t\"a\" \"1\" [$WIN32]\n\t\"b\" \"1\" \"2\"\n\t\"c\"\n\t\"3\"\n}\n",
+            include_str!("../tests/data/messy.vdf"),
+            include_str!("../tests/data/conditional.res"),
+            include_str!("../tests/data/serde_array_type.vdf"),
+        ];
+        for input in inputs {
+            let expected: Vec<_> = Reader::from(input)
+                .map(|event| event.unwrap().into_owned())
+                .collect();
+            let streamed: Vec<_> = line_by_line(input).map(Result::unwrap).collect();
+            assert_eq!(expected, streamed);
+        }
+    }
+
+    #[test]
+    fn test_continuation() {
+        let mut reader = line_by_line("\"a\" \"1\" \"2\"\n\"b\" \"3\"\n");
+        assert_eq!(
+            Some(EventType::Entry),
+            reader.next().map(|e| e.unwrap().ty())
+        );
+        let continuation = reader.next().unwrap().unwrap();
+        assert_eq!(EventType::ValueContinuation, continuation.ty());
+        assert_eq!(8..11, continuation.span());
+        assert_eq!(
+            Some(EventType::Entry),
+            reader.next().map(|e| e.unwrap().ty())
+        );
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_error_span() {
+        let mut reader = line_by_line("a 1\nb 2\nc [$X] d\n");
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(Some((15..16).into()), error.span());
+        assert_eq!(None, error.source());
+
+        let error = StreamReader::new("a 1\nb [$X] c\n".as_bytes())
+            .nth(1)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(Some((11..12).into()), error.span());
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_load_table() {
+        let table = Table::load(&mut line_by_line("a {\n b 1\n c 2\n}\n")).unwrap();
+        let group = table.get("a").unwrap().as_table().unwrap();
+        assert_eq!(Some("2"), group.get("c").unwrap().as_str());
+    }
+}
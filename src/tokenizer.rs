@@ -1,5 +1,5 @@
 use crate::reader::quoted_string;
-use crate::Token;
+use crate::{EscapeMode, Token};
 use logos::{Lexer, Span};
 use std::borrow::Cow;
 
@@ -10,10 +10,10 @@ pub struct SpannedToken {
 }
 
 impl SpannedToken {
-    pub fn string<'source>(&self, source: &'source str) -> Cow<'source, str> {
+    pub fn string<'source>(&self, source: &'source str, escape: EscapeMode) -> Cow<'source, str> {
         let full = &source[self.span.clone()];
         match self.token {
-            Token::QuotedItem | Token::QuotedStatement => quoted_string(full),
+            Token::QuotedItem | Token::QuotedStatement => quoted_string(full, escape),
             _ => full.into(),
         }
     }
@@ -27,8 +27,12 @@ pub struct Tokenizer<'source> {
 
 impl<'source> Tokenizer<'source> {
     pub fn from_str(input: &'source str) -> Self {
+        Self::with_escape_mode(input, EscapeMode::default())
+    }
+
+    pub fn with_escape_mode(input: &'source str, escape: EscapeMode) -> Self {
         Tokenizer {
-            lexer: Lexer::new(input),
+            lexer: Lexer::with_extras(input, escape),
             count: 0,
         }
     }
@@ -42,13 +46,17 @@ impl Iterator for Tokenizer<'_> {
     type Item = Result<SpannedToken, Span>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token = match self.lexer.next() {
-            Some(Ok(token)) => token,
-            Some(Err(_)) => {
-                return Some(Err(self.lexer.span()));
-            }
-            None => {
-                return None;
+        let token = loop {
+            match self.lexer.next() {
+                // conditions aren't evaluated when deserializing, all entries are used
+                Some(Ok(Token::Condition)) => continue,
+                Some(Ok(token)) => break token,
+                Some(Err(_)) => {
+                    return Some(Err(self.lexer.span()));
+                }
+                None => {
+                    return None;
+                }
             }
         };
         self.count += 1;
@@ -0,0 +1,559 @@
+//! Source engine materials (`.vmt` files).
+//!
+//! ```text
+//! "VertexLitGeneric"
+//! {
+//!     "$basetexture"          "models/props/crate"
+//!     "$color2"               "[1 0.5 0.5]"
+//!     "$basetexturetransform" "center .5 .5 scale 1 1 rotate 0 translate 0 0"
+//!     ">=dx90"
+//!     {
+//!         "$bumpmap"          "models/props/crate_normal"
+//!     }
+//!     "Proxies"
+//!     {
+//!         "Sine"
+//!         {
+//!             "sineperiod"    "8"
+//!             "resultVar"     "$color2[0]"
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Parameters are looked up regardless of case like the engine does, and their values are
+//! typed from their text. Any group other than `Proxies`, like the `>=dx90` or
+//! `LightmappedGeneric_DX80` shader fallbacks, is loaded as a nested [`Material`].
+//! Conditions on groups, like `">=dx90" [$WIN32]`, are kept and written back.
+
+use crate::entry::{BorrowedEntry, BorrowedTable, LoadOptions, TypedValue};
+use crate::error::UnknownError;
+use crate::writer::{Writer, WriterOptions};
+use crate::{Condition, Result};
+use std::fmt::{self, Display, Formatter, Write};
+
+/// A texture transform like `"center .5 .5 scale 1 1 rotate 0 translate 0 0"`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TextureTransform {
+    pub center: [f32; 2],
+    pub scale: [f32; 2],
+    /// The rotation in degrees.
+    pub rotate: f32,
+    pub translate: [f32; 2],
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        TextureTransform {
+            center: [0.5, 0.5],
+            scale: [1.0, 1.0],
+            rotate: 0.0,
+            translate: [0.0, 0.0],
+        }
+    }
+}
+
+impl TextureTransform {
+    /// Parse a texture transform, parts that are left out keep their default.
+    pub fn parse(value: &str) -> Option<TextureTransform> {
+        let mut transform = TextureTransform::default();
+        let mut parts = value.split_whitespace();
+        let mut found = false;
+        while let Some(part) = parts.next() {
+            let mut number = || parts.next()?.parse::<f32>().ok();
+            match part.to_ascii_lowercase().as_str() {
+                "center" => transform.center = [number()?, number()?],
+                "scale" => transform.scale = [number()?, number()?],
+                "rotate" => transform.rotate = number()?,
+                "translate" => transform.translate = [number()?, number()?],
+                _ => return None,
+            }
+            found = true;
+        }
+        found.then_some(transform)
+    }
+}
+
+impl Display for TextureTransform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let TextureTransform {
+            center: [center_x, center_y],
+            scale: [scale_x, scale_y],
+            rotate,
+            translate: [translate_x, translate_y],
+        } = self;
+        write!(
+            f,
+            "center {center_x} {center_y} scale {scale_x} {scale_y} rotate {rotate} translate {translate_x} {translate_y}"
+        )
+    }
+}
+
+/// The value of a material parameter, typed from its text.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParameterValue {
+    Int(i32),
+    Float(f32),
+    /// A `[x y z]` vector.
+    Vector(Vec<f32>),
+    /// A `{r g b}` color with components from 0 to 255.
+    Color(Vec<f32>),
+    Transform(TextureTransform),
+    /// Any other value, like a texture path or a proxy variable.
+    String(String),
+}
+
+impl ParameterValue {
+    /// Infer the type of a value from its text.
+    pub fn parse(value: &str) -> ParameterValue {
+        let trimmed = value.trim();
+        if let Some(vector) = delimited(trimmed, '[', ']') {
+            return ParameterValue::Vector(vector);
+        }
+        if let Some(color) = delimited(trimmed, '{', '}') {
+            return ParameterValue::Color(color);
+        }
+        if let Some(transform) = TextureTransform::parse(trimmed) {
+            return ParameterValue::Transform(transform);
+        }
+        match TypedValue::infer(value) {
+            TypedValue::Int(value) => ParameterValue::Int(value),
+            TypedValue::Float(value) => ParameterValue::Float(value),
+            _ => ParameterValue::String(value.into()),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ParameterValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            ParameterValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        match self {
+            ParameterValue::Int(value) => Some(*value as f32),
+            ParameterValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Flags like `$translucent` are written as `0` or `1`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ParameterValue::Int(value) => Some(*value != 0),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<&[f32]> {
+        match self {
+            ParameterValue::Vector(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Read a color from either a `[r g b]` vector or a `{r g b}` color, with components from 0 to 1.
+    pub fn as_color(&self) -> Option<[f32; 3]> {
+        match self {
+            ParameterValue::Vector(value) => value.as_slice().try_into().ok(),
+            ParameterValue::Color(value) => {
+                let [r, g, b]: [f32; 3] = value.as_slice().try_into().ok()?;
+                Some([r / 255.0, g / 255.0, b / 255.0])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_transform(&self) -> Option<&TextureTransform> {
+        match self {
+            ParameterValue::Transform(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Read the value as a texture path relative to the `materials` directory, without extension and with forward slashes.
+    pub fn as_texture(&self) -> Option<String> {
+        let path = self.as_str()?.trim().replace('\\', "/");
+        let path = strip_prefix_ignore_case(&path, "materials/").unwrap_or(&path);
+        let path = strip_suffix_ignore_case(path, ".vtf").unwrap_or(path);
+        Some(path.into())
+    }
+}
+
+impl From<&str> for ParameterValue {
+    fn from(value: &str) -> Self {
+        ParameterValue::parse(value)
+    }
+}
+
+impl From<i32> for ParameterValue {
+    fn from(value: i32) -> Self {
+        ParameterValue::Int(value)
+    }
+}
+
+impl From<f32> for ParameterValue {
+    fn from(value: f32) -> Self {
+        ParameterValue::Float(value)
+    }
+}
+
+impl From<TextureTransform> for ParameterValue {
+    fn from(value: TextureTransform) -> Self {
+        ParameterValue::Transform(value)
+    }
+}
+
+impl Display for ParameterValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterValue::Int(value) => write!(f, "{value}"),
+            ParameterValue::Float(value) => write!(f, "{value}"),
+            ParameterValue::Vector(value) => write_delimited(f, value, '[', ']'),
+            ParameterValue::Color(value) => write_delimited(f, value, '{', '}'),
+            ParameterValue::Transform(value) => write!(f, "{value}"),
+            ParameterValue::String(value) => f.write_str(value),
+        }
+    }
+}
+
+fn delimited(value: &str, start: char, end: char) -> Option<Vec<f32>> {
+    value
+        .strip_prefix(start)?
+        .strip_suffix(end)?
+        .split_whitespace()
+        .map(|component| component.parse().ok())
+        .collect()
+}
+
+fn write_delimited(f: &mut Formatter<'_>, values: &[f32], start: char, end: char) -> fmt::Result {
+    f.write_char(start)?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_char(' ')?;
+        }
+        write!(f, "{value}")?;
+    }
+    f.write_char(end)
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value
+        .get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+fn strip_suffix_ignore_case<'a>(value: &'a str, suffix: &str) -> Option<&'a str> {
+    let start = value.len().checked_sub(suffix.len())?;
+    value
+        .get(start..)
+        .filter(|end| end.eq_ignore_ascii_case(suffix))
+        .map(|_| &value[..start])
+}
+
+/// A material parameter.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub value: ParameterValue,
+    pub condition: Option<Condition>,
+}
+
+/// The parameters of a material or proxy, looked up regardless of case.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Parameters(Vec<Parameter>);
+
+impl Parameters {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Get the value of the first parameter with the given name.
+    pub fn get(&self, name: &str) -> Option<&ParameterValue> {
+        self.0
+            .iter()
+            .find(|parameter| parameter.name.eq_ignore_ascii_case(name))
+            .map(|parameter| &parameter.value)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ParameterValue> {
+        self.0
+            .iter_mut()
+            .find(|parameter| parameter.name.eq_ignore_ascii_case(name))
+            .map(|parameter| &mut parameter.value)
+    }
+
+    /// Set a parameter, replacing the value of an existing parameter with the same name and keeping its spelling.
+    pub fn insert<N: Into<String>, V: Into<ParameterValue>>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> Option<ParameterValue> {
+        let name = name.into();
+        let value = value.into();
+        match self.get_mut(&name) {
+            Some(existing) => Some(std::mem::replace(existing, value)),
+            None => {
+                self.push(name, value, None);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ParameterValue> {
+        let index = self
+            .0
+            .iter()
+            .position(|parameter| parameter.name.eq_ignore_ascii_case(name))?;
+        Some(self.0.remove(index).value)
+    }
+
+    /// Iterate over all parameters in the order they are defined in.
+    pub fn iter(&self) -> impl Iterator<Item = &Parameter> {
+        self.0.iter()
+    }
+
+    fn push(&mut self, name: String, value: ParameterValue, condition: Option<Condition>) {
+        self.0.push(Parameter {
+            name,
+            value,
+            condition,
+        });
+    }
+
+    /// Load the values of a group, later definitions replace earlier ones with the same condition.
+    fn load(table: &BorrowedTable<'_>) -> Self {
+        let mut parameters = Parameters::default();
+        for (name, entry, condition) in table.iter_with_conditions() {
+            let BorrowedEntry::Value(value) = entry else {
+                continue;
+            };
+            let value = ParameterValue::parse(value);
+            match parameters.0.iter_mut().find(|parameter| {
+                parameter.name.eq_ignore_ascii_case(name)
+                    && parameter.condition.as_ref() == condition
+            }) {
+                Some(existing) => existing.value = value,
+                None => parameters.push(name.into(), value, condition.cloned()),
+            }
+        }
+        parameters
+    }
+
+    fn write<W: Write>(&self, writer: &mut Writer<W>) -> fmt::Result {
+        let key_width = writer.key_width(self.0.iter().map(|parameter| parameter.name.as_str()));
+        for parameter in &self.0 {
+            writer.write_key_value(
+                &parameter.name,
+                &parameter.value.to_string(),
+                parameter.condition.as_ref(),
+                key_width,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A material proxy from the `Proxies` group.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Proxy {
+    pub name: String,
+    pub parameters: Parameters,
+    pub condition: Option<Condition>,
+}
+
+impl Proxy {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Proxy {
+            name: name.into(),
+            parameters: Parameters::default(),
+            condition: None,
+        }
+    }
+}
+
+/// A material, with its shader and parameters.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Material {
+    pub shader: String,
+    pub parameters: Parameters,
+    pub proxies: Vec<Proxy>,
+    /// The condition of the `Proxies` group, the proxies of all `Proxies` groups are combined
+    /// under the condition of the first one.
+    pub proxies_condition: Option<Condition>,
+    /// Groups like `>=dx90` or `LightmappedGeneric_DX80`, with the name of the group as shader.
+    pub fallbacks: Vec<Material>,
+    /// The condition of the group, like `">=dx90" [$WIN32]`.
+    pub condition: Option<Condition>,
+}
+
+impl Material {
+    pub fn new<S: Into<String>>(shader: S) -> Self {
+        Material {
+            shader: shader.into(),
+            parameters: Parameters::default(),
+            proxies: Vec::new(),
+            proxies_condition: None,
+            fallbacks: Vec::new(),
+            condition: None,
+        }
+    }
+
+    pub fn load_from_str(input: &str) -> Result<Material> {
+        Self::load_from_str_with_options(input, &LoadOptions::default())
+    }
+
+    pub fn load_from_str_with_options(input: &str, options: &LoadOptions) -> Result<Material> {
+        Self::from_table(&BorrowedTable::load_from_str_with_options(input, options)?)
+    }
+
+    /// Read the material from a loaded table, containing the shader group.
+    pub fn from_table(table: &BorrowedTable<'_>) -> Result<Material> {
+        match table.iter_with_conditions().next() {
+            Some((shader, BorrowedEntry::Table(group), condition)) => {
+                Ok(Material::load(shader, group, condition))
+            }
+            _ => Err(UnknownError::from("material has no shader group").into()),
+        }
+    }
+
+    fn load(shader: &str, group: &BorrowedTable<'_>, condition: Option<&Condition>) -> Material {
+        let mut material = Material::new(shader);
+        material.condition = condition.cloned();
+        material.parameters = Parameters::load(group);
+        for (name, entry, condition) in group.iter_with_conditions() {
+            let BorrowedEntry::Table(group) = entry else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case("Proxies") {
+                if material.proxies.is_empty() {
+                    material.proxies_condition = condition.cloned();
+                }
+                material
+                    .proxies
+                    .extend(
+                        group
+                            .iter_with_conditions()
+                            .filter_map(|(name, entry, condition)| {
+                                Some(Proxy {
+                                    name: name.into(),
+                                    parameters: Parameters::load(entry.as_table()?),
+                                    condition: condition.cloned(),
+                                })
+                            }),
+                    );
+            } else {
+                material
+                    .fallbacks
+                    .push(Material::load(name, group, condition));
+            }
+        }
+        material
+    }
+
+    /// Get the value of a parameter.
+    pub fn get(&self, name: &str) -> Option<&ParameterValue> {
+        self.parameters.get(name)
+    }
+
+    /// Get a fallback group by name, like `>=dx90`.
+    pub fn fallback(&self, name: &str) -> Option<&Material> {
+        self.fallbacks
+            .iter()
+            .find(|fallback| fallback.shader.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the first proxy with the given name.
+    pub fn proxy(&self, name: &str) -> Option<&Proxy> {
+        self.proxies
+            .iter()
+            .find(|proxy| proxy.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Write the material as vdf text, with the parameters first and the proxies last.
+    pub fn write_to<W: Write>(&self, writer: &mut W, options: &WriterOptions) -> fmt::Result {
+        self.write(&mut Writer::new(writer, options))
+    }
+
+    fn write<W: Write>(&self, writer: &mut Writer<W>) -> fmt::Result {
+        writer.start_group(&self.shader, self.condition.as_ref())?;
+        self.parameters.write(writer)?;
+        for fallback in &self.fallbacks {
+            fallback.write(writer)?;
+        }
+        if !self.proxies.is_empty() {
+            writer.start_group("Proxies", self.proxies_condition.as_ref())?;
+            for proxy in &self.proxies {
+                writer.start_group(&proxy.name, proxy.condition.as_ref())?;
+                proxy.parameters.write(writer)?;
+                writer.end_group()?;
+            }
+            writer.end_group()?;
+        }
+        writer.end_group()
+    }
+}
+
+impl Display for Material {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f, &WriterOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(ParameterValue::Int(1), ParameterValue::parse("1"));
+        assert_eq!(ParameterValue::Float(1.9), ParameterValue::parse("1.9"));
+        assert_eq!(
+            ParameterValue::Vector(vec![0.5, 0.5]),
+            ParameterValue::parse("[ 0.50 0.50 ]")
+        );
+        assert_eq!(
+            Some([1.0, 0.0, 0.0]),
+            ParameterValue::parse("{255 0 0}").as_color()
+        );
+        assert_eq!(
+            ParameterValue::String("[ a b ]".into()),
+            ParameterValue::parse("[ a b ]")
+        );
+        let transform = ParameterValue::parse("center .5 .5 scale 2 2 rotate 45 translate 0 0");
+        assert_eq!(
+            Some(&TextureTransform {
+                scale: [2.0, 2.0],
+                rotate: 45.0,
+                ..TextureTransform::default()
+            }),
+            transform.as_transform()
+        );
+        assert_eq!(
+            "center 0.5 0.5 scale 2 2 rotate 45 translate 0 0",
+            transform.to_string()
+        );
+        assert_eq!(None, TextureTransform::parse("center 1"));
+        assert_eq!(
+            Some("concrete/floor".into()),
+            ParameterValue::parse("Materials\\concrete\\floor.vtf").as_texture()
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{Condition, EscapeMode};
+use std::borrow::Cow;
+use std::fmt::{Result, Write};
+
+/// How nested groups are indented.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum IndentStyle {
+    /// A single tab per level.
+    #[default]
+    Tabs,
+    /// The given number of spaces per level.
+    Spaces(usize),
+}
+
+/// When keys and values are quoted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum QuoteStyle {
+    /// Always quote keys and values.
+    #[default]
+    Always,
+    /// Only quote keys and values that can't be read back unquoted.
+    WhenNeeded,
+}
+
+/// How arrays are written.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ArrayStyle {
+    /// Write the key once for every item.
+    #[default]
+    RepeatedKeys,
+    /// Write the items as a single `"[a b c]"` value.
+    ///
+    /// Arrays containing items that can't be written this way fall back to repeated keys.
+    Bracketed,
+}
+
+/// Formatting options for writing vdf text.
+#[derive(Clone, Debug, Default)]
+pub struct WriterOptions {
+    pub indent: IndentStyle,
+    pub quote: QuoteStyle,
+    /// Pad keys so that all values in a group line up.
+    pub align_values: bool,
+    pub array: ArrayStyle,
+    /// How quoted strings are escaped, this should match the mode used to read the output.
+    ///
+    /// With [`EscapeMode::Raw`], writing fails for strings containing a quote,
+    /// since those can't be read back unchanged without escaping.
+    pub escape: EscapeMode,
+}
+
+impl WriterOptions {
+    pub fn with_indent(self, indent: IndentStyle) -> Self {
+        WriterOptions { indent, ..self }
+    }
+
+    pub fn with_quote(self, quote: QuoteStyle) -> Self {
+        WriterOptions { quote, ..self }
+    }
+
+    pub fn with_align_values(self, align_values: bool) -> Self {
+        WriterOptions {
+            align_values,
+            ..self
+        }
+    }
+
+    pub fn with_array(self, array: ArrayStyle) -> Self {
+        WriterOptions { array, ..self }
+    }
+
+    pub fn with_escape(self, escape: EscapeMode) -> Self {
+        WriterOptions { escape, ..self }
+    }
+}
+
+/// Escape a string so that reading it back as a quoted item with the same mode gives the original string.
+///
+/// With [`EscapeMode::QuotesOnly`] the reader only decodes `\\` and `\"`, any other backslash is kept as-is,
+/// so we only need to escape backslashes that would otherwise be decoded.
+/// With [`EscapeMode::Raw`] nothing is escaped, only strings for which [`can_be_raw`] holds can be written.
+pub(crate) fn escape(value: &str, mode: EscapeMode) -> Cow<'_, str> {
+    let special: &[char] = match mode {
+        EscapeMode::Raw => return value.into(),
+        EscapeMode::QuotesOnly => &['"', '\\'],
+        EscapeMode::Full => &['"', '\\', '\n', '\t', '\r'],
+    };
+    if !value.contains(special) {
+        return value.into();
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+    let mut chars = value.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, mode) {
+            ('"', _) => escaped.push_str(r#"\""#),
+            ('\\', EscapeMode::Full) => escaped.push_str(r#"\\"#),
+            ('\\', _) if matches!(chars.peek(), None | Some('\\' | '"')) => {
+                escaped.push_str(r#"\\"#)
+            }
+            ('\n', EscapeMode::Full) => escaped.push_str(r#"\n"#),
+            ('\t', EscapeMode::Full) => escaped.push_str(r#"\t"#),
+            ('\r', EscapeMode::Full) => escaped.push_str(r#"\r"#),
+            (char, _) => escaped.push(char),
+        }
+    }
+    escaped.into()
+}
+
+/// Check if a string can be written in quotes without escaping and be read back unchanged.
+///
+/// Without escaping, a quote would end the string early.
+pub(crate) fn can_be_raw(value: &str) -> bool {
+    !value.contains('"')
+}
+
+/// Check if a string can be written without quotes and be read back unchanged.
+pub(crate) fn can_be_unquoted(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with(['"', '['])
+        && !value.starts_with("//")
+        && !value.contains(|c: char| c.is_whitespace() || c == '{' || c == '}')
+}
+
+/// Writes vdf text with the configured formatting.
+pub(crate) struct Writer<'a, W> {
+    out: &'a mut W,
+    options: &'a WriterOptions,
+    depth: usize,
+}
+
+impl<'a, W: Write> Writer<'a, W> {
+    pub fn new(out: &'a mut W, options: &'a WriterOptions) -> Self {
+        Writer {
+            out,
+            options,
+            depth: 0,
+        }
+    }
+
+    pub fn options(&self) -> &WriterOptions {
+        self.options
+    }
+
+    /// The width of the key column when aligning values.
+    pub fn key_width<'k, I: IntoIterator<Item = &'k str>>(&self, keys: I) -> usize {
+        if self.options.align_values {
+            keys.into_iter()
+                .map(|key| self.string_width(key))
+                .max()
+                .unwrap_or_default()
+        } else {
+            0
+        }
+    }
+
+    fn string_width(&self, value: &str) -> usize {
+        if self.options.quote == QuoteStyle::WhenNeeded && can_be_unquoted(value) {
+            value.chars().count()
+        } else {
+            escape(value, self.options.escape).chars().count() + 2
+        }
+    }
+
+    /// Write a key or value, quoting it if needed.
+    ///
+    /// Fails for strings that can't be written with [`EscapeMode::Raw`] instead of writing something
+    /// that reads back differently.
+    pub fn write_string(&mut self, value: &str) -> Result {
+        if self.options.quote == QuoteStyle::WhenNeeded && can_be_unquoted(value) {
+            self.out.write_str(value)
+        } else if self.options.escape == EscapeMode::Raw && !can_be_raw(value) {
+            Err(std::fmt::Error)
+        } else {
+            write!(self.out, "\"{}\"", escape(value, self.options.escape))
+        }
+    }
+
+    fn write_indent(&mut self) -> Result {
+        for _ in 0..self.depth {
+            match self.options.indent {
+                IndentStyle::Tabs => self.out.write_char('\t')?,
+                IndentStyle::Spaces(count) => write!(self.out, "{:count$}", "")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a single value as the root of the document
+    pub fn write_root_value(&mut self, value: &str) -> Result {
+        self.write_string(value)?;
+        self.out.write_char('\n')
+    }
+
+    fn write_condition(&mut self, condition: Option<&Condition>) -> Result {
+        match condition {
+            Some(condition) => write!(self.out, " {condition}"),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_key_value(
+        &mut self,
+        key: &str,
+        value: &str,
+        condition: Option<&Condition>,
+        key_width: usize,
+    ) -> Result {
+        self.write_indent()?;
+        self.write_string(key)?;
+        if self.options.align_values {
+            let padding = key_width.saturating_sub(self.string_width(key)) + 1;
+            write!(self.out, "{:padding$}", "")?;
+        } else if self.options.indent == IndentStyle::Tabs {
+            self.out.write_str("\t\t")?;
+        } else {
+            self.out.write_char(' ')?;
+        }
+        self.write_string(value)?;
+        self.write_condition(condition)?;
+        self.out.write_char('\n')
+    }
+
+    pub fn start_group(&mut self, key: &str, condition: Option<&Condition>) -> Result {
+        self.write_indent()?;
+        self.write_string(key)?;
+        self.write_condition(condition)?;
+        self.out.write_char('\n')?;
+        self.write_indent()?;
+        self.out.write_str("{\n")?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn end_group(&mut self) -> Result {
+        self.depth = self.depth.saturating_sub(1);
+        self.write_indent()?;
+        self.out.write_str("}\n")
+    }
+}
+
+/// Format the items of an array as a single `[a b c]` string, if possible.
+pub(crate) fn bracketed<'a, I: IntoIterator<Item = Option<&'a str>>>(items: I) -> Option<String> {
+    let mut out = String::from("[");
+    for (i, item) in items.into_iter().enumerate() {
+        let item = item?;
+        if item.is_empty() || item.contains(char::is_whitespace) {
+            return None;
+        }
+        if i > 0 {
+            out.push(' ');
+        }
+        out.push_str(item);
+    }
+    out.push(']');
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::quoted_string;
+
+    #[test]
+    fn test_escape_roundtrip() {
+        for input in [
+            "plain",
+            r#"C:\test\no newline"#,
+            r#"\\"$translucent""#,
+            r#"trailing\"#,
+            r#"quote " inside"#,
+            "new\nline\tand tab",
+        ] {
+            for mode in [EscapeMode::QuotesOnly, EscapeMode::Full] {
+                let quoted = format!("\"{}\"", escape(input, mode));
+                assert_eq!(input, quoted_string(&quoted, mode));
+            }
+        }
+        assert_eq!(r#"a\nb"#, escape("a\nb", EscapeMode::Full));
+        assert_eq!(r#"C:\\test"#, escape(r#"C:\test"#, EscapeMode::Full));
+        assert_eq!(r#"C:\test"#, escape(r#"C:\test"#, EscapeMode::QuotesOnly));
+    }
+
+    #[test]
+    fn test_raw() {
+        for input in ["plain", r"C:\test", r"trailing\", "line\\\nbreak", r"\\"] {
+            assert!(can_be_raw(input));
+            let quoted = format!("\"{input}\"");
+            assert_eq!(input, quoted_string(&quoted, EscapeMode::Raw));
+        }
+        assert!(!can_be_raw(r#"a"b"#));
+        assert!(!can_be_raw(r#"escaped \" quote"#));
+
+        let mut out = String::new();
+        let options = WriterOptions::default().with_escape(EscapeMode::Raw);
+        let mut writer = Writer::new(&mut out, &options);
+        assert!(writer.write_key_value("k", r#"a"b"#, None, 0).is_err());
+    }
+
+    #[test]
+    fn test_unquoted() {
+        assert!(can_be_unquoted("foo"));
+        assert!(can_be_unquoted("$basetexture"));
+        assert!(!can_be_unquoted(""));
+        assert!(!can_be_unquoted("foo bar"));
+        assert!(!can_be_unquoted("{"));
+        assert!(!can_be_unquoted("//foo"));
+        assert!(!can_be_unquoted("[1 2]"));
+    }
+}
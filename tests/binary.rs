@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::read;
+use test_case::test_case;
+use vdf_reader::binary::{
+    from_bytes, infer_type, to_bytes, BinaryEntry, BinaryReader, BinaryTable, BinaryType,
+    BinaryValue,
+};
+use vdf_reader::entry::Table;
+
+#[test_case("tests/data/shortcuts.vdf")]
+fn test_binary_parse(path: &str) {
+    let raw = read(path).unwrap();
+    let parsed = Table::load(&mut BinaryReader::new(&raw))
+        .map_err(miette::Error::from)
+        .expect("failed to parse test data");
+    insta::assert_ron_snapshot!(path, parsed);
+}
+
+#[test_case("tests/data/shortcuts.vdf")]
+fn test_binary_roundtrip(path: &str) {
+    let raw = read(path).unwrap();
+    let table = BinaryTable::load_from_bytes(&raw).unwrap();
+    assert_eq!(raw, table.to_bytes());
+}
+
+#[test_case(b"\x00a\0\x01k\0v\0\x0B\x0B" ; "alternate end markers")]
+#[test_case(b"\x00a\0\x01k\0v\0\x08\x0B" ; "mixed end markers")]
+#[test_case(b"\x01k\0caf\xE9\0\x08" ; "windows 1252 value")]
+#[test_case(b"\x01caf\xE9\0v\0\x00caf\xE9\0\x08\x08" ; "windows 1252 key")]
+#[test_case(b"\x00a\0\x01k\0v\0\x08" ; "no root end")]
+#[test_case(b"" ; "empty")]
+fn test_binary_roundtrip_bytes(raw: &[u8]) {
+    let table = BinaryTable::load_from_bytes(raw).unwrap();
+    assert_eq!(raw, table.to_bytes());
+}
+
+#[test]
+fn test_binary_roundtrip_string_table() {
+    let strings: Vec<Cow<str>> = vec!["appid".into(), "common".into()];
+    let mut raw = vec![0x00, 1, 0, 0, 0];
+    raw.extend([0x02, 0, 0, 0, 0]);
+    raw.extend(440i32.to_le_bytes());
+    raw.extend([0x08, 0x08]);
+
+    let mut reader = BinaryReader::new(&raw).with_string_table(&strings);
+    let table = BinaryTable::load(&mut reader).unwrap();
+    assert_eq!(
+        Some(&BinaryValue::Int32(440)),
+        table
+            .get("common")
+            .and_then(BinaryEntry::as_table)
+            .and_then(|common| common.get("appid"))
+            .and_then(BinaryEntry::as_value)
+    );
+    assert_eq!(raw, table.to_bytes());
+}
+
+#[test]
+fn test_binary_invalid_utf8() {
+    let raw = b"\x01k\0caf\xE9\0\x08";
+    let table = BinaryTable::load_from_bytes(raw).unwrap();
+    let value = table.get("k").and_then(BinaryEntry::as_value).unwrap();
+    assert_eq!(&BinaryValue::Bytes(b"caf\xE9".as_slice().into()), value);
+    assert_eq!(BinaryType::String, value.ty());
+    assert_eq!("caf\u{FFFD}", value.to_string());
+}
+
+#[test]
+fn test_binary_from_table() {
+    let table = Table::load_from_str(
+        r#"
+        "shortcut"
+        {
+            "appid" "3000000000"
+            "AppName" "123"
+            "IsHidden" "0"
+            "scale" "1.5"
+        }
+    "#,
+    )
+    .unwrap();
+
+    let inferred = BinaryTable::from_table(&table, |_, value| infer_type(value)).unwrap();
+    let shortcut = inferred
+        .get("shortcut")
+        .and_then(BinaryEntry::as_table)
+        .unwrap();
+    assert_eq!(
+        Some(&BinaryValue::Uint64(3000000000)),
+        shortcut.get("appid").and_then(BinaryEntry::as_value)
+    );
+    assert_eq!(
+        Some(&BinaryValue::Int32(123)),
+        shortcut.get("AppName").and_then(BinaryEntry::as_value)
+    );
+    assert_eq!(
+        Some(&BinaryValue::Float32(1.5)),
+        shortcut.get("scale").and_then(BinaryEntry::as_value)
+    );
+
+    let picked = BinaryTable::from_table(&table, |key, value| match key {
+        "AppName" => BinaryType::String,
+        "appid" => BinaryType::Int64,
+        _ => infer_type(value),
+    })
+    .unwrap();
+    let shortcut = picked
+        .get("shortcut")
+        .and_then(BinaryEntry::as_table)
+        .unwrap();
+    assert_eq!(
+        Some(&BinaryValue::String("123".into())),
+        shortcut.get("AppName").and_then(BinaryEntry::as_value)
+    );
+    assert_eq!(
+        Some(&BinaryValue::Int64(3000000000)),
+        shortcut.get("appid").and_then(BinaryEntry::as_value)
+    );
+
+    let reloaded = Table::load(&mut BinaryReader::new(&picked.to_bytes())).unwrap();
+    assert_eq!(table, reloaded);
+
+    assert!(BinaryTable::from_table(&table, |_, _| BinaryType::Int32).is_err());
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Shortcuts {
+    shortcuts: BTreeMap<String, Shortcut>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+struct Shortcut {
+    #[serde(rename = "appid")]
+    app_id: i32,
+    app_name: String,
+    exe: String,
+    is_hidden: bool,
+    #[serde(rename = "tags")]
+    tags: BTreeMap<String, String>,
+}
+
+#[test]
+fn test_binary_serde() {
+    let raw = read("tests/data/shortcuts.vdf").unwrap();
+    let shortcuts: Shortcuts = from_bytes(&raw).map_err(miette::Error::from).unwrap();
+    let heroic = &shortcuts.shortcuts["0"];
+    assert_eq!(-1234567890, heroic.app_id);
+    assert_eq!("Heroic", heroic.app_name);
+    assert_eq!(r#""/usr/bin/heroic""#, heroic.exe);
+    assert!(!heroic.is_hidden);
+    assert_eq!(Some("favorite"), heroic.tags.get("0").map(String::as_str));
+    assert!(shortcuts.shortcuts["1"].tags.is_empty());
+}
+
+#[test]
+fn test_binary_serde_roundtrip() {
+    let raw = read("tests/data/shortcuts.vdf").unwrap();
+    let shortcuts: Shortcuts = from_bytes(&raw).unwrap();
+
+    let written = to_bytes(&shortcuts).unwrap();
+    let table = BinaryTable::load_from_bytes(&written).unwrap();
+    let heroic = table
+        .get("shortcuts")
+        .and_then(BinaryEntry::as_table)
+        .and_then(|shortcuts| shortcuts.get("0"))
+        .and_then(BinaryEntry::as_table)
+        .unwrap();
+    assert_eq!(
+        Some(&BinaryValue::Int32(-1234567890)),
+        heroic.get("appid").and_then(BinaryEntry::as_value)
+    );
+    assert_eq!(
+        Some(&BinaryValue::Int32(0)),
+        heroic.get("IsHidden").and_then(BinaryEntry::as_value)
+    );
+    assert_eq!(shortcuts, from_bytes(&written).unwrap());
+}
+
+#[test]
+fn test_binary_serde_f64() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Floats {
+        small: f64,
+        large: f64,
+        precise: f64,
+    }
+
+    let floats = Floats {
+        small: 0.1,
+        large: 1e300,
+        precise: 16777217.0,
+    };
+    let written = to_bytes(&floats).unwrap();
+    let table = BinaryTable::load_from_bytes(&written).unwrap();
+    assert_eq!(
+        Some(BinaryType::Float32),
+        table
+            .get("small")
+            .and_then(BinaryEntry::as_value)
+            .map(BinaryValue::ty)
+    );
+    assert_eq!(
+        Some(BinaryType::String),
+        table
+            .get("large")
+            .and_then(BinaryEntry::as_value)
+            .map(BinaryValue::ty)
+    );
+    assert_eq!(floats, from_bytes(&written).unwrap());
+}
@@ -0,0 +1,20 @@
+"Resource/UI/MainMenu.res"
+{
+	"TitleLabel"
+	{
+		"ControlName"		"CExLabel"
+		"xpos"		"10"	[$WIN32]
+		"xpos"		"20"	[$OSX]
+		"ypos"		"30"
+		"font"		"HudFontSmall"	[!$X360]
+		"font"		"HudFontBig"	[$X360]
+	}
+	"ConsoleOnly" [$X360]
+	{
+		"visible"		"1"
+	}
+	"DesktopOnly" [$WIN32||$OSX||$LINUX]
+	{
+		"visible"		"1"
+	}
+}
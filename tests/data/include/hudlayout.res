@@ -0,0 +1,8 @@
+"Resource/HudLayout.res"
+{
+	"HudHealth"
+	{
+		"xpos"		"10"
+		"ypos"		"20"
+	}
+}
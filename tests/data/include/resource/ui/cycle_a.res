@@ -0,0 +1,2 @@
+#base "cycle_b.res"
+"a" "1"
@@ -0,0 +1,2 @@
+#base "cycle_a.res"
+"b" "1"
@@ -0,0 +1,2 @@
+#base "..\..\..\conditional.res"
+"a" "1"
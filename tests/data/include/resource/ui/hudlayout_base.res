@@ -0,0 +1,14 @@
+#base "../../hudlayout.res"
+
+"Resource/HudLayout.res"
+{
+	"HudHealth"
+	{
+		"xpos"		"15"
+		"wide"		"100"
+	}
+	"HudAmmo"
+	{
+		"xpos"		"30"
+	}
+}
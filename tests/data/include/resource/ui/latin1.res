@@ -0,0 +1,5 @@
+#include "latin1_strings.res"
+"menu"
+{
+	"name"	"main"
+}
@@ -0,0 +1,11 @@
+#base "hudlayout_base.res"
+#include "overrides.res"
+
+"Resource/HudLayout.res"
+{
+	"HudAmmo"
+	{
+		"xpos"		"35"
+		"visible"		"1"
+	}
+}
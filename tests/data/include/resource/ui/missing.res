@@ -0,0 +1 @@
+#base "does_not_exist.res"
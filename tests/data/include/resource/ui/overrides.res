@@ -0,0 +1,7 @@
+"Resource/HudLayout.res"
+{
+	"HudAmmo"
+	{
+		"xpos"		"40"
+	}
+}
@@ -0,0 +1,142 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::{read, read_to_string};
+use test_case::test_case;
+use vdf_reader::entry::{Entry, Table};
+use vdf_reader::kv3::{Kv3Document, Kv3Value};
+
+#[test_case("tests/data/ability.kv3")]
+fn test_kv3_parse(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let parsed = Kv3Document::parse(&raw)
+        .map_err(miette::Error::from)
+        .expect("failed to parse test data");
+    let table = Entry::from(parsed.root)
+        .as_table()
+        .cloned()
+        .expect("root is an object");
+    insta::assert_ron_snapshot!(path, table);
+}
+
+#[test_case("tests/data/ability.kv3")]
+fn test_kv3_roundtrip(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let parsed = Kv3Document::parse(&raw).unwrap();
+    let written = parsed.to_string();
+    let reparsed = Kv3Document::parse(&written)
+        .map_err(miette::Error::from)
+        .expect("failed to parse written data");
+    assert_eq!(parsed, reparsed, "{written}");
+}
+
+#[test]
+fn test_kv3_write() {
+    let document = Kv3Document::parse(
+        "<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->\n{ a = 1 b = [1.0, \"x\"] c = { d = resource:\"e\" } }",
+    )
+    .unwrap();
+    assert_eq!(
+        "<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->\n{\n\ta = 1\n\tb = [ 1.0, \"x\" ]\n\tc =\n\t{\n\t\td = resource:\"e\"\n\t}\n}\n",
+        document.to_string()
+    );
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[allow(non_snake_case)]
+struct Upgrade {
+    m_strName: String,
+    m_flBonus: f32,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[allow(non_snake_case)]
+struct Property {
+    m_strValue: String,
+    #[serde(default)]
+    m_bCanSetTokenOverride: bool,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[allow(non_snake_case)]
+struct Ability {
+    _class: String,
+    m_bEnabled: bool,
+    m_flCooldown: f32,
+    m_iMaxCharges: u8,
+    m_nAbilityID: u64,
+    m_strCastSound: String,
+    m_vecOffset: [f32; 3],
+    m_strDescription: String,
+    m_vecUpgrades: Vec<Upgrade>,
+    m_mapProperties: BTreeMap<String, Property>,
+}
+
+#[test]
+fn test_kv3_serde() {
+    let raw = read_to_string("tests/data/ability.kv3").unwrap();
+    let ability: Ability = vdf_reader::from_str(&raw)
+        .map_err(miette::Error::from)
+        .unwrap();
+    assert_eq!("citadel_ability_fireball", ability._class);
+    assert!(ability.m_bEnabled);
+    assert_eq!(12.5, ability.m_flCooldown);
+    assert_eq!(2828153926, ability.m_nAbilityID);
+    assert_eq!([0.0, -1.5, 24.0], ability.m_vecOffset);
+    assert_eq!(
+        "Throws a fireball.\nDeals \"burn\" damage over time.",
+        ability.m_strDescription
+    );
+    assert_eq!(2, ability.m_vecUpgrades.len());
+    assert_eq!("Range", ability.m_vecUpgrades[1].m_strName);
+    assert!(ability.m_mapProperties["AbilityCooldown"].m_bCanSetTokenOverride);
+    assert_eq!("30m", ability.m_mapProperties["Ability Range"].m_strValue);
+
+    let same: Ability = vdf_reader::kv3::from_str(&raw).unwrap();
+    assert_eq!(ability, same);
+}
+
+#[test]
+fn test_kv3_table() {
+    let raw = read_to_string("tests/data/ability.kv3").unwrap();
+    let document = Kv3Document::parse(&raw).unwrap();
+    assert_eq!(
+        Some("fireball_base"),
+        document
+            .root
+            .get("m_subclass")
+            .and_then(|subclass| subclass.get("_base"))
+            .and_then(Kv3Value::as_str)
+    );
+    let table: Table = document.root.as_object().cloned().unwrap().into();
+    assert_eq!(Some("1"), table.get("m_bEnabled").and_then(Entry::as_str));
+}
+
+#[test_case("tests/data/kv3/legacy.kv3b")]
+#[test_case("tests/data/kv3/v1_lz4.kv3b")]
+#[test_case("tests/data/kv3/v2_zstd.kv3b")]
+#[test_case("tests/data/kv3/v4.kv3b")]
+#[test_case("tests/data/kv3/v5.kv3b")]
+fn test_kv3_binary(path: &str) {
+    let raw = read(path).unwrap();
+    let decoded = Kv3Document::from_bytes(&raw)
+        .map_err(miette::Error::from)
+        .expect("failed to decode test data");
+    let expected =
+        Kv3Document::parse(&read_to_string("tests/data/kv3/fixture.kv3").unwrap()).unwrap();
+    assert_eq!(expected, decoded);
+}
+
+#[test]
+fn test_kv3_binary_errors() {
+    let error = Kv3Document::from_bytes(b"VKV4").unwrap_err();
+    assert_eq!(
+        "unknown KeyValues3 magic 0x34564b56 at offset 0",
+        error.to_string()
+    );
+
+    for path in ["tests/data/kv3/v4.kv3b", "tests/data/kv3/v5.kv3b"] {
+        let mut raw = read(path).unwrap();
+        raw.truncate(raw.len() - 2);
+        assert!(Kv3Document::from_bytes(&raw).is_err());
+    }
+}
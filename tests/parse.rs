@@ -1,12 +1,24 @@
-use std::fs::read_to_string;
+use miette::{GraphicalReportHandler, GraphicalTheme};
+use std::borrow::Cow;
+use std::fs::{read_to_string, File};
 use test_case::test_case;
-use vdf_reader::entry::Table;
-use vdf_reader::Reader;
+use vdf_reader::cst::Document;
+use vdf_reader::entry::{
+    BorrowedEntry, BorrowedTable, ConditionMode, Entry, LoadOptions, Table, TypeInference,
+    TypedTable, TypedValue,
+};
+use vdf_reader::error::ParseErrors;
+use vdf_reader::localization::{Localization, LocalizationReport};
+use vdf_reader::{
+    ArrayStyle, BoolInference, Condition, DecodedText, Defines, Encoding, EscapeMode, FileResolver,
+    IndentStyle, QuoteStyle, Reader, StreamReader, VdfError, WriterOptions,
+};
 
 #[test_case("tests/data/concrete.vmt")]
 #[test_case("tests/data/messy.vdf")]
 #[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
 #[test_case("tests/data/toplevel.vdf")]
+#[test_case("tests/data/conditional.res")]
 fn test_parse(path: &str) {
     let raw = read_to_string(path).unwrap();
     let mut reader = Reader::from(raw.as_str());
@@ -15,3 +27,543 @@ fn test_parse(path: &str) {
         .expect("failed to parse test data");
     insta::assert_ron_snapshot!(path, parsed);
 }
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/messy.vdf")]
+#[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
+#[test_case("tests/data/toplevel.vdf")]
+#[test_case("tests/data/conditional.res")]
+fn test_write_roundtrip(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let parsed = Table::load_from_str(&raw).unwrap();
+
+    let options = [
+        WriterOptions::default(),
+        WriterOptions::default()
+            .with_indent(IndentStyle::Spaces(4))
+            .with_quote(QuoteStyle::WhenNeeded)
+            .with_align_values(true),
+        WriterOptions::default().with_array(ArrayStyle::Bracketed),
+    ];
+    for options in options {
+        let mut written = String::new();
+        parsed.write_to(&mut written, &options).unwrap();
+        let reparsed = Table::load_from_str(&written)
+            .map_err(miette::Error::from)
+            .expect("failed to parse written data");
+        assert_eq!(parsed, reparsed, "{written}");
+    }
+}
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/messy.vdf")]
+#[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
+#[test_case("tests/data/toplevel.vdf")]
+#[test_case("tests/data/conditional.res")]
+fn test_typed_roundtrip(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let typed = TypedTable::load_from_str(&raw).unwrap();
+    let reparsed = TypedTable::load_from_str(&typed.to_string()).unwrap();
+    assert_eq!(typed, reparsed);
+    assert_eq!(Table::load_from_str(&raw).unwrap(), Table::from(typed));
+}
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/messy.vdf")]
+#[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
+#[test_case("tests/data/toplevel.vdf")]
+#[test_case("tests/data/conditional.res")]
+#[test_case("tests/data/serde_array_type.vdf")]
+fn test_borrowed_roundtrip(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let borrowed = BorrowedTable::load_from_str(&raw).unwrap();
+    let written = borrowed.to_string();
+    let reparsed = BorrowedTable::load_from_str(&written).unwrap();
+    assert_eq!(borrowed, reparsed);
+    assert_eq!(
+        Table::load_from_str(&raw).unwrap(),
+        Table::from(borrowed.clone())
+    );
+    assert_eq!(borrowed, borrowed.clone().into_owned());
+}
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/messy.vdf")]
+#[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
+#[test_case("tests/data/toplevel.vdf")]
+#[test_case("tests/data/conditional.res")]
+#[test_case("tests/data/serde_array_type.vdf")]
+fn test_stream(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let expected: Vec<_> = Reader::from(raw.as_str())
+        .map(|event| event.map(|event| event.into_owned()))
+        .collect();
+    let streamed: Vec<_> = StreamReader::from_reader(File::open(path).unwrap()).collect();
+    assert_eq!(format!("{expected:?}"), format!("{streamed:?}"));
+
+    let mut stream = StreamReader::from_reader(File::open(path).unwrap());
+    assert_eq!(
+        Table::load_from_str(&raw).unwrap(),
+        Table::load(&mut stream).unwrap()
+    );
+}
+
+#[test]
+fn test_borrowed_values() {
+    let source = r#"
+    "plain" "value"
+    "escaped" "a \"quoted\" value"
+    "#;
+    let table = BorrowedTable::load_from_str(source).unwrap();
+    assert!(matches!(
+        table.get("plain"),
+        Some(BorrowedEntry::Value(Cow::Borrowed("value")))
+    ));
+    assert!(matches!(
+        table.get("escaped"),
+        Some(BorrowedEntry::Value(Cow::Owned(_)))
+    ));
+    assert_eq!(
+        Some("a \"quoted\" value"),
+        table.get("escaped").and_then(BorrowedEntry::as_str)
+    );
+}
+
+#[test]
+fn test_borrowed_case_insensitive() {
+    let source = "Material {\n$BaseTexture a\n\"$basetexture\" b\n}";
+    let table = BorrowedTable::load_from_str(source).unwrap();
+    assert!(table.get("material").is_none());
+
+    let options = LoadOptions::default().with_case_insensitive(true);
+    let table = BorrowedTable::load_from_str_with_options(source, &options).unwrap();
+    assert!(table.is_case_insensitive());
+    let material = table.get("material").and_then(BorrowedEntry::as_table);
+    assert_eq!(2, material.unwrap().get_all("$BASETEXTURE").count());
+
+    let table = Table::from(table);
+    assert!(table.is_case_insensitive());
+    assert_eq!(
+        Some("a"),
+        Entry::Table(table)
+            .lookup("MATERIAL.$basetexture")
+            .and_then(|entry| entry.as_slice())
+            .and_then(|array| array[0].as_str())
+    );
+}
+
+#[test]
+fn test_typed_values() {
+    let raw = read_to_string("tests/data/concrete.vmt").unwrap();
+    let typed = TypedTable::load_from_str(&raw).unwrap();
+    let material = typed.get("LightmappedGeneric").unwrap().as_table().unwrap();
+    let scale = material.get("$detailscale").unwrap();
+    assert_eq!(Some(TypedValue::Float(1.9)), scale.typed());
+    assert_eq!(Some("1.9"), scale.as_str());
+    assert_eq!(
+        Some(TypedValue::Int(1)),
+        material.get("$ssbump").unwrap().typed()
+    );
+    assert_eq!(
+        Some(TypedValue::String),
+        material.get("%keywords").unwrap().typed()
+    );
+
+    let source = "root {\n enabled true\n origin \"[0 -1.5 24]\"\n count 3\n}";
+    let options = LoadOptions::default().with_types(
+        TypeInference::default()
+            .with_bools(BoolInference::TrueFalse)
+            .with_vectors(true),
+    );
+    let typed = TypedTable::load_from_str_with_options(source, &options).unwrap();
+    let root = typed.get("root").unwrap().as_table().unwrap();
+    assert_eq!(
+        Some(TypedValue::Bool(true)),
+        root.get("enabled").unwrap().typed()
+    );
+    assert_eq!(
+        Some(TypedValue::Vector([0.0, -1.5, 24.0])),
+        root.get("origin").unwrap().typed()
+    );
+    assert_eq!(Some(TypedValue::Int(3)), root.get("count").unwrap().typed());
+    assert_eq!(Some("[0 -1.5 24]"), root.get("origin").unwrap().as_str());
+    let typed = TypedTable::load_from_str(source).unwrap();
+    let root = typed.get("root").unwrap().as_table().unwrap();
+    assert_eq!(
+        Some(TypedValue::String),
+        root.get("enabled").unwrap().typed()
+    );
+
+    let table = Table::load_from_str(
+        "color \"255 128 0\"\nscale 1.5\nenabled 1\nid 76561197960265728\nname test",
+    )
+    .unwrap();
+    let get = |key| table.get(key).unwrap();
+    assert_eq!(Some([255, 128, 0, 255]), get("color").as_color());
+    assert_eq!(Some(1.5), get("scale").as_f32());
+    assert_eq!(Some(true), get("enabled").as_bool());
+    assert_eq!(Some(76561197960265728), get("id").as_i64());
+    assert_eq!(Some(TypedValue::String), get("id").typed());
+    assert_eq!(None, get("name").as_bool());
+    assert_eq!(None, get("name").as_color());
+}
+
+#[test_case("tests/errors/novalue.vdf")]
+#[test_case("tests/errors/recover.vdf")]
+fn test_load_recovering(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let (table, errors) = Table::load_recovering(&raw, &LoadOptions::default());
+    assert!(Table::load_from_str(&raw).is_err());
+
+    let handler = GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor());
+    let mut out = String::new();
+    handler
+        .render_report(&mut out, &ParseErrors::from(errors))
+        .unwrap();
+    insta::assert_ron_snapshot!(format!("recovered__{}", path), table);
+    insta::assert_snapshot!(format!("recovered_errors__{}", path), out);
+}
+
+#[test]
+fn test_write_options() {
+    let parsed =
+        Table::load_from_str("group {\n key value\n \"longer key\" \"with space\"\n}").unwrap();
+    let options = WriterOptions::default()
+        .with_indent(IndentStyle::Spaces(2))
+        .with_quote(QuoteStyle::WhenNeeded)
+        .with_align_values(true);
+    let mut written = String::new();
+    parsed.write_to(&mut written, &options).unwrap();
+    assert_eq!(
+        "group\n{\n  key          value\n  \"longer key\" \"with space\"\n}\n",
+        written
+    );
+}
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/messy.vdf")]
+#[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
+#[test_case("tests/data/toplevel.vdf")]
+#[test_case("tests/data/serde_array_type.vdf")]
+#[test_case("tests/data/game_text.vmt")]
+#[test_case("tests/data/conditional.res")]
+fn test_cst_lossless(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let document = Document::parse(&raw)
+        .map_err(miette::Error::from)
+        .expect("failed to parse test data");
+    assert_eq!(raw, document.to_string());
+    assert_eq!(
+        Table::load_from_str(&raw).unwrap(),
+        document.to_table().unwrap()
+    );
+}
+
+#[test]
+fn test_write_keeps_layout() {
+    let raw = read_to_string("tests/data/DialogConfigOverlay_1280x720.vdf").unwrap();
+    let parsed = Table::load_from_str(&raw).unwrap();
+    assert_eq!(raw, parsed.to_string());
+}
+
+#[test]
+fn test_conditions() {
+    let raw = read_to_string("tests/data/conditional.res").unwrap();
+
+    let kept = Table::load_from_str(&raw).unwrap();
+    let label = kept
+        .get("Resource/UI/MainMenu.res")
+        .and_then(|menu| menu.get("TitleLabel"))
+        .and_then(Entry::as_table)
+        .unwrap();
+    assert_eq!(2, label.get_all("xpos").count());
+    assert_eq!(Some(&Condition::new("[$WIN32]")), label.condition("xpos"));
+
+    let options = LoadOptions::default().with_conditions(ConditionMode::Evaluate(
+        ["$WIN32", "$WINDOWS"].into_iter().collect::<Defines>(),
+    ));
+    let evaluated = Table::load_from_str_with_options(&raw, &options).unwrap();
+    let menu = evaluated
+        .get("Resource/UI/MainMenu.res")
+        .and_then(Entry::as_table)
+        .unwrap();
+    let label = menu.get("TitleLabel").and_then(Entry::as_table).unwrap();
+    assert_eq!(
+        vec![Some("10")],
+        label
+            .get_all("xpos")
+            .map(|entry| entry.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some("HudFontSmall"),
+        label.get("font").and_then(Entry::as_str)
+    );
+    assert_eq!(None, label.condition("xpos"));
+    assert!(!menu.contains_key("ConsoleOnly"));
+    assert!(menu.contains_key("DesktopOnly"));
+}
+
+#[test]
+fn test_includes() {
+    let resolver = FileResolver::new("tests/data/include");
+    let options = LoadOptions::default();
+
+    let table = Table::load_with_includes(
+        "tests/data/include/resource/ui/main.res",
+        &resolver,
+        &options,
+    )
+    .map_err(miette::Error::from)
+    .unwrap();
+    let layout = table
+        .get("Resource/HudLayout.res")
+        .and_then(Entry::as_table)
+        .unwrap();
+    assert!(!table.contains_key("#base"));
+
+    let health = layout.get("HudHealth").and_then(Entry::as_table).unwrap();
+    assert_eq!(Some("15"), health.get("xpos").and_then(Entry::as_str));
+    assert_eq!(Some("20"), health.get("ypos").and_then(Entry::as_str));
+    assert_eq!(Some("100"), health.get("wide").and_then(Entry::as_str));
+
+    let ammo = layout.get("HudAmmo").and_then(Entry::as_table).unwrap();
+    assert_eq!(Some("40"), ammo.get("xpos").and_then(Entry::as_str));
+    assert_eq!(Some("1"), ammo.get("visible").and_then(Entry::as_str));
+}
+
+#[test]
+fn test_include_encoding() {
+    let resolver = FileResolver::new("tests/data/include");
+    let path = "tests/data/include/resource/ui/latin1.res";
+    assert!(Table::load_with_includes(path, &resolver, &LoadOptions::default()).is_err());
+
+    let options = LoadOptions::default().with_encoding(Encoding::Windows1252);
+    let table = Table::load_with_includes(path, &resolver, &options).unwrap();
+    assert_eq!(Some("Café"), table.get("title").and_then(Entry::as_str));
+}
+
+#[test]
+fn test_include_spans() {
+    let resolver = FileResolver::new("tests/data/include");
+    let options = LoadOptions::default().with_spans(true);
+
+    let table = Table::load_with_includes(
+        "tests/data/include/resource/ui/main.res",
+        &resolver,
+        &options,
+    )
+    .unwrap();
+    let layout = table.get("Resource/HudLayout.res").unwrap();
+    let location = layout.lookup("HudHealth.xpos").unwrap().location().unwrap();
+    assert!(location.path().unwrap().ends_with("hudlayout_base.res"));
+    assert_eq!("\"15\"", &location.source()[location.span()]);
+    let location = layout.lookup("HudAmmo.xpos").unwrap().location().unwrap();
+    assert!(location.path().unwrap().ends_with("overrides.res"));
+}
+
+#[test_case("cycle_a.res", "cycle_b.res", "includes itself")]
+#[test_case("escape.res", "escape.res", "outside of the allowed directories")]
+#[test_case("missing.res", "missing.res", "does_not_exist.res")]
+fn test_include_errors(file: &str, failing: &str, message: &str) {
+    let resolver = FileResolver::new("tests/data/include");
+    let error = Table::load_with_includes(
+        format!("tests/data/include/resource/ui/{file}"),
+        &resolver,
+        &LoadOptions::default(),
+    )
+    .unwrap_err();
+    let VdfError::Include(error) = &error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert!(error.file.ends_with(failing), "{error}");
+    assert!(error.to_string().contains(message), "{error}");
+}
+
+#[test]
+fn test_include_depth() {
+    let resolver = FileResolver::new("tests/data/include");
+    let options = LoadOptions::default().with_max_include_depth(1);
+    let error = Table::load_with_includes(
+        "tests/data/include/resource/ui/main.res",
+        &resolver,
+        &options,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("nested includes"), "{error}");
+}
+
+#[test]
+fn test_escape_modes() {
+    let source = r#""path" "C:\test\no newline"
+"text" "tab\there \"quoted\"""#;
+
+    let load = |escape| {
+        Table::load_from_str_with_options(source, &LoadOptions::default().with_escape(escape))
+            .unwrap()
+    };
+    let quotes_only = load(EscapeMode::QuotesOnly);
+    assert_eq!(
+        Some(r#"C:\test\no newline"#),
+        quotes_only.get("path").and_then(Entry::as_str)
+    );
+    assert_eq!(
+        Some(r#"tab\there "quoted""#),
+        quotes_only.get("text").and_then(Entry::as_str)
+    );
+
+    let full = load(EscapeMode::Full);
+    assert_eq!(
+        Some("C:\test\no newline"),
+        full.get("path").and_then(Entry::as_str)
+    );
+    assert_eq!(
+        Some("tab\there \"quoted\""),
+        full.get("text").and_then(Entry::as_str)
+    );
+
+    // like `UsesEscapeSequences=false`, a raw string ends at the first quote
+    let raw = Table::load_from_str_with_options(
+        "\"path\" \"C:\\dir\\\"\n\"next\" \"1\"",
+        &LoadOptions::default().with_escape(EscapeMode::Raw),
+    )
+    .unwrap();
+    assert_eq!(Some(r"C:\dir\"), raw.get("path").and_then(Entry::as_str));
+    assert_eq!(Some("1"), raw.get("next").and_then(Entry::as_str));
+
+    for (escape, table) in [
+        (EscapeMode::QuotesOnly, quotes_only),
+        (EscapeMode::Full, full),
+    ] {
+        let mut written = String::new();
+        table
+            .write_to(&mut written, &WriterOptions::default().with_escape(escape))
+            .unwrap();
+        let mut reader = Reader::from(written.as_str()).with_escape_mode(escape);
+        assert_eq!(table, Table::load(&mut reader).unwrap(), "{written}");
+    }
+}
+
+#[test]
+fn test_case_insensitive() {
+    let source = r#"
+    "Resource/SpecificPanel.res"
+    {
+        "Empty"
+        {
+            "ControlName" "Panel"
+        }
+        "Visible" "1"
+        "VISIBLE" "0"
+    }
+    "#;
+
+    let table = Table::load_from_str(source).unwrap();
+    let root = Entry::Table(table.clone());
+    assert!(root
+        .lookup("Resource/SpecificPanel.res.Empty.ControlName")
+        .is_some());
+    assert!(root.lookup("resource/specificpanel.res.empty").is_none());
+
+    let options = LoadOptions::default().with_case_insensitive(true);
+    let table = Table::load_from_str_with_options(source, &options).unwrap();
+    assert!(table.is_case_insensitive());
+    let root = Entry::Table(table.clone());
+    assert_eq!(
+        Some("Panel"),
+        root.lookup("resource/specificpanel.res.empty.controlname")
+            .and_then(Entry::as_str)
+    );
+
+    // keys that only differ by case are combined, keeping the first spelling
+    let panel = root.lookup("RESOURCE/SPECIFICPANEL.RES").unwrap();
+    let panel = panel.as_table().unwrap();
+    assert_eq!(vec!["Empty", "Visible"], panel.keys().collect::<Vec<_>>());
+    assert_eq!(2, panel.get("visible").unwrap().as_slice().unwrap().len());
+    assert!(table.to_string().contains("\"Resource/SpecificPanel.res\""));
+
+    // the mode can be set after loading as well
+    let table = Table::load_from_str(source)
+        .unwrap()
+        .with_case_insensitive(true);
+    assert!(Entry::Table(table)
+        .lookup("resource/specificpanel.res.empty")
+        .is_some());
+}
+
+#[test]
+fn test_load_bytes() {
+    let raw = read_to_string("tests/data/concrete.vmt").unwrap();
+    let expected = Table::load_from_str(&raw).unwrap();
+
+    let mut utf8 = b"\xEF\xBB\xBF".to_vec();
+    utf8.extend(raw.as_bytes());
+    assert_eq!(expected, Table::load_bytes(&utf8).unwrap());
+
+    let mut utf16 = vec![0xFF, 0xFE];
+    utf16.extend(raw.encode_utf16().flat_map(u16::to_le_bytes));
+    assert_eq!(expected, Table::load_bytes(&utf16).unwrap());
+
+    let latin = b"\"lang\" { \"caf\xE9\" \"\x93quoted\x94\" }";
+    let options = LoadOptions::default().with_encoding(Encoding::Windows1252);
+    let table = Table::load_bytes_with_options(latin, &options).unwrap();
+    let lang = table.get("lang").and_then(Entry::as_table).unwrap();
+    assert_eq!(
+        Some("\u{201C}quoted\u{201D}"),
+        lang.get("café").and_then(Entry::as_str)
+    );
+    assert!(matches!(
+        Table::load_bytes(latin),
+        Err(VdfError::Encoding(error)) if error.offset == 13
+    ));
+}
+
+#[test]
+fn test_decoded_spans() {
+    let text = "\"Tokens\"\n{\n\t\"key\"\n}\n";
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    let decoded = DecodedText::decode(&bytes, Encoding::Utf8).unwrap();
+    let error = Table::load(&mut Reader::from(&decoded)).unwrap_err();
+    let span = error.span().unwrap();
+    let span = span.offset()..span.offset() + span.len();
+    assert_eq!("}", &text[span.clone()]);
+    assert_eq!(2 + 2 * span.start, decoded.byte_span(span).start);
+}
+
+#[test]
+fn test_localization() {
+    let load = |name: &str| {
+        let bytes = std::fs::read(format!("tests/data/localization/{name}")).unwrap();
+        Localization::load_bytes(&bytes).unwrap()
+    };
+    let english = load("mod_english.txt");
+    let mut german = load("mod_german.txt");
+    assert_eq!("German", german.language());
+    assert_eq!(Some("Spielen"), german.get("#MOD_Play"));
+    assert_eq!(None, german.get("MOD_Credits"));
+
+    german.merge(load("mod_german_extra.txt"));
+    assert_eq!(Some("Mitwirkende"), german.get("MOD_Credits"));
+
+    assert_eq!(
+        LocalizationReport {
+            missing: vec![],
+            extra: vec!["MOD_Removed".into()],
+            outdated: vec!["MOD_Options".into()],
+        },
+        german.compare(&english)
+    );
+
+    let x360 = Defines::from_iter(["X360"]);
+    let german = german.with_fallback(english);
+    assert_eq!(Some("Beenden"), german.get("MOD_Quit"));
+    assert_eq!(Some("Exit"), german.get_for("MOD_Quit", &x360));
+    assert_eq!(None, german.get("MOD_Unknown"));
+
+    let mut partial = Localization::new("French").with_fallback(load("mod_english.txt"));
+    assert_eq!(Some("Play"), partial.get("MOD_Play"));
+    assert_eq!(4, partial.compare(&load("mod_english.txt")).missing.len());
+    partial.merge(load("mod_german.txt"));
+    assert_eq!(Some("Spielen"), partial.get("MOD_Play"));
+}
@@ -5,8 +5,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs::read_to_string;
 use test_case::test_case;
-use vdf_reader::entry::Table;
-use vdf_reader::{from_entry, from_str};
+use vdf_reader::entry::{ConditionMode, LoadOptions, Table};
+use vdf_reader::{
+    from_entry, from_entry_with_options, from_str, from_str_with_options, to_string,
+    to_string_with_options, to_writer_with_options, BoolInference, Defines, Deserializer,
+    DeserializerOptions, EscapeMode, VdfError, WriterOptions,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -255,3 +259,321 @@ fn test_serde_from_table(path: &str) {
     let material: Expected = from_entry(result.into()).expect("table to material");
     insta::assert_ron_snapshot!(format!("table_to_material__{}", path), material);
 }
+
+#[test_case("tests/errors/concrete.vmt")]
+#[test_case("tests/errors/serde_array_type.vdf")]
+fn test_serde_from_spanned_table(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let options = LoadOptions::default().with_spans(true);
+    let table = Table::load_from_str_with_options(&raw, &options).unwrap();
+
+    let e = from_entry::<Expected>(table.into()).unwrap_err();
+    let handler = GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor());
+    let mut out = String::new();
+    handler.render_report(&mut out, &e).unwrap();
+    insta::assert_snapshot!(format!("spanned__{}", path), out)
+}
+
+#[test]
+fn test_spanned_entry() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Mode {
+        Fast,
+        Slow,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Settings {
+        mode: Mode,
+        size: u8,
+    }
+
+    let source = "settings\n{\n\tmode \"Medium\"\n\tsize 1\n}\n";
+    let options = LoadOptions::default().with_spans(true);
+    let table = Table::load_from_str_with_options(source, &options).unwrap();
+    let settings = table.get("settings").unwrap();
+    assert_eq!(Some(9..35), settings.span());
+    let mode = settings.get("mode").unwrap();
+    assert_eq!(Some(17..25), mode.span());
+    assert_eq!(None, mode.location().unwrap().path());
+
+    let e = from_entry::<BTreeMap<String, Settings>>(table.clone().into()).unwrap_err();
+    assert_eq!(Some((17..25).into()), e.span());
+    assert_eq!(Some(source), e.source());
+
+    let e = VdfError::from(mode.clone().to::<u8>().unwrap_err());
+    assert_eq!(Some((17..25).into()), e.span());
+
+    // without spans the errors don't point anywhere
+    let table = Table::load_from_str(source).unwrap();
+    assert_eq!(None, table.get("settings").unwrap().span());
+    let e = from_entry::<BTreeMap<String, Settings>>(table.into()).unwrap_err();
+    assert_eq!(None, e.span());
+}
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/messy.vdf")]
+#[test_case("tests/data/DialogConfigOverlay_1280x720.vdf")]
+#[test_case("tests/data/serde_array_type.vdf")]
+#[test_case("tests/data/game_text.vmt")]
+#[test_case("tests/data/enuminmap.vdf")]
+fn test_serde_roundtrip(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let parsed: Expected = from_str(&raw).unwrap();
+    let serialized = to_string(&parsed).expect("serialize");
+    let reparsed: Expected = from_str(&serialized)
+        .map_err(miette::Error::from)
+        .expect("parse serialized");
+    assert_eq!(serialized, to_string(&reparsed).unwrap());
+    insta::assert_snapshot!(format!("serialized__{}", path), serialized);
+}
+
+#[test]
+fn test_serde_escape_mode() {
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Text {
+        text: String,
+    }
+
+    let source = r#"text "line\nbreak \"quoted\"""#;
+    let quotes_only: Text = from_str(source).unwrap();
+    assert_eq!(r#"line\nbreak "quoted""#, quotes_only.text);
+
+    let mut deserializer = Deserializer::from_str(source).with_escape_mode(EscapeMode::Full);
+    let full = Text::deserialize(&mut deserializer).unwrap();
+    assert_eq!("line\nbreak \"quoted\"", full.text);
+
+    // a raw string ends at the first quote, even after a backslash
+    let mut deserializer =
+        Deserializer::from_str("text \"C:\\dir\\\"\nnext 1").with_escape_mode(EscapeMode::Raw);
+    let raw = Text::deserialize(&mut deserializer).unwrap();
+    assert_eq!(r"C:\dir\", raw.text);
+
+    let options = WriterOptions::default().with_escape(EscapeMode::Full);
+    let written = to_string_with_options(&full, &options).unwrap();
+    let mut deserializer = Deserializer::from_str(&written).with_escape_mode(EscapeMode::Full);
+    assert_eq!(full, Text::deserialize(&mut deserializer).unwrap());
+
+    let options = WriterOptions::default().with_escape(EscapeMode::Raw);
+    let written = to_string_with_options(&raw, &options).unwrap();
+    let mut deserializer = Deserializer::from_str(&written).with_escape_mode(EscapeMode::Raw);
+    assert_eq!(raw, Text::deserialize(&mut deserializer).unwrap());
+    assert!(to_string_with_options(&quotes_only, &options).is_err());
+
+    let mut out = Vec::new();
+    to_writer_with_options(&mut out, &raw, &options).unwrap();
+    assert_eq!(written.as_bytes(), out);
+    assert!(to_writer_with_options(Vec::new(), &quotes_only, &options).is_err());
+}
+
+#[test]
+fn test_serde_many_conditions() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Text {
+        text: String,
+    }
+
+    let source = format!("text value {}", "[$WIN32] ".repeat(1_000_000));
+    let text: Text = from_str(&source).unwrap();
+    assert_eq!("value", text.text);
+}
+
+#[test]
+fn test_evaluate_deep_condition() {
+    let source = format!(
+        "a 1 [$A&&{}$B]
+b 2",
+        "!".repeat(1_000_000)
+    );
+    let defines = Defines::from_iter(["A"]);
+    let options = LoadOptions::default().with_conditions(ConditionMode::Evaluate(defines));
+    let table = Table::load_from_str_with_options(&source, &options).unwrap();
+    assert_eq!(None, table.get("a"));
+    assert!(table.get("b").is_some());
+
+    let source = format!("a 1 [$A&&{}$B]", "!".repeat(999_999));
+    let table = Table::load_from_str_with_options(&source, &options).unwrap();
+    assert_eq!(Some("1"), table.get("a").and_then(|a| a.as_str()));
+}
+
+#[test]
+fn test_serde_interleaved_keys() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Wave {
+        #[serde(rename = "WaveSpawn")]
+        spawns: Vec<Spawn>,
+        #[serde(rename = "Checkpoint")]
+        checkpoint: String,
+        #[serde(rename = "Sound")]
+        sounds: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Spawn {
+        #[serde(rename = "Name")]
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Population {
+        #[serde(rename = "Wave")]
+        waves: Vec<Wave>,
+    }
+
+    let source = r#"
+    Wave
+    {
+        Sound "start.wav"
+        WaveSpawn { Name first }
+        Checkpoint Yes
+        WaveSpawn
+        {
+            Name second
+        }
+        Sound "end.wav"
+    }
+    Wave
+    {
+        Checkpoint No
+        WaveSpawn { Name third }
+        Sound none
+    }
+    "#;
+    let expected = Population {
+        waves: vec![
+            Wave {
+                spawns: vec![
+                    Spawn {
+                        name: "first".into(),
+                    },
+                    Spawn {
+                        name: "second".into(),
+                    },
+                ],
+                checkpoint: "Yes".into(),
+                sounds: vec!["start.wav".into(), "end.wav".into()],
+            },
+            Wave {
+                spawns: vec![Spawn {
+                    name: "third".into(),
+                }],
+                checkpoint: "No".into(),
+                sounds: vec!["none".into()],
+            },
+        ],
+    };
+    assert_eq!(expected, from_str::<Population>(source).unwrap());
+
+    let table = Table::load_from_str(source).unwrap();
+    assert_eq!(expected, from_entry::<Population>(table.into()).unwrap());
+
+    let wrapped = format!("population {{ {source} }}");
+    let wrapped: BTreeMap<String, Population> = from_str(&wrapped).unwrap();
+    assert_eq!(expected, wrapped["population"]);
+
+    let map: BTreeMap<String, Vec<u8>> = from_str("b 1\nb 2\na 3\nb 4").unwrap();
+    assert_eq!(vec![1, 2, 4], map["b"]);
+    assert_eq!(vec![3], map["a"]);
+    let map: BTreeMap<String, Vec<u8>> = from_str("b 1\na 2\nb 3").unwrap();
+    assert_eq!(vec![1, 3], map["b"]);
+    assert_eq!(vec![2], map["a"]);
+
+    // tables keep the document order
+    let table: Table = from_str("b 1\na 2\nb 3").unwrap();
+    let entries: Vec<_> = table
+        .iter()
+        .map(|(key, value)| (key, value.as_str().unwrap()))
+        .collect();
+    assert_eq!(vec![("b", "1"), ("a", "2"), ("b", "3")], entries);
+}
+
+#[test]
+fn test_serde_inference_options() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    enum Setting {
+        Flag(bool),
+        Number(i64),
+        Float(f64),
+        Text(String),
+    }
+
+    let source = r#"
+    enabled true
+    count 0x10
+    id 007
+    version 1e5
+    "#;
+    let options = DeserializerOptions::default()
+        .with_bools(BoolInference::TrueFalse)
+        .with_keep_leading_zeros(true)
+        .with_hex(true)
+        .with_exponents(false);
+    let expected: BTreeMap<String, Setting> = [
+        ("enabled".into(), Setting::Flag(true)),
+        ("count".into(), Setting::Number(16)),
+        ("id".into(), Setting::Text("007".into())),
+        ("version".into(), Setting::Text("1e5".into())),
+    ]
+    .into_iter()
+    .collect();
+
+    let parsed: BTreeMap<String, Setting> = from_str_with_options(source, &options).unwrap();
+    assert_eq!(expected, parsed);
+
+    let table = Table::load_from_str(source).unwrap();
+    let parsed: BTreeMap<String, Setting> =
+        from_entry_with_options(table.into(), &options).unwrap();
+    assert_eq!(expected, parsed);
+
+    let parsed: BTreeMap<String, Setting> = from_str(source).unwrap();
+    assert_eq!(Setting::Text("true".into()), parsed["enabled"]);
+    assert_eq!(Setting::Number(7), parsed["id"]);
+    assert_eq!(Setting::Float(1e5), parsed["version"]);
+}
+
+#[test]
+fn test_serde_case_insensitive() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Material {
+        LightmappedGeneric(Parameters),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Parameters {
+        #[serde(rename = "$basetexture")]
+        base_texture: String,
+        #[serde(rename = "$surfaceprop")]
+        surface_prop: Option<String>,
+        #[serde(rename = "Proxies", default)]
+        proxies: Vec<Table>,
+    }
+
+    let source = r#"
+    "lightmappedgeneric"
+    {
+        "$baseTexture" "concrete/wall"
+        proxies { Sine {} }
+        "$SurfaceProp" "concrete"
+        PROXIES { AnimatedTexture {} }
+    }
+    "#;
+    let options = DeserializerOptions::default().with_case_insensitive(true);
+
+    let material: Material = from_str_with_options(source, &options).unwrap();
+    let Material::LightmappedGeneric(parameters) = &material;
+    assert_eq!("concrete/wall", parameters.base_texture);
+    assert_eq!(Some("concrete"), parameters.surface_prop.as_deref());
+    assert_eq!(2, parameters.proxies.len());
+
+    let table = Table::load_from_str(source).unwrap();
+    assert_eq!(
+        material,
+        from_entry_with_options(table.clone().into(), &options).unwrap()
+    );
+
+    assert!(from_str::<Material>(source).is_err());
+    assert!(from_entry::<Material>(table.into()).is_err());
+}
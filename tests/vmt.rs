@@ -0,0 +1,156 @@
+use std::fs::read_to_string;
+use test_case::test_case;
+use vdf_reader::vmt::{Material, ParameterValue, TextureTransform};
+use vdf_reader::Defines;
+
+#[test_case("tests/data/concrete.vmt")]
+#[test_case("tests/data/game_text.vmt")]
+#[test_case("tests/data/vmt/crate.vmt")]
+fn test_vmt_roundtrip(path: &str) {
+    let raw = read_to_string(path).unwrap();
+    let material = Material::load_from_str(&raw).unwrap();
+    let written = material.to_string();
+    insta::assert_snapshot!(path, written);
+    assert_eq!(material, Material::load_from_str(&written).unwrap());
+}
+
+#[test]
+fn test_vmt_parameters() {
+    let raw = read_to_string("tests/data/vmt/crate.vmt").unwrap();
+    let material = Material::load_from_str(&raw).unwrap();
+    assert_eq!("VertexLitGeneric", material.shader);
+    assert_eq!(
+        Some("models/props/crate".into()),
+        material
+            .get("$basetexture")
+            .and_then(ParameterValue::as_texture)
+    );
+    assert_eq!(
+        Some([1.0, 0.5, 0.5]),
+        material.get("$COLOR2").and_then(ParameterValue::as_color)
+    );
+    assert_eq!(
+        Some([1.0, 128.0 / 255.0, 0.0]),
+        material.get("$color").and_then(ParameterValue::as_color)
+    );
+    assert_eq!(
+        Some(&TextureTransform {
+            scale: [2.0, 2.0],
+            rotate: 90.0,
+            ..TextureTransform::default()
+        }),
+        material
+            .get("$basetexturetransform")
+            .and_then(ParameterValue::as_transform)
+    );
+    assert_eq!(
+        Some(20),
+        material
+            .get("$phongexponent")
+            .and_then(ParameterValue::as_int)
+    );
+    assert_eq!(
+        Some(true),
+        material.get("$phong").and_then(ParameterValue::as_bool)
+    );
+
+    let envmaps: Vec<_> = material
+        .parameters
+        .iter()
+        .filter(|parameter| parameter.name == "$envmap")
+        .collect();
+    assert_eq!(2, envmaps.len());
+    let x360 = Defines::from_iter(["X360"]);
+    assert!(envmaps[1].condition.as_ref().unwrap().evaluate(&x360));
+
+    let dx80 = material.fallback("vertexlitgeneric_dx80").unwrap();
+    assert_eq!(
+        Some(false),
+        dx80.get("$phong").and_then(ParameterValue::as_bool)
+    );
+    assert!(material
+        .fallback(">=dx90")
+        .unwrap()
+        .get("$bumpmap")
+        .is_some());
+
+    assert_eq!(2, material.proxies.len());
+    let sine = material.proxy("sine").unwrap();
+    assert_eq!(
+        Some(8),
+        sine.parameters
+            .get("SinePeriod")
+            .and_then(ParameterValue::as_int)
+    );
+    assert_eq!(
+        Some("$color2[0]"),
+        sine.parameters
+            .get("resultVar")
+            .and_then(ParameterValue::as_str)
+    );
+}
+
+#[test]
+fn test_vmt_edit() {
+    let mut material = Material::load_from_str(r#""UnlitGeneric" { "$baseTexture" "a" }"#).unwrap();
+    assert_eq!(
+        Some(ParameterValue::from("a")),
+        material.parameters.insert("$BASETEXTURE", "b")
+    );
+    material.parameters.insert("$alpha", 0.5);
+    assert_eq!(
+        "\"UnlitGeneric\"\n{\n\t\"$baseTexture\"\t\t\"b\"\n\t\"$alpha\"\t\t\"0.5\"\n}\n",
+        material.to_string()
+    );
+    assert!(Material::load_from_str(r#""$basetexture" "a""#).is_err());
+}
+
+#[test]
+fn test_vmt_group_conditions() {
+    let raw = r#"
+    "UnlitGeneric"
+    {
+        "$basetexture" "a"
+        ">=dx90" [$WIN32]
+        {
+            "$basetexture" "b"
+        }
+        "Proxies" [!$X360]
+        {
+            "Sine" [$WIN32]
+            {
+                "sineperiod" "8"
+            }
+            "AnimatedTexture"
+            {
+            }
+        }
+    }
+    "#;
+    let material = Material::load_from_str(raw).unwrap();
+    let dx90 = material.fallback(">=dx90").unwrap();
+    assert_eq!(
+        Some("$WIN32"),
+        dx90.condition.as_ref().map(|c| c.expression())
+    );
+
+    assert_eq!(
+        Some("!$X360"),
+        material.proxies_condition.as_ref().map(|c| c.expression())
+    );
+    let sine = material.proxy("Sine").unwrap();
+    assert_eq!(
+        Some("$WIN32"),
+        sine.condition.as_ref().map(|c| c.expression())
+    );
+    assert!(material
+        .proxy("AnimatedTexture")
+        .unwrap()
+        .condition
+        .is_none());
+
+    let written = material.to_string();
+    assert!(written.contains("\">=dx90\" [$WIN32]"), "{written}");
+    assert!(written.contains("\"Proxies\" [!$X360]"), "{written}");
+    assert_eq!(material, Material::load_from_str(&written).unwrap());
+}